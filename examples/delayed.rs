@@ -1,14 +1,10 @@
 #![feature(async_await)]
 #![feature(await_macro)]
-// The next 3 are required for the sleeper module
-#![feature(arbitrary_self_types)]
 #![feature(futures_api)]
-#![feature(pin)]
 
 #[macro_use] extern crate alligator;
 
-mod sleeper;
-
+use alligator::time;
 use std::time::Duration;
 
 /// There is nothing asynchronous about this 'async' function. Anything that polls the return of
@@ -17,10 +13,13 @@ async fn not_actually_async() -> String {
     String::from("This message is not actually asychronous")
 }
 
-/// The sleeper object just takes a message and a duration for when to return the message.
+/// The sleep future of alligator's timer completes after the given duration, then the message is
+/// returned.
 async fn basic_async<T: Into<String>>(sleep_time: Duration, message: T) -> String {
 
-    await!(sleeper::Sleeper::new( sleep_time, message.into()))
+    await!(time::sleep(sleep_time));
+
+    message.into()
 }
 
 
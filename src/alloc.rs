@@ -0,0 +1,107 @@
+//! Boxing the future of a `Later` in memory from an allocator of the caller's choosing
+
+use std::alloc::{handle_alloc_error, Alloc, Layout};
+use std::future::Future;
+use std::pin::Pin;
+use std::ptr::{self, NonNull};
+use std::task::{Poll, LocalWaker};
+
+use super::Later;
+
+/// A future boxed in memory from the allocator `A`
+///
+/// This is the boxed future of a [`DynLaterIn`](./type.DynLaterIn.html). Like the boxed future of a
+/// `DynLater` the type of the future is erased, but the memory of the future comes from `A` (and
+/// is given back to `A` when the future is dropped) instead of from the global allocator. The
+/// future is never moved out of its memory, so it's always pinned.
+pub struct BoxedIn<O, A> where A: Alloc {
+    future: NonNull<dyn Future<Output=O> + Send>,
+    layout: Layout,
+    alloc: A,
+}
+
+// The future is `Send`, and the pointer is owned by the box
+unsafe impl<O, A> Send for BoxedIn<O, A> where A: Alloc + Send {}
+
+impl<O, A> BoxedIn<O, A> where A: Alloc {
+
+    /// Move `future` into memory allocated by `alloc`
+    ///
+    /// This calls `std::alloc::handle_alloc_error` if the allocator fails.
+    pub fn new<T>(future: T, mut alloc: A) -> Self where T: Future<Output=O> + Send + 'static {
+        let layout = Layout::new::<T>();
+
+        let memory = if layout.size() == 0 {
+            NonNull::<T>::dangling()
+        } else {
+            match unsafe { alloc.alloc(layout) } {
+                Ok(memory) => memory.cast::<T>(),
+                Err(_) => handle_alloc_error(layout),
+            }
+        };
+
+        unsafe { ptr::write(memory.as_ptr(), future) };
+
+        let future: *mut (dyn Future<Output=O> + Send) = memory.as_ptr();
+
+        BoxedIn {
+            future: unsafe { NonNull::new_unchecked(future) },
+            layout: layout,
+            alloc: alloc,
+        }
+    }
+}
+
+impl<O, A> Future for BoxedIn<O, A> where A: Alloc {
+    type Output = O;
+
+    fn poll(self: Pin<&mut Self>, lw: &LocalWaker) -> Poll<O> {
+        let this = unsafe { Pin::get_mut_unchecked(self) };
+
+        unsafe { Pin::new_unchecked(&mut *this.future.as_ptr()) }.poll(lw)
+    }
+}
+
+impl<O, A> Drop for BoxedIn<O, A> where A: Alloc {
+    fn drop(&mut self) {
+        unsafe {
+            ptr::drop_in_place(self.future.as_ptr());
+
+            if self.layout.size() != 0 {
+                self.alloc.dealloc(self.future.cast::<u8>(), self.layout);
+            }
+        }
+    }
+}
+
+/// A `Later` of a future boxed in memory from the allocator `A`
+///
+/// This is the allocator generic form of [`DynLater`](./type.DynLater.html), for embedded and arena
+/// based programs that have to control where the state of their futures lives.
+pub type DynLaterIn<O, A> = Later<BoxedIn<O, A>>;
+
+impl<O, A> Later<BoxedIn<O, A>> where A: Alloc {
+
+    /// Create a `Later` of `future` boxed in memory from `alloc`
+    ///
+    /// ```rust
+    /// # #![feature(futures_api)]
+    /// # #![feature(async_await)]
+    /// # #![feature(allocator_api)]
+    /// # extern crate alligator;
+    /// # use alligator::{DynLaterIn, Later};
+    /// # use std::alloc::Global;
+    /// # async fn from_disk() -> Vec<u8> { vec![1] }
+    /// # async fn from_network() -> Vec<u8> { vec![2] }
+    /// # fn main() {
+    /// let sources: Vec<DynLaterIn<Vec<u8>, Global>> = vec![
+    ///     Later::boxed_in(from_disk(), Global),
+    ///     Later::boxed_in(from_network(), Global),
+    /// ];
+    /// # assert_eq!(*sources[1], vec![2]);
+    /// # }
+    /// ```
+    pub fn boxed_in<T>(future: T, alloc: A) -> Self where T: Future<Output=O> + Send + 'static {
+        Later::new(BoxedIn::new(future, alloc))
+    }
+}
@@ -0,0 +1,329 @@
+//! Futures for values handed back by threads and channels
+
+use std::future::Future;
+use std::mem;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::sync::mpsc::{Receiver, RecvError, RecvTimeoutError, TryRecvError};
+use std::task::{Poll, LocalWaker, Waker};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+#[cfg(feature = "oneshot")]
+use futures::channel::oneshot;
+
+use super::Later;
+#[cfg(feature = "combinators")]
+use super::Waitable;
+
+/// Where a helper thread puts the value it waited on
+struct Slot<O> {
+    output: Option<O>,
+    waker: Option<Waker>,
+}
+
+impl<O> Slot<O> {
+    fn new() -> Arc<Mutex<Self>> {
+        Arc::new(Mutex::new(Slot { output: None, waker: None }))
+    }
+
+    fn complete(&mut self, output: O) {
+        self.output = Some(output);
+
+        if let Some(waker) = self.waker.take() {
+            waker.wake()
+        }
+    }
+
+    /// Take the output, or keep the waker to be woken with the output
+    fn take(&mut self, lw: &LocalWaker) -> Option<O> {
+        let output = self.output.take();
+
+        if output.is_none() {
+            self.waker = Some(lw.as_waker().clone());
+        }
+
+        output
+    }
+}
+
+/// How often a helper thread waiting on a channel checks if its `Later` was dropped
+///
+/// A `Receiver` can't be woken from `recv` by anything but a send or a disconnect, so this is the
+/// longest a helper thread is held after its `Later` is dropped. Every check is a wake up of the
+/// thread, which is the cost of every `Later` of a channel waiting with nothing sent.
+const ABANDON_CHECK_MILLIS: u64 = 100;
+
+/// Call `f` on a helper thread, the output of `f` is put in the returned slot
+///
+/// `f` is given a check for whether the future waiting on the slot was dropped, so that a wait
+/// that can be interrupted gives up (returning `None`) instead of holding the thread forever. The
+/// helper thread is one of the blocking threads of [`spawn_blocking`](../fn.spawn_blocking.html)
+/// when the `pool` feature is enabled.
+fn wait_on_thread<F,O>(f: F) -> Arc<Mutex<Slot<O>>>
+    where F: FnOnce(&dyn Fn() -> bool) -> Option<O> + Send + 'static,
+          O: Send + 'static,
+{
+    let slot = Slot::new();
+
+    let thread_slot = Arc::downgrade(&slot);
+
+    let wait = move || {
+        let output = f(&|| thread_slot.upgrade().is_none());
+
+        if let (Some(output), Some(slot)) = (output, thread_slot.upgrade()) {
+            slot.lock().unwrap().complete(output)
+        }
+    };
+
+    #[cfg(feature = "pool")]
+    super::pool::run_blocking(wait);
+
+    #[cfg(not(feature = "pool"))]
+    thread::Builder::new()
+        .name(String::from("alligator-bridge"))
+        .spawn(wait)
+        .expect("failed to spawn a helper thread");
+
+    slot
+}
+
+/// Receive from `receiver` on the helper thread of `wait_on_thread`, giving up once `abandoned`
+///
+/// The wait is a `recv_timeout` of `ABANDON_CHECK_MILLIS` in a loop rather than a single `recv`,
+/// so that the thread isn't held for good by a channel whose senders are kept but never send.
+fn receive<O>(receiver: Receiver<O>, abandoned: &dyn Fn() -> bool) -> Option<Result<O, RecvError>> {
+    loop {
+        match receiver.recv_timeout(Duration::from_millis(ABANDON_CHECK_MILLIS)) {
+            Ok(val) => return Some(Ok(val)),
+            Err(RecvTimeoutError::Disconnected) => return Some(Err(RecvError)),
+            Err(RecvTimeoutError::Timeout) => if abandoned() {
+                return None;
+            },
+        }
+    }
+}
+
+enum Receiving<O> {
+    Receiver(Receiver<O>),
+    Thread(Arc<Mutex<Slot<Result<O, RecvError>>>>),
+    /// Received by `Waitable::poll_ready` and kept for the next poll
+    Received(O),
+    Done,
+}
+
+/// The future of a `Later` created by [`Later::from_receiver`](./struct.Later.html#method.from_receiver)
+///
+/// # Panic
+/// Polling panics if the channel disconnected without a value being sent
+pub struct Received<O> {
+    state: Receiving<O>,
+}
+
+impl<O> Received<O> where O: Send + 'static {
+
+    /// Create a future of the next value received from a channel
+    ///
+    /// This is the future of [`Later::from_receiver`](./struct.Later.html#method.from_receiver)
+    /// without the `Later`, for waiting on a channel along with other waitables in
+    /// [`select`](./fn.select.html).
+    pub fn new(receiver: Receiver<O>) -> Self {
+        Received { state: Receiving::Receiver(receiver) }
+    }
+}
+
+/// Panic for a channel whose senders were all dropped without sending
+fn disconnected() -> ! {
+    panic!("the channel of a Later disconnected without a value being sent")
+}
+
+impl<O> Future for Received<O> where O: Send + 'static {
+    type Output = O;
+
+    fn poll(mut self: Pin<&mut Self>, lw: &LocalWaker) -> Poll<O> {
+        let slot = match mem::replace(&mut self.state, Receiving::Done) {
+            Receiving::Receiver(receiver) => match receiver.try_recv() {
+                Ok(val) => return Poll::Ready(val),
+                Err(TryRecvError::Disconnected) => disconnected(),
+                Err(TryRecvError::Empty) => wait_on_thread(move |abandoned| receive(receiver, abandoned)),
+            },
+            Receiving::Thread(slot) => slot,
+            Receiving::Received(val) => return Poll::Ready(val),
+            Receiving::Done => panic!("Received polled after completion"),
+        };
+
+        let output = slot.lock().unwrap().take(lw);
+
+        match output {
+            Some(Ok(val)) => Poll::Ready(val),
+            Some(Err(RecvError)) => disconnected(),
+            None => {
+                self.state = Receiving::Thread(slot);
+
+                Poll::Pending
+            },
+        }
+    }
+}
+
+/// A `Received` is ready once the value has been received, which is kept for the next poll
+///
+/// # Panic
+/// Polling for readiness panics if the channel disconnected without a value being sent
+#[cfg(feature = "combinators")]
+impl<O> Waitable for Received<O> where O: Send + 'static {
+    fn poll_ready(&mut self, lw: &LocalWaker) -> Poll<()> {
+        if let Receiving::Received(_) = self.state {
+            return Poll::Ready(());
+        }
+
+        match unsafe { Pin::new_unchecked(&mut *self) }.poll(lw) {
+            Poll::Ready(val) => {
+                self.state = Receiving::Received(val);
+
+                Poll::Ready(())
+            },
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+impl<O> Later<Received<O>> where O: Send + 'static {
+
+    /// Create a `Later` of the next value received from a channel
+    ///
+    /// Nothing is received until the output is first accessed. If the value hasn't been sent by
+    /// then, a helper thread is started to wait on the channel so that the `Later` can be waited
+    /// on like any other. If every sender is dropped without sending, accessing the output panics
+    /// instead of waiting forever.
+    ///
+    /// # Threads
+    /// Every `Later` that has to wait on its channel holds a thread for the wait. With the `pool`
+    /// feature this is one of the bounded blocking threads of
+    /// [`spawn_blocking`](./fn.spawn_blocking.html), so once they're all busy the wait is queued,
+    /// and otherwise it's a thread of its own. The thread is released when the value is received,
+    /// when every sender is dropped, or up to 100 milliseconds after the `Later` is dropped, and
+    /// until then it wakes up every 100 milliseconds to check whether the `Later` was dropped.
+    /// Prefer a single `Later` over many for a busy channel, and a oneshot channel (with the
+    /// `oneshot` feature) to a `Receiver` for a value that takes long to arrive, as it's woken by
+    /// the sender and doesn't need a thread at all.
+    ///
+    /// ```rust
+    /// # #![feature(futures_api)]
+    /// # extern crate alligator;
+    /// # use alligator::Later;
+    /// # use std::sync::mpsc::channel;
+    /// # fn main() {
+    /// let (sender, receiver) = channel();
+    ///
+    /// ::std::thread::spawn(move || sender.send(String::from("report")).unwrap());
+    ///
+    /// let report = Later::from_receiver(receiver);
+    ///
+    /// assert_eq!(*report, "report");
+    /// # }
+    /// ```
+    pub fn from_receiver(receiver: Receiver<O>) -> Self {
+        Later::unpolled(Received::new(receiver))
+    }
+}
+
+enum Joining<O> {
+    Handle(JoinHandle<O>),
+    Thread(Arc<Mutex<Slot<thread::Result<O>>>>),
+    Done,
+}
+
+/// The future of a `Later` created by
+/// [`Later::from_join_handle`](./struct.Later.html#method.from_join_handle)
+pub struct Joined<O> {
+    state: Joining<O>,
+}
+
+impl<O> Future for Joined<O> where O: Send + 'static {
+    type Output = thread::Result<O>;
+
+    fn poll(mut self: Pin<&mut Self>, lw: &LocalWaker) -> Poll<thread::Result<O>> {
+        let slot = match mem::replace(&mut self.state, Joining::Done) {
+            Joining::Handle(handle) => wait_on_thread(move |_| Some(handle.join())),
+            Joining::Thread(slot) => slot,
+            Joining::Done => panic!("Joined polled after completion"),
+        };
+
+        let output = slot.lock().unwrap().take(lw);
+
+        match output {
+            Some(output) => Poll::Ready(output),
+            None => {
+                self.state = Joining::Thread(slot);
+
+                Poll::Pending
+            },
+        }
+    }
+}
+
+impl<O> Later<Joined<O>> where O: Send + 'static {
+
+    /// Create a `Later` of the return of a thread
+    ///
+    /// The thread isn't joined until the output is first accessed, at which point a helper thread
+    /// is started to join it so that the `Later` can be waited on like any other. The output is
+    /// what `JoinHandle::join` returns, so if the thread panicked the output is an `Err` with the
+    /// panic payload instead of the `Later` panicking.
+    ///
+    /// # Threads
+    /// Joining holds a helper thread (one of the blocking threads of
+    /// [`spawn_blocking`](./fn.spawn_blocking.html) when the `pool` feature is enabled) until the
+    /// joined thread exits, even if the `Later` is dropped before then as a join can't be
+    /// interrupted.
+    ///
+    /// ```rust
+    /// # #![feature(futures_api)]
+    /// # extern crate alligator;
+    /// # use alligator::Later;
+    /// # fn main() {
+    /// let primes = Later::from_join_handle(::std::thread::spawn(|| {
+    ///     (2..100u32).filter(|n| (2..*n).all(|d| n % d != 0)).count()
+    /// }));
+    ///
+    /// let crashed = Later::from_join_handle(::std::thread::spawn(|| -> usize {
+    ///     panic!("out of memory")
+    /// }));
+    ///
+    /// assert_eq!(*primes.as_ref().unwrap(), 25);
+    /// assert!(crashed.is_err());
+    /// # }
+    /// ```
+    pub fn from_join_handle(handle: JoinHandle<O>) -> Self {
+        Later::unpolled(Joined { state: Joining::Handle(handle) })
+    }
+}
+
+#[cfg(feature = "oneshot")]
+impl<O> Later<oneshot::Receiver<O>> {
+
+    /// Create a `Later` of the value sent through a oneshot channel of the `futures` crate
+    ///
+    /// This is for asynchronous libraries that hand back the receiving half of a oneshot channel.
+    /// The output is what the receiver resolves to, so if the sender is dropped without sending
+    /// the output is `Err(Canceled)` instead of the `Later` waiting forever.
+    ///
+    /// ```rust
+    /// # #![feature(futures_api)]
+    /// # extern crate alligator;
+    /// # extern crate futures;
+    /// # use alligator::Later;
+    /// # use futures::channel::oneshot;
+    /// # fn main() {
+    /// let (sender, receiver) = oneshot::channel::<u32>();
+    ///
+    /// drop(sender);
+    ///
+    /// assert!(Later::from_oneshot(receiver).into_inner().is_err());
+    /// # }
+    /// ```
+    pub fn from_oneshot(receiver: oneshot::Receiver<O>) -> Self {
+        Later::new(receiver)
+    }
+}
@@ -0,0 +1,145 @@
+//! Cooperative cancellation
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::task::Waker;
+
+struct Inner {
+    cancelled: AtomicBool,
+    next_key: AtomicUsize,
+    wakers: Mutex<HashMap<usize, Waker>>,
+}
+
+/// A token for signalling cancellation
+///
+/// Clones of a token share the same state, so cancelling any clone cancels all of them. Anything
+/// waiting on the token (such as [`run_with_cancel`](../fn.run_with_cancel.html) or a `Later`
+/// created with [`Later::with_cancel`](../struct.Later.html#method.with_cancel)) is woken when it
+/// is cancelled.
+///
+/// # Example
+/// ```rust
+/// # #![feature(futures_api)]
+/// # extern crate alligator;
+/// # use alligator::{AccessError, CancellationToken, Later, Pending};
+/// # use std::thread;
+/// # use std::time::Duration;
+/// # fn main() {
+/// let shutdown = CancellationToken::new();
+///
+/// let reply = Later::with_cancel(Pending::<u32>::new(), &shutdown);
+///
+/// let signal = {
+///     let shutdown = shutdown.clone();
+///
+///     thread::spawn(move || {
+///         thread::sleep(Duration::from_millis(20));
+///
+///         shutdown.cancel();
+///     })
+/// };
+///
+/// // Blocks until the other thread cancels the token
+/// assert_eq!(*reply.try_force().unwrap_err().error(), AccessError::Cancelled);
+///
+/// signal.join().unwrap();
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct CancellationToken {
+    inner: Arc<Inner>,
+}
+
+impl CancellationToken {
+
+    /// Create a new token that isn't cancelled
+    pub fn new() -> Self {
+        CancellationToken {
+            inner: Arc::new( Inner {
+                cancelled: AtomicBool::new(false),
+                next_key: AtomicUsize::new(0),
+                wakers: Mutex::new(HashMap::new()),
+            })
+        }
+    }
+
+    /// Cancel the token
+    ///
+    /// This wakes everything waiting on the token. Cancelling a token that is already cancelled
+    /// does nothing.
+    pub fn cancel(&self) {
+        if !self.inner.cancelled.swap(true, Ordering::AcqRel) {
+            let wakers: Vec<Waker> = self.inner.wakers.lock().unwrap().drain().map(|(_, w)| w).collect();
+
+            for waker in wakers {
+                waker.wake()
+            }
+        }
+    }
+
+    /// Check if the token is cancelled
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.cancelled.load(Ordering::Acquire)
+    }
+
+    /// Register a waker to be woken when the token is cancelled
+    ///
+    /// The returned key is for removing the waker with `unregister`. If the token is already
+    /// cancelled the waker is woken right away.
+    pub(crate) fn register(&self, waker: Waker) -> usize {
+        let key = self.inner.next_key.fetch_add(1, Ordering::Relaxed);
+
+        self.inner.wakers.lock().unwrap().insert(key, waker);
+
+        // The token may have been cancelled after the waker was inserted but before the wakers
+        // were drained, or before it was inserted at all.
+        if self.is_cancelled() {
+            if let Some(waker) = self.inner.wakers.lock().unwrap().remove(&key) {
+                waker.wake()
+            }
+        }
+
+        key
+    }
+
+    pub(crate) fn unregister(&self, key: usize) {
+        self.inner.wakers.lock().unwrap().remove(&key);
+    }
+}
+
+impl Default for CancellationToken {
+    fn default() -> Self {
+        CancellationToken::new()
+    }
+}
+
+/// A handle for aborting the future of a `Later` from anywhere
+///
+/// This is created by [`Later::abort_handle`](../struct.Later.html#method.abort_handle). Clones of
+/// a handle abort the same future. Once the future is aborted it's dropped without being polled
+/// again, any thread waiting for its output is woken, and the accessors of the `Later` panic (or
+/// return `AccessError::Cancelled` if they're fallible) instead of waiting forever.
+#[derive(Clone)]
+pub struct AbortHandle {
+    token: CancellationToken,
+}
+
+impl AbortHandle {
+
+    pub(crate) fn new(token: CancellationToken) -> Self {
+        AbortHandle { token: token }
+    }
+
+    /// Abort the future
+    ///
+    /// Aborting a future that has already completed, or aborting it again, does nothing.
+    pub fn abort(&self) {
+        self.token.cancel()
+    }
+
+    /// Check if `abort` was called on this handle or any of its clones
+    pub fn is_aborted(&self) -> bool {
+        self.token.is_cancelled()
+    }
+}
@@ -0,0 +1,121 @@
+//! The clock used for deadlines and timeouts
+//!
+//! With the `testing` feature the clock can be replaced by the virtual clock of
+//! `testing::FakeTime`. Without it `now` is just `Instant::now`.
+
+use std::time::Instant;
+
+#[cfg(feature = "testing")]
+pub(crate) mod fake {
+    use std::sync::{Mutex, Once, ONCE_INIT};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::task::Waker;
+    use std::time::Instant;
+
+    /// Whether the virtual clock is in use, so that the real clock is read without taking the lock
+    /// of the state
+    static ACTIVE: AtomicBool = AtomicBool::new(false);
+
+    pub(crate) struct State {
+        pub(crate) now: Option<Instant>,
+        waiters: Vec<(usize, Waker)>,
+        next: usize,
+    }
+
+    pub(crate) fn state() -> &'static Mutex<State> {
+        static INIT: Once = ONCE_INIT;
+        static mut STATE: *const Mutex<State> = 0 as *const _;
+
+        unsafe {
+            INIT.call_once(|| {
+                STATE = Box::into_raw(Box::new(Mutex::new(State { now: None, waiters: Vec::new(), next: 0 })));
+            });
+
+            &*STATE
+        }
+    }
+
+    /// Set the time of the virtual clock, `None` goes back to the real clock
+    ///
+    /// Every waiter is woken.
+    pub(crate) fn set(now: Option<Instant>) {
+        let waiters = {
+            let mut state = state().lock().unwrap();
+
+            state.now = now;
+
+            ACTIVE.store(now.is_some(), Ordering::SeqCst);
+
+            ::std::mem::replace(&mut state.waiters, Vec::new())
+        };
+
+        for (_, waker) in waiters {
+            waker.wake()
+        }
+    }
+
+    /// Get the time of the virtual clock, `None` if the real clock is in use
+    pub(crate) fn now() -> Option<Instant> {
+        if ACTIVE.load(Ordering::SeqCst) {
+            state().lock().unwrap().now
+        } else {
+            None
+        }
+    }
+
+    /// A waker registered with `wake_on_change`
+    ///
+    /// The waker is removed from the virtual clock when this is dropped, if the clock hasn't
+    /// changed (waking it) by then.
+    pub(crate) struct Waiter {
+        key: usize,
+    }
+
+    impl Drop for Waiter {
+        fn drop(&mut self) {
+            let key = self.key;
+
+            state().lock().unwrap().waiters.retain(|&(waiter, _)| waiter != key)
+        }
+    }
+
+    /// Wake `waker` the next time the virtual clock changes
+    pub(crate) fn wake_on_change(waker: Waker) -> Waiter {
+        let mut state = state().lock().unwrap();
+
+        let key = state.next;
+
+        state.next = state.next.wrapping_add(1);
+        state.waiters.push((key, waker));
+
+        Waiter { key: key }
+    }
+}
+
+/// Get the current time
+pub(crate) fn now() -> Instant {
+    #[cfg(feature = "testing")]
+    {
+        if let Some(now) = fake::now() {
+            return now;
+        }
+    }
+
+    Instant::now()
+}
+
+/// Check if the clock is the virtual clock
+///
+/// When it is, waiting for a deadline must be done by waiting to be woken by `fake::wake_on_change`
+/// instead of waiting for the real time to pass.
+pub(crate) fn is_fake() -> bool {
+    #[cfg(feature = "testing")]
+    {
+        return fake::now().is_some();
+    }
+
+    #[cfg(not(feature = "testing"))]
+    {
+        false
+    }
+}
@@ -0,0 +1,176 @@
+//! Futures that make progress while their thread waits on another `Later`
+//!
+//! The futures of cooperative `Later`s are kept in a registry of their thread. Whenever the thread
+//! is about to wait for the future of any `Later`, it first polls every registered future that
+//! hasn't completed, with the waker of the wait. A registered future that is woken wakes the
+//! waiting thread too, so every registered future keeps making progress for as long as the thread
+//! is blocked, without the need for another thread.
+
+use std::cell::RefCell;
+use std::future::Future;
+use std::mem;
+use std::panic::{self, AssertUnwindSafe};
+use std::pin::Pin;
+use std::rc::{Rc, Weak};
+use std::task::{Poll, LocalWaker};
+
+use super::Later;
+
+thread_local! {
+    /// The futures of the cooperative `Later`s created on this thread
+    static SIBLINGS: RefCell<Vec<Weak<dyn Drive>>> = RefCell::new(Vec::new());
+}
+
+enum State<T> where T: Future {
+    Pending(T),
+    Ready(T::Output),
+    Panicked,
+    Taken,
+}
+
+/// A registered future along with its output once it completes
+struct Shared<T> where T: Future {
+    state: RefCell<State<T>>,
+}
+
+trait Drive {
+    /// Poll the future once with `lw` if it hasn't completed and isn't already being polled
+    fn drive(&self, lw: &LocalWaker);
+
+    fn is_pending(&self) -> bool;
+}
+
+impl<T> Drive for Shared<T> where T: Future {
+    fn drive(&self, lw: &LocalWaker) {
+        let mut state = match self.state.try_borrow_mut() {
+            Ok(state) => state,
+            Err(_) => return,
+        };
+
+        let poll = match *state {
+            State::Pending(ref mut future) => {
+                // The future isn't moved until it is dropped
+                let future = unsafe { Pin::new_unchecked(future) };
+
+                // A panic is kept for the `Later` of the future rather than unwinding the thread
+                // waiting on an unrelated `Later`
+                panic::catch_unwind(AssertUnwindSafe(|| future.poll(lw)))
+            },
+            _ => return,
+        };
+
+        match poll {
+            Ok(Poll::Ready(val)) => *state = State::Ready(val),
+            Ok(Poll::Pending) => (),
+            Err(_) => *state = State::Panicked,
+        }
+    }
+
+    fn is_pending(&self) -> bool {
+        match self.state.try_borrow() {
+            Ok(state) => match *state {
+                State::Pending(_) => true,
+                _ => false,
+            },
+            Err(_) => true,
+        }
+    }
+}
+
+/// Poll every registered future of this thread once with `lw`
+///
+/// This is called by a thread just before it waits for the future of a `Later`.
+pub(crate) fn drive_siblings(lw: &LocalWaker) {
+    let siblings: Vec<Rc<dyn Drive>> = SIBLINGS.with(|siblings| {
+        let mut siblings = siblings.borrow_mut();
+
+        siblings.retain(|sibling| sibling.upgrade().map_or(false, |sibling| sibling.is_pending()));
+
+        siblings.iter().filter_map(Weak::upgrade).collect()
+    });
+
+    // The registry isn't borrowed while the futures are polled, as they may create more
+    // cooperative `Later`s
+    for sibling in siblings {
+        sibling.drive(lw)
+    }
+}
+
+/// The future of a cooperative `Later`
+///
+/// This is created by [`Later::cooperative`](./struct.Later.html#method.cooperative).
+///
+/// # Panic
+/// Polling panics if the future panicked while it was polled by a thread waiting on another
+/// `Later`
+pub struct Cooperative<T> where T: Future {
+    shared: Rc<Shared<T>>,
+}
+
+impl<T,O> Future for Cooperative<T> where T: Future<Output=O> {
+    type Output = O;
+
+    fn poll(self: Pin<&mut Self>, lw: &LocalWaker) -> Poll<O> {
+        let mut state = self.shared.state.borrow_mut();
+
+        let val = match *state {
+            State::Pending(ref mut future) => match unsafe { Pin::new_unchecked(future) }.poll(lw) {
+                Poll::Ready(val) => Some(val),
+                Poll::Pending => return Poll::Pending,
+            },
+            _ => None,
+        };
+
+        match val {
+            Some(val) => {
+                *state = State::Taken;
+
+                Poll::Ready(val)
+            },
+            None => match mem::replace(&mut *state, State::Taken) {
+                State::Ready(val) => Poll::Ready(val),
+                State::Panicked => panic!("the future of a cooperative Later panicked while it was polled"),
+                _ => panic!("Cooperative polled after completion"),
+            },
+        }
+    }
+}
+
+impl<T,O> Later<Cooperative<T>> where T: Future<Output=O> + 'static {
+
+    /// Create a `Later` whose future is also polled while this thread waits on any other `Later`
+    ///
+    /// Normally the future of a `Later` only makes progress when it's accessed. The future of a
+    /// cooperative `Later` is registered with the current thread, and whenever the thread blocks
+    /// on another `Later` the registered futures are polled as well. Several cooperative `Later`s
+    /// therefore make progress at the same time without extra threads, and the ones that weren't
+    /// forced may already be done once they are accessed.
+    ///
+    /// The future is only polled by the thread that created the `Later` while it waits, or by
+    /// whichever thread accesses the output.
+    ///
+    /// ```rust
+    /// # #![feature(futures_api)]
+    /// # #![feature(async_await)]
+    /// # extern crate alligator;
+    /// # use alligator::Later;
+    /// # async fn fetch_profile() -> &'static str { "ferris" }
+    /// # async fn fetch_avatar() -> Vec<u8> { vec![0; 16] }
+    /// # fn main() {
+    /// let profile = Later::cooperative(fetch_profile());
+    /// let avatar = Later::cooperative(fetch_avatar());
+    ///
+    /// // While this thread waits on the profile the avatar is fetched as well
+    /// println!("{} has an avatar of {} bytes", *profile, avatar.len());
+    /// # }
+    /// ```
+    pub fn cooperative(future: T) -> Self {
+        let shared = Rc::new(Shared { state: RefCell::new(State::Pending(future)) });
+
+        let sibling: Rc<dyn Drive> = shared.clone();
+
+        SIBLINGS.with(|siblings| siblings.borrow_mut().push(Rc::downgrade(&sibling)));
+
+        Later::new(Cooperative { shared: shared })
+    }
+}
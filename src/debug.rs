@@ -0,0 +1,136 @@
+//! A registry of the threads currently blocked on `Later`s
+//!
+//! Whenever a thread has to wait for the future of a `Later` within one of its accessors, the wait
+//! is registered until the future completes. [`dump_blocked`](./fn.dump_blocked.html) lists every
+//! registered wait, which is the first thing to look at when a process appears to hang.
+//!
+//! # Example
+//! ```rust
+//! # extern crate alligator;
+//! # fn main() {
+//! for wait in alligator::debug::dump_blocked() {
+//!     eprintln!("{}", wait);
+//! }
+//! # }
+//! ```
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Mutex, Once, ONCE_INIT};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use super::Location;
+
+struct Entry {
+    label: Option<Cow<'static, str>>,
+    location: Option<Location>,
+    thread: String,
+    since: Instant,
+}
+
+fn registry() -> &'static Mutex<HashMap<usize, Entry>> {
+    static INIT: Once = ONCE_INIT;
+    static mut REGISTRY: *const Mutex<HashMap<usize, Entry>> = 0 as *const _;
+
+    unsafe {
+        INIT.call_once(|| {
+            REGISTRY = Box::into_raw(Box::new(Mutex::new(HashMap::new())));
+        });
+
+        &*REGISTRY
+    }
+}
+
+/// Removes a wait from the registry when dropped
+pub(crate) struct Registration {
+    id: usize,
+}
+
+impl Drop for Registration {
+    fn drop(&mut self) {
+        registry().lock().unwrap().remove(&self.id);
+    }
+}
+
+/// Register the current thread as waiting on the `Later` with the id
+pub(crate) fn register(id: usize, label: Option<Cow<'static, str>>, location: Option<Location>) -> Registration {
+    let current = thread::current();
+
+    let entry = Entry {
+        label: label,
+        location: location,
+        thread: current.name().map(String::from).unwrap_or_else(|| format!("{:?}", current.id())),
+        since: Instant::now(),
+    };
+
+    registry().lock().unwrap().insert(id, entry);
+
+    Registration { id: id }
+}
+
+/// A thread waiting for the future of a `Later`
+#[derive(Clone, Debug)]
+pub struct BlockedWait {
+    label: Option<Cow<'static, str>>,
+    location: Option<Location>,
+    thread: String,
+    elapsed: Duration,
+}
+
+impl BlockedWait {
+
+    /// The first context given to the `Later`, if any
+    pub fn label(&self) -> Option<&str> {
+        self.label.as_ref().map(|l| &**l)
+    }
+
+    /// Where the `Later` was created, if it is known
+    pub fn location(&self) -> Option<&Location> {
+        self.location.as_ref()
+    }
+
+    /// The name (or id, if it has no name) of the waiting thread
+    pub fn thread(&self) -> &str {
+        &self.thread
+    }
+
+    /// How long the thread has been waiting
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+}
+
+impl fmt::Display for BlockedWait {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "thread '{}' blocked for {:?} on ", self.thread, self.elapsed)?;
+
+        match self.label {
+            Some(ref label) => write!(f, "'{}'", label)?,
+            None => f.write_str("a Later")?,
+        }
+
+        match self.location {
+            Some(ref location) => write!(f, " created at {}", location),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Get every wait in progress, longest first
+pub fn dump_blocked() -> Vec<BlockedWait> {
+    let now = Instant::now();
+
+    let mut waits: Vec<BlockedWait> = registry().lock().unwrap().values().map(|entry| BlockedWait {
+        label: entry.label.clone(),
+        location: entry.location,
+        thread: entry.thread.clone(),
+        elapsed: now - entry.since,
+    })
+    .collect();
+
+    waits.sort_by(|a, b| b.elapsed.cmp(&a.elapsed));
+
+    waits
+}
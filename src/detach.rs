@@ -0,0 +1,116 @@
+//! The background thread for futures that are no longer waited on
+//!
+//! A single thread is started the first time a future is detached. It polls every detached future
+//! to completion, one at a time as they are woken, and drops their outputs. A detached future that
+//! panics is dropped.
+
+use std::collections::VecDeque;
+use std::future::Future;
+use std::panic::{self, AssertUnwindSafe};
+use std::pin::Pin;
+use std::sync::{Arc, Condvar, Mutex, Once, ONCE_INIT};
+use std::task::{Wake, Poll, LocalWaker};
+use std::thread;
+
+type DetachedFuture = Pin<Box<dyn Future<Output=()> + Send>>;
+
+/// The queue of woken tasks of the background thread
+struct Driver {
+    woken: Mutex<VecDeque<Arc<Task>>>,
+    cvar: Condvar,
+}
+
+struct Task {
+    future: Mutex<Option<DetachedFuture>>,
+}
+
+impl Wake for Task {
+    fn wake(arc_self: &Arc<Self>) {
+        let driver = driver();
+
+        driver.woken.lock().unwrap().push_back(arc_self.clone());
+
+        driver.cvar.notify_one();
+    }
+}
+
+fn driver() -> &'static Driver {
+    static INIT: Once = ONCE_INIT;
+    static mut DRIVER: *const Driver = 0 as *const _;
+
+    unsafe {
+        INIT.call_once(|| {
+            DRIVER = Box::into_raw(Box::new( Driver {
+                woken: Mutex::new(VecDeque::new()),
+                cvar: Condvar::new(),
+            }));
+
+            thread::Builder::new()
+                .name(String::from("alligator-detached"))
+                .spawn(run)
+                .expect("failed to spawn the thread for detached futures");
+        });
+
+        &*DRIVER
+    }
+}
+
+/// The loop of the background thread
+fn run() {
+    let driver = driver();
+
+    loop {
+        let task = {
+            let mut woken = driver.woken.lock().unwrap();
+
+            loop {
+                match woken.pop_front() {
+                    Some(task) => break task,
+                    None => woken = driver.cvar.wait(woken).unwrap(),
+                }
+            }
+        };
+
+        let lw = ::std::task::local_waker_from_nonlocal(task.clone());
+
+        let mut future = task.future.lock().unwrap();
+
+        // A task woken more than once is queued more than once, it's already done the second time
+        let poll = match *future {
+            // Nothing waits on a detached future, a panic drops it rather than the background
+            // thread and every other detached future with it
+            Some(ref mut future) => {
+                panic::catch_unwind(AssertUnwindSafe(|| future.as_mut().poll(&lw)))
+            },
+            None => continue,
+        };
+
+        if poll.map(|poll| poll.is_ready()).unwrap_or(true) {
+            *future = None;
+        }
+    }
+}
+
+/// Hand a future over to the background thread
+pub(crate) fn detach<T>(future: T) where T: Future + Send + 'static {
+    let task = Arc::new( Task {
+        future: Mutex::new(Some(Box::pinned(Discard { future: future }))),
+    });
+
+    Wake::wake(&task);
+}
+
+/// A future that drops the output of its future
+struct Discard<T> {
+    future: T,
+}
+
+impl<T> Future for Discard<T> where T: Future {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, lw: &LocalWaker) -> Poll<()> {
+        let this = unsafe { Pin::get_mut_unchecked(self) };
+
+        unsafe { Pin::new_unchecked(&mut this.future) }.poll(lw).map(|_| ())
+    }
+}
@@ -0,0 +1,192 @@
+//! Errors returned by alligator
+
+use std::borrow::Cow;
+use std::error::Error;
+use std::fmt;
+
+/// The error returned when the output of a `Later` can't be accessed
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AccessError {
+    /// The output was taken out of the `Later` with `take_value`, or the future with `take_future`
+    Taken,
+    /// The future panicked while it was polled, so there will never be an output
+    Poisoned,
+    /// The future was aborted with an `AbortHandle` or cancelled by the token given to
+    /// `Later::with_cancel`, so there will never be an output
+    Cancelled,
+    /// The deadline given to `Later::with_deadline` passed before the future completed
+    Elapsed,
+    /// Waiting for the output would exceed the limit set by `set_blocked_limit`
+    BlockedLimit,
+    /// Waiting for the output on the current thread could never end, see `can_block`
+    WouldDeadlock,
+    /// The future was polled more times than the budget set by `Later::poll_budget` allows
+    PollBudgetExceeded {
+        /// The type name of the future
+        type_name: &'static str,
+        /// The number of times the future was polled
+        polls: usize,
+    },
+}
+
+impl fmt::Display for AccessError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            AccessError::Taken => f.write_str("the output was already taken"),
+            AccessError::Poisoned => f.write_str("the future panicked while it was polled"),
+            AccessError::Cancelled => f.write_str("the future was cancelled"),
+            AccessError::Elapsed => f.write_str("the deadline passed before the future completed"),
+            AccessError::BlockedLimit => f.write_str("too many threads are already blocked on Laters"),
+            AccessError::WouldDeadlock => f.write_str("waiting for the output on this thread would never end"),
+            AccessError::PollBudgetExceeded { type_name, polls } => write!(f,
+                "the future of type `{}` was polled {} times without completing", type_name, polls),
+        }
+    }
+}
+
+impl Error for AccessError {}
+
+/// The error returned when waiting was stopped by a cancellation
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Cancelled {
+    _priv: (),
+}
+
+impl Cancelled {
+    pub(crate) fn new() -> Self {
+        Cancelled { _priv: () }
+    }
+}
+
+impl fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("the future was cancelled")
+    }
+}
+
+impl Error for Cancelled {}
+
+/// The error returned when waiting for an output took longer than the allowed time
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TimeoutError {
+    _priv: (),
+}
+
+impl TimeoutError {
+    pub(crate) fn new() -> Self {
+        TimeoutError { _priv: () }
+    }
+}
+
+impl fmt::Display for TimeoutError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("timed out waiting for the output of a future")
+    }
+}
+
+impl Error for TimeoutError {}
+
+/// The error returned by a future of `time::timeout` that didn't complete in time
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Elapsed {
+    _priv: (),
+}
+
+impl Elapsed {
+    pub(crate) fn new() -> Self {
+        Elapsed { _priv: () }
+    }
+}
+
+impl fmt::Display for Elapsed {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("the future did not complete before the timeout")
+    }
+}
+
+impl Error for Elapsed {}
+
+/// The error returned when a future spawned with a deadline did not complete before the deadline
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DeadlineExceeded {
+    _priv: (),
+}
+
+impl DeadlineExceeded {
+    pub(crate) fn new() -> Self {
+        DeadlineExceeded { _priv: () }
+    }
+}
+
+impl fmt::Display for DeadlineExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("the deadline passed before the future completed")
+    }
+}
+
+impl Error for DeadlineExceeded {}
+
+/// An error along with the context of where it came from
+///
+/// The context is a chain of human readable descriptions of what was being done when the error
+/// occurred. The context is displayed before the error, from the outermost context to the
+/// innermost, separated by colons.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ContextError<E> {
+    error: E,
+    context: Vec<Cow<'static, str>>,
+}
+
+impl<E> ContextError<E> {
+
+    /// Create a new `ContextError` with no context
+    pub fn new(error: E) -> Self {
+        ContextError {
+            error: error,
+            context: Vec::new(),
+        }
+    }
+
+    /// Add an outer context to the error
+    pub fn context<C>(mut self, context: C) -> Self where C: Into<Cow<'static, str>> {
+        self.context.push(context.into());
+        self
+    }
+
+    /// Get the contexts of the error, from the innermost to the outermost
+    pub fn contexts(&self) -> &[Cow<'static, str>] {
+        &self.context
+    }
+
+    /// Get a reference to the error
+    pub fn error(&self) -> &E {
+        &self.error
+    }
+
+    /// Discard the context and return the error
+    pub fn into_error(self) -> E {
+        self.error
+    }
+}
+
+impl<E> From<E> for ContextError<E> {
+    fn from(error: E) -> Self {
+        ContextError::new(error)
+    }
+}
+
+impl<E> fmt::Display for ContextError<E> where E: fmt::Display {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for context in self.context.iter().rev() {
+            write!(f, "{}: ", context)?;
+        }
+
+        self.error.fmt(f)
+    }
+}
+
+impl<E> Error for ContextError<E> where E: Error + 'static {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.error)
+    }
+}
@@ -0,0 +1,48 @@
+use std::future::Future;
+
+use super::{block_on, Later};
+
+/// Methods for wrapping or running any future
+///
+/// This is implemented for every type that implements `Future`, so that a `Later` can be created
+/// at the end of a method chain.
+///
+/// # Example
+/// ```rust
+/// # #![feature(futures_api)]
+/// # #![feature(async_await)]
+/// # extern crate alligator;
+/// use alligator::LaterExt;
+///
+/// # async fn fetch_report() -> &'static str { "report" }
+/// # async fn flush() -> bool { true }
+/// # fn main() {
+/// let report = fetch_report().later();
+///
+/// let flushed = flush().block();
+/// # assert_eq!(*report, "report");
+/// # assert!(flushed);
+/// # }
+/// ```
+pub trait LaterExt: Future + Sized {
+
+    /// Wrap self in a `Later`
+    ///
+    /// This is the same as `Later::new`. Unlike the `l!` macro the location isn't recorded.
+    fn later(self) -> Later<Self> {
+        Later::new(self)
+    }
+
+    /// Block the current thread polling self to completion and return its output
+    ///
+    /// This is the same as [`block_on`](./fn.block_on.html).
+    ///
+    /// # Panic
+    /// This panics if self doesn't complete at the first poll and the current thread can't block
+    /// (see [`can_block`](./fn.can_block.html)), such as within a future spawned onto a pool.
+    fn block(self) -> Self::Output {
+        block_on(self)
+    }
+}
+
+impl<T> LaterExt for T where T: Future {}
@@ -0,0 +1,90 @@
+//! Adapters between asynchronous and synchronous io
+
+use std::future::Future;
+use std::io::{self, Write};
+use std::pin::Pin;
+use std::task::{Poll, LocalWaker};
+
+use futures::io::AsyncRead;
+
+use super::Later;
+
+const BUFFER_SIZE: usize = 8 * 1024;
+
+/// A future for copying all the bytes of an `AsyncRead` into a `Write`
+///
+/// The output is the total number of bytes copied. Writes to the writer are synchronous, so they
+/// happen within the call to `poll`.
+pub struct Copy<R,W> where R: AsyncRead, W: Write {
+    reader: R,
+    writer: W,
+    buffer: Box<[u8]>,
+    total: u64,
+}
+
+impl<R,W> Copy<R,W> where R: AsyncRead, W: Write {
+
+    /// Create a new `Copy` from `reader` into `writer`
+    pub fn new( reader: R, writer: W ) -> Self {
+        Copy {
+            reader: reader,
+            writer: writer,
+            buffer: vec![0; BUFFER_SIZE].into_boxed_slice(),
+            total: 0,
+        }
+    }
+}
+
+impl<R,W> Future for Copy<R,W> where R: AsyncRead, W: Write {
+    type Output = io::Result<u64>;
+
+    fn poll(self: Pin<&mut Self>, lw: &LocalWaker) -> Poll<io::Result<u64>> {
+        let this = unsafe { Pin::get_mut_unchecked(self) };
+
+        loop {
+            match this.reader.poll_read(lw, &mut this.buffer) {
+                Poll::Ready(Ok(0)) => break Poll::Ready(this.writer.flush().map(|_| this.total)),
+                Poll::Ready(Ok(len)) => {
+                    if let Err(e) = this.writer.write_all(&this.buffer[..len]) {
+                        break Poll::Ready(Err(e));
+                    }
+
+                    this.total += len as u64;
+                },
+                Poll::Ready(Err(ref e)) if e.kind() == io::ErrorKind::Interrupted => (),
+                Poll::Ready(Err(e)) => break Poll::Ready(Err(e)),
+                Poll::Pending => break Poll::Pending,
+            }
+        }
+    }
+}
+
+/// Create a `Later` for copying all of the bytes of `reader` into `writer`
+///
+/// The output of the `Later` is the number of bytes copied. The writer is flushed after the reader
+/// has reached the end.
+///
+/// ```rust
+/// # #![feature(futures_api)]
+/// # extern crate alligator;
+/// # use std::io::Cursor;
+/// # fn main() {
+/// let upload = Cursor::new(vec![7u8; 20_000]);
+/// let mut stored = Vec::new();
+///
+/// let copied = {
+///     let copy = alligator::io::copy(upload, &mut stored);
+///
+///     *copy.as_ref().unwrap()
+/// };
+///
+/// assert_eq!(copied, 20_000);
+/// assert_eq!(stored, vec![7u8; 20_000]);
+/// # }
+/// ```
+pub fn copy<R,W>( reader: R, writer: W ) -> Later<Copy<R,W>>
+    where R: AsyncRead,
+          W: Write,
+{
+    Later::new(Copy::new(reader, writer))
+}
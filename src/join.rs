@@ -0,0 +1,642 @@
+//! Driving multiple futures concurrently
+//!
+//! Polling a group of futures with a single waker means every wake up polls the group from the
+//! start, so a future that is woken often (a chatty future) can be polled again and again while the
+//! others wait. The driver used here gives each future its own waker so that only the futures
+//! that have been woken are polled, and the order they are polled in is decided by a
+//! [`Policy`](./enum.Policy.html).
+
+use std::collections::VecDeque;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Wake, Waker, Poll, LocalWaker};
+
+use super::{Later, MainWaker};
+use super::map::Source;
+
+/// The order in which woken futures of a group are polled
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Policy {
+    /// Every woken future is polled once before any future is polled again
+    ///
+    /// The position the next round starts from is rotated every round so no future is always
+    /// polled first.
+    RoundRobin,
+    /// Woken futures are polled in the order they were added to the group
+    ///
+    /// A future earlier in the group is always polled before a future later in the group, so a
+    /// future that is continuously woken can delay the futures after it.
+    Priority,
+}
+
+impl Default for Policy {
+    fn default() -> Self {
+        Policy::RoundRobin
+    }
+}
+
+/// The order of the outputs of a group of futures
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Order {
+    /// The outputs are in the same order as their futures were given
+    ///
+    /// Outputs of futures that complete before the futures given ahead of them are held until the
+    /// futures ahead of them complete.
+    Input,
+    /// The outputs are in the order their futures completed
+    ///
+    /// Nothing is held, so this uses less memory and the first outputs are available sooner.
+    Completion,
+}
+
+/// The wake up flags of the futures in a group
+struct Wakeups {
+    woken: Mutex<Vec<bool>>,
+    parent: Mutex<Option<Waker>>,
+}
+
+impl Wakeups {
+
+    /// Create the wake up flags for `len` futures along with the waker of each future
+    ///
+    /// Every future starts off woken so that it is polled the first time.
+    fn with_wakers(len: usize) -> (Arc<Self>, Vec<LocalWaker>) {
+        let wakeups = Arc::new( Wakeups {
+            woken: Mutex::new(vec![true; len]),
+            parent: Mutex::new(None),
+        });
+
+        let wakers = (0..len).map(|index| {
+            ::std::task::local_waker_from_nonlocal( Arc::new( SlotWaker {
+                index: index,
+                wakeups: wakeups.clone(),
+            }))
+        })
+        .collect();
+
+        (wakeups, wakers)
+    }
+
+    /// Take the woken flags, resetting them all to false
+    fn take(&self) -> Vec<bool> {
+        let mut woken = self.woken.lock().unwrap();
+
+        let len = woken.len();
+
+        ::std::mem::replace(&mut *woken, vec![false; len])
+    }
+
+    /// Take the flag for the future at `index`
+    fn take_one(&self, index: usize) -> bool {
+        ::std::mem::replace(&mut self.woken.lock().unwrap()[index], false)
+    }
+}
+
+/// The waker given to a single future of a group
+struct SlotWaker {
+    index: usize,
+    wakeups: Arc<Wakeups>,
+}
+
+impl Wake for SlotWaker {
+    fn wake(arc_self: &Arc<Self>) {
+        arc_self.wakeups.woken.lock().unwrap()[arc_self.index] = true;
+
+        if let Some(ref waker) = *arc_self.wakeups.parent.lock().unwrap() {
+            waker.wake()
+        }
+    }
+}
+
+/// A driver of multiple futures
+///
+/// Futures are never moved once they're added to the driver.
+pub(crate) struct Driver<T,O> where T: Future<Output=O> {
+    futures: Vec<Option<T>>,
+    wakers: Vec<LocalWaker>,
+    wakeups: Arc<Wakeups>,
+    policy: Policy,
+    cursor: usize,
+    remaining: usize,
+    ready: VecDeque<(usize, O)>,
+}
+
+impl<T,O> Driver<T,O> where T: Future<Output=O> {
+
+    pub(crate) fn new<I>( futures: I, policy: Policy ) -> Self where I: IntoIterator<Item=T> {
+        let futures: Vec<Option<T>> = futures.into_iter().map(Some).collect();
+
+        let (wakeups, wakers) = Wakeups::with_wakers(futures.len());
+
+        Driver {
+            remaining: futures.len(),
+            futures: futures,
+            wakers: wakers,
+            wakeups: wakeups,
+            policy: policy,
+            cursor: 0,
+            ready: VecDeque::new(),
+        }
+    }
+
+    /// The number of futures added to the driver
+    pub(crate) fn len(&self) -> usize {
+        self.futures.len()
+    }
+
+    /// The number of futures that have not had their output returned by `poll_next`
+    pub(crate) fn remaining(&self) -> usize {
+        self.remaining + self.ready.len()
+    }
+
+    /// Poll the futures of the driver to get the next completed output
+    ///
+    /// The output is returned along with the position of its future within the driver. `None` is
+    /// returned once all the futures have completed.
+    pub(crate) fn poll_next(&mut self, lw: &LocalWaker) -> Poll<Option<(usize, O)>> {
+        *self.wakeups.parent.lock().unwrap() = Some(lw.as_waker().clone());
+
+        if self.ready.is_empty() && self.remaining != 0 {
+            match self.policy {
+                Policy::RoundRobin => self.round_robin(),
+                Policy::Priority   => self.priority(),
+            }
+        }
+
+        match self.ready.pop_front() {
+            Some(ready) => Poll::Ready(Some(ready)),
+            None if self.remaining == 0 => Poll::Ready(None),
+            None => Poll::Pending,
+        }
+    }
+
+    /// Add a future to the driver
+    ///
+    /// Adding a future may move the futures already in the driver, which is why they must be
+    /// `Unpin`.
+    pub(crate) fn push(&mut self, future: T) where T: Unpin {
+        let index = self.futures.len();
+
+        self.futures.push(Some(future));
+
+        self.wakeups.woken.lock().unwrap().push(true);
+
+        self.wakers.push( ::std::task::local_waker_from_nonlocal( Arc::new( SlotWaker {
+            index: index,
+            wakeups: self.wakeups.clone(),
+        })));
+
+        self.remaining += 1;
+    }
+
+    /// Drop every future that hasn't completed
+    ///
+    /// The driver is finished afterwards, `poll_next` only returns the outputs that were already
+    /// completed.
+    pub(crate) fn cancel(&mut self) {
+        for future in self.futures.iter_mut() {
+            *future = None;
+        }
+
+        self.remaining = 0;
+    }
+
+    fn poll_slot(&mut self, index: usize) {
+        let poll = match self.futures[index] {
+            Some(ref mut future) => unsafe { Pin::new_unchecked(future) }.poll(&self.wakers[index]),
+            None => return,
+        };
+
+        if let Poll::Ready(val) = poll {
+            self.futures[index] = None;
+            self.remaining -= 1;
+            self.ready.push_back((index, val));
+        }
+    }
+
+    /// Poll every future that was woken before the pass started once
+    fn round_robin(&mut self) {
+        let woken = self.wakeups.take();
+        let len = woken.len();
+
+        for offset in 0..len {
+            let index = (self.cursor + offset) % len;
+
+            if woken[index] {
+                self.poll_slot(index);
+            }
+        }
+
+        if len != 0 {
+            self.cursor = (self.cursor + 1) % len;
+        }
+    }
+
+    /// Poll the earliest woken future until either a future completes or as many polls as there are
+    /// futures have been made.
+    fn priority(&mut self) {
+        for _ in 0..self.futures.len() {
+            match (0..self.futures.len()).find(|index| self.wakeups.take_one(*index)) {
+                Some(index) => self.poll_slot(index),
+                None => break,
+            }
+
+            if !self.ready.is_empty() {
+                break
+            }
+        }
+    }
+}
+
+/// The outputs of a `JoinAll` collected so far
+enum Outputs<O> {
+    Input(Vec<Option<O>>),
+    Completion(Vec<O>),
+}
+
+/// A future for the outputs of a group of futures
+///
+/// By default the outputs are in the same order as the futures were given, use
+/// [`order`](#method.order) to have them in the order the futures complete.
+pub struct JoinAll<T,O> where T: Future<Output=O> {
+    driver: Driver<T,O>,
+    outputs: Outputs<O>,
+}
+
+impl<T,O> JoinAll<T,O> where T: Future<Output=O> {
+
+    /// Create a `JoinAll` that polls woken futures in round robin order
+    pub fn new<I>( futures: I ) -> Self where I: IntoIterator<Item=T> {
+        Self::with_policy(futures, Policy::default())
+    }
+
+    /// Create a `JoinAll` that polls woken futures in the order given by `policy`
+    pub fn with_policy<I>( futures: I, policy: Policy ) -> Self where I: IntoIterator<Item=T> {
+        let driver = Driver::new(futures, policy);
+
+        JoinAll {
+            outputs: Outputs::Input((0..driver.len()).map(|_| None).collect()),
+            driver: driver,
+        }
+    }
+
+    /// Set the order of the outputs
+    pub fn order(mut self, order: Order) -> Self {
+        self.outputs = match order {
+            Order::Input => Outputs::Input((0..self.driver.len()).map(|_| None).collect()),
+            Order::Completion => Outputs::Completion(Vec::with_capacity(self.driver.len())),
+        };
+
+        self
+    }
+}
+
+impl<T,O> Future for JoinAll<T,O> where T: Future<Output=O> {
+    type Output = Vec<O>;
+
+    fn poll(self: Pin<&mut Self>, lw: &LocalWaker) -> Poll<Vec<O>> {
+        let this = unsafe { Pin::get_mut_unchecked(self) };
+
+        loop {
+            match (this.driver.poll_next(lw), &mut this.outputs) {
+                (Poll::Ready(Some((index, val))), &mut Outputs::Input(ref mut outputs)) => {
+                    outputs[index] = Some(val)
+                },
+                (Poll::Ready(Some((_, val))), &mut Outputs::Completion(ref mut outputs)) => {
+                    outputs.push(val)
+                },
+                (Poll::Ready(None), &mut Outputs::Input(ref mut outputs)) => break Poll::Ready(
+                    outputs.drain(..).map(|o| o.expect("Report a bug if you get this panic")).collect()
+                ),
+                (Poll::Ready(None), &mut Outputs::Completion(ref mut outputs)) => break Poll::Ready(
+                    ::std::mem::replace(outputs, Vec::new())
+                ),
+                (Poll::Pending, _) => break Poll::Pending,
+            }
+        }
+    }
+}
+
+/// Create a `Later` for the outputs of all the futures
+///
+/// The futures are polled concurrently when the `Later` is forced, using the round robin
+/// [`Policy`](./join/enum.Policy.html). Use [`JoinAll::with_policy`](./join/struct.JoinAll.html)
+/// with `Later::new` for a different policy.
+///
+/// Collecting an iterator of futures into a `Later` does the same thing.
+///
+/// ```rust
+/// # #![feature(futures_api)]
+/// # #![feature(async_await)]
+/// # extern crate alligator;
+/// # use alligator::Later;
+/// # async fn fetch(n: u32) -> u32 { n + 1 }
+/// # fn main() {
+/// let all: Later<_> = (0..3).map(fetch).collect();
+/// # assert_eq!(*all, vec![1, 2, 3]);
+/// # }
+/// ```
+pub fn join_all<I,T,O>( futures: I ) -> Later<JoinAll<T,O>>
+    where I: IntoIterator<Item=T>,
+          T: Future<Output=O>,
+{
+    Later::new(JoinAll::new(futures))
+}
+
+/// Collect futures into a `Later` for the outputs of all of them
+///
+/// This is the same as [`join_all`](./fn.join_all.html).
+impl<T,O> ::std::iter::FromIterator<T> for Later<JoinAll<T,O>> where T: Future<Output=O> {
+    fn from_iter<I>(futures: I) -> Self where I: IntoIterator<Item=T> {
+        join_all(futures)
+    }
+}
+
+/// A future for the first successful output of a group of fallible futures
+///
+/// If every future fails then the output is all the errors, in the same order as the futures were
+/// given.
+pub struct RaceOk<T,V,E> where T: Future<Output=Result<V,E>> {
+    driver: Driver<T,Result<V,E>>,
+    errors: Vec<Option<E>>,
+}
+
+impl<T,V,E> RaceOk<T,V,E> where T: Future<Output=Result<V,E>> {
+
+    /// Create a `RaceOk` that polls woken futures in round robin order
+    pub fn new<I>( futures: I ) -> Self where I: IntoIterator<Item=T> {
+        Self::with_policy(futures, Policy::default())
+    }
+
+    /// Create a `RaceOk` that polls woken futures in the order given by `policy`
+    pub fn with_policy<I>( futures: I, policy: Policy ) -> Self where I: IntoIterator<Item=T> {
+        let driver = Driver::new(futures, policy);
+
+        RaceOk {
+            errors: (0..driver.len()).map(|_| None).collect(),
+            driver: driver,
+        }
+    }
+}
+
+impl<T,V,E> Future for RaceOk<T,V,E> where T: Future<Output=Result<V,E>> {
+    type Output = Result<V,Vec<E>>;
+
+    fn poll(self: Pin<&mut Self>, lw: &LocalWaker) -> Poll<Result<V,Vec<E>>> {
+        let this = unsafe { Pin::get_mut_unchecked(self) };
+
+        loop {
+            match this.driver.poll_next(lw) {
+                Poll::Ready(Some((_, Ok(val)))) => {
+                    this.driver.cancel();
+
+                    break Poll::Ready(Ok(val))
+                },
+                Poll::Ready(Some((index, Err(e)))) => this.errors[index] = Some(e),
+                Poll::Ready(None) => break Poll::Ready( Err(
+                    this.errors.drain(..).map(|e| e.expect("Report a bug if you get this panic")).collect()
+                )),
+                Poll::Pending => break Poll::Pending,
+            }
+        }
+    }
+}
+
+/// Create a `Later` for the first successful output of the fallible futures
+///
+/// The futures are polled concurrently when the `Later` is forced and the remaining futures are
+/// dropped as soon as one succeeds. The output is only an error when all of the futures fail (or
+/// when there are no futures), in which case it contains every error.
+///
+/// ```rust
+/// # #![feature(futures_api)]
+/// # #![feature(async_await)]
+/// # extern crate alligator;
+/// # use alligator::race_ok;
+/// async fn download(mirror: u32, online: &'static [u32]) -> Result<u32, String> {
+///     if online.contains(&mirror) {
+///         Ok(mirror)
+///     } else {
+///         Err(format!("mirror {} is down", mirror))
+///     }
+/// }
+///
+/// # fn main() {
+/// let file = race_ok((0..3).map(|mirror| download(mirror, &[1])));
+///
+/// assert_eq!(*file, Ok(1));
+///
+/// let outage = race_ok((0..3).map(|mirror| download(mirror, &[])));
+///
+/// assert_eq!(outage.as_ref().unwrap_err().len(), 3);
+/// # }
+/// ```
+pub fn race_ok<I,T,V,E>( futures: I ) -> Later<RaceOk<T,V,E>>
+    where I: IntoIterator<Item=T>,
+          T: Future<Output=Result<V,E>>,
+{
+    Later::new(RaceOk::new(futures))
+}
+
+/// One of the futures of a `Zip`, kept until the output of the other future is also available
+enum Side<T,O> {
+    Running(Source<T,O>),
+    Done(Option<O>),
+}
+
+impl<T,O> Side<T,O> where T: Future<Output=O> {
+
+    /// Poll the future if it hasn't completed, returning true once the output is available
+    fn poll_done(&mut self, lw: &LocalWaker) -> bool {
+        let val = match *self {
+            Side::Running(ref mut source) => match unsafe { Pin::new_unchecked(source) }.poll(lw) {
+                Poll::Ready(val) => val,
+                Poll::Pending => return false,
+            },
+            Side::Done(_) => return true,
+        };
+
+        *self = Side::Done(Some(val));
+
+        true
+    }
+
+    fn take(&mut self) -> O {
+        match *self {
+            Side::Done(ref mut val) => val.take().expect("Zip polled after completion"),
+            Side::Running(_) => panic!("Report a bug if you get this panic"),
+        }
+    }
+}
+
+/// A future for the outputs of two `Later`s
+///
+/// This is created by [`Later::zip`](../struct.Later.html#method.zip).
+pub struct Zip<T,A,T2,B> {
+    first: Side<T,A>,
+    second: Side<T2,B>,
+}
+
+impl<T,A,T2,B> Zip<T,A,T2,B> {
+    pub(crate) fn new(first: Source<T,A>, second: Source<T2,B>) -> Self {
+        Zip {
+            first: Side::Running(first),
+            second: Side::Running(second),
+        }
+    }
+}
+
+impl<T,A,T2,B> Future for Zip<T,A,T2,B> where T: Future<Output=A>, T2: Future<Output=B> {
+    type Output = (A,B);
+
+    fn poll(self: Pin<&mut Self>, lw: &LocalWaker) -> Poll<(A,B)> {
+        let this = unsafe { Pin::get_mut_unchecked(self) };
+
+        let first = this.first.poll_done(lw);
+        let second = this.second.poll_done(lw);
+
+        if first && second {
+            Poll::Ready((this.first.take(), this.second.take()))
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+/// A future for the output of whichever of two `Later`s completes first
+///
+/// Each future is given its own waker so that a wake up only polls the future that was woken.
+/// The other future is dropped once one completes.
+///
+/// This is created by [`Later::race`](../struct.Later.html#method.race).
+pub struct Race<T,T2,O> {
+    first: Option<Source<T,O>>,
+    second: Option<Source<T2,O>>,
+    wakers: Vec<LocalWaker>,
+    wakeups: Arc<Wakeups>,
+}
+
+impl<T,T2,O> Race<T,T2,O> {
+    pub(crate) fn new(first: Source<T,O>, second: Source<T2,O>) -> Self {
+        let (wakeups, wakers) = Wakeups::with_wakers(2);
+
+        Race {
+            first: Some(first),
+            second: Some(second),
+            wakers: wakers,
+            wakeups: wakeups,
+        }
+    }
+}
+
+impl<T,T2,O> Future for Race<T,T2,O> where T: Future<Output=O>, T2: Future<Output=O> {
+    type Output = O;
+
+    fn poll(self: Pin<&mut Self>, lw: &LocalWaker) -> Poll<O> {
+        let this = unsafe { Pin::get_mut_unchecked(self) };
+
+        *this.wakeups.parent.lock().unwrap() = Some(lw.as_waker().clone());
+
+        let woken = this.wakeups.take();
+
+        let mut poll = Poll::Pending;
+
+        if woken[0] {
+            if let Some(ref mut first) = this.first {
+                poll = unsafe { Pin::new_unchecked(first) }.poll(&this.wakers[0]);
+            }
+        }
+
+        if poll.is_pending() && woken[1] {
+            if let Some(ref mut second) = this.second {
+                poll = unsafe { Pin::new_unchecked(second) }.poll(&this.wakers[1]);
+            }
+        }
+
+        if poll.is_ready() {
+            this.first = None;
+            this.second = None;
+        }
+
+        poll
+    }
+}
+
+/// A tuple of `Later`s that can be waited on by `wait_all`
+///
+/// This is implemented for tuples of two to four `Later`s.
+pub trait WaitAll {
+    /// The outputs of the `Later`s
+    type Output;
+
+    /// Block until every `Later` has completed and return their outputs
+    fn wait_all(self) -> Self::Output;
+}
+
+macro_rules! wait_all_tuple {
+    ( $( $later:ident : $T:ident, $O:ident ),+ ) => {
+        impl<$($T, $O),+> WaitAll for ( $(Later<$T>,)+ ) where $($T: Future<Output=$O>),+ {
+            type Output = ( $($O,)+ );
+
+            fn wait_all(self) -> Self::Output {
+                let ( $(mut $later,)+ ) = self;
+
+                let waker = MainWaker::new();
+
+                let lw = waker.local();
+
+                loop {
+                    let mut pending = false;
+
+                    $( pending |= $later.poll_ready(&lw).is_pending(); )+
+
+                    if !pending {
+                        break ( $($later.into_inner(),)+ );
+                    }
+
+                    let mut deadline = None;
+
+                    $( deadline = $crate::earliest(deadline, $later.deadline()); )+
+
+                    $crate::wait_blocking_until(&waker, deadline);
+                }
+            }
+        }
+    };
+}
+
+wait_all_tuple!(a: T1, O1, b: T2, O2);
+wait_all_tuple!(a: T1, O1, b: T2, O2, c: T3, O3);
+wait_all_tuple!(a: T1, O1, b: T2, O2, c: T3, O3, d: T4, O4);
+
+/// Block until every one of a tuple of `Later`s has completed and return their outputs
+///
+/// All the futures are polled from the one wait, so this takes as long as the slowest future
+/// instead of the sum of them all as it would by getting the outputs one after the other. The
+/// `Later`s can have different output types.
+///
+/// # Example
+/// ```rust
+/// # #![feature(futures_api)]
+/// # #![feature(async_await)]
+/// # #[macro_use] extern crate alligator;
+/// # async fn fetch_profile() -> &'static str { "gpace" }
+/// # async fn fetch_friends() -> Vec<u32> { vec![1, 2] }
+/// # async fn fetch_unread() -> u32 { 5 }
+/// # fn main() {
+/// let (profile, friends, unread) = alligator::wait_all((
+///     l!{ fetch_profile() },
+///     l!{ fetch_friends() },
+///     l!{ fetch_unread() },
+/// ));
+/// # assert_eq!((profile, friends, unread), ("gpace", vec![1, 2], 5));
+/// # }
+/// ```
+///
+/// # Panic
+/// This panics if the output of any of the `Later`s was taken with `take_value`, or once any of
+/// them is cancelled or its deadline passes
+pub fn wait_all<L>(laters: L) -> L::Output where L: WaitAll {
+    laters.wait_all()
+}
@@ -0,0 +1,149 @@
+use std::cell::Cell;
+use std::future::Future;
+use std::marker::PhantomData;
+
+use super::Poller;
+
+/// An enum for switching between a closure that creates a future and the future's Output
+enum FnPair<F,O> {
+    Fn(F),
+    Val(O),
+    None,
+}
+
+impl<F,T,O> FnPair<F,O> where F: FnOnce() -> T, T: Future<Output=O> {
+
+    /// Get a reference to the contained value
+    ///
+    /// If self is a `Fn` then the closure is called and the returned future is polled to
+    /// completion.
+    fn get_ref_from_cell(cell: &Cell<Self>) -> &mut O {
+        match unsafe { &mut *cell.as_ptr() } {
+            FnPair::Val(ref mut val) => val,
+            FnPair::Fn(_) => {
+                cell.set(FnPair::Val(cell.take().into()));
+                Self::get_ref_from_cell(&cell)
+            },
+            _ => panic!("Report a bug if you get this panic"),
+        }
+    }
+
+    /// Convert self into O
+    fn into( self ) -> O {
+        match self {
+            FnPair::Val(v) => v,
+            FnPair::Fn(f) => Poller::new(f()).poll_to_completion(),
+            _ => panic!("Report a bug if you get this panic"),
+        }
+    }
+}
+
+impl<F,O> Default for FnPair<F,O> {
+    fn default() -> Self {
+        FnPair::None
+    }
+}
+
+/// A wrapper that defers the creation of a future until its output is needed
+///
+/// `Later::new` creates the future and polls it once, so any side effects of starting the future
+/// happen when the `Later` is created. `LaterFn` holds the closure that creates the future instead
+/// and will only call it (and then poll the future to completion) at the first call to a function
+/// that returns the output or a reference to the output.
+///
+/// # Example
+/// ```rust
+/// # #![feature(futures_api)]
+/// # #![feature(async_await)]
+/// # extern crate alligator;
+/// # use alligator::LaterFn;
+/// # use std::cell::Cell;
+/// # async fn connect(port: u16) -> u16 { port }
+/// # fn main() {
+/// let attempts = Cell::new(0);
+///
+/// let connection = LaterFn::new(|| {
+///     attempts.set(attempts.get() + 1);
+///
+///     connect(5432)
+/// });
+///
+/// assert!(!connection.is_started());
+/// assert_eq!(attempts.get(), 0);
+///
+/// assert_eq!(*connection, 5432);
+/// assert_eq!(*connection, 5432);
+///
+/// assert!(connection.is_started());
+/// assert_eq!(attempts.get(), 1);
+/// # }
+/// ```
+pub struct LaterFn<F,T,O> where F: FnOnce() -> T, T: Future<Output=O> {
+    fn_pair: Cell<FnPair<F,O>>,
+    future: PhantomData<fn() -> T>,
+}
+
+impl<F,T,O> LaterFn<F,T,O> where F: FnOnce() -> T, T: Future<Output=O> {
+
+    /// Create a new `LaterFn` with the closure for creating the future
+    ///
+    /// The closure is not called by `new`.
+    pub fn new( func: F ) -> Self {
+        LaterFn {
+            fn_pair: Cell::new( FnPair::Fn(func) ),
+            future: PhantomData,
+        }
+    }
+
+    /// Check if the closure has been called
+    pub fn is_started(&self) -> bool {
+        match unsafe { &*self.fn_pair.as_ptr() } {
+            FnPair::Fn(_) => false,
+            _ => true,
+        }
+    }
+
+    /// Consume self and return the output of the future
+    pub fn into_inner(self) -> O {
+        self.fn_pair.into_inner().into()
+    }
+}
+
+impl<F,T,O> LaterFn<F,T,O> where F: FnOnce() -> T, T: Future<Output=O>, O: Clone {
+
+    /// Get the output value of the future
+    pub fn get(&self) -> O {
+        FnPair::get_ref_from_cell(&self.fn_pair).clone()
+    }
+}
+
+/// Dereference the output value of the future, calling the closure and waiting for the future if
+/// this is the first access
+///
+/// Unlike a `Later`, this isn't changed by the `strict_deref` feature. The closure is only called
+/// by the first access, so a `LaterFn` has no explicit accessor to wait for the output before it's
+/// dereferenced.
+impl<F,T,O> ::std::ops::Deref for LaterFn<F,T,O> where F: FnOnce() -> T, T: Future<Output=O> {
+    type Target = O;
+
+    fn deref(&self) -> &O {
+        FnPair::get_ref_from_cell(&self.fn_pair)
+    }
+}
+
+impl<F,T,O> ::std::ops::DerefMut for LaterFn<F,T,O> where F: FnOnce() -> T, T: Future<Output=O> {
+    fn deref_mut(&mut self) -> &mut O {
+        FnPair::get_ref_from_cell(&self.fn_pair)
+    }
+}
+
+/// Display the output value of the future
+impl<F,T,O> ::std::fmt::Display for LaterFn<F,T,O>
+    where F: FnOnce() -> T, T: Future<Output=O>, O: ::std::fmt::Display
+{
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        use std::ops::Deref;
+
+        self.deref().fmt(f)
+    }
+}
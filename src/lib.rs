@@ -3,6 +3,12 @@
 #![feature(async_await)]
 #![feature(await_macro)]
 #![feature(arbitrary_self_types)]
+#![feature(unboxed_closures)]
+#![feature(fn_traits)]
+#![feature(const_fn)]
+#![feature(core_intrinsics)]
+#![feature(termination_trait_lib)]
+#![cfg_attr(feature = "allocator_api", feature(allocator_api))]
 
 //! # Alligator 🐊
 //!
@@ -53,21 +59,158 @@
 //! println!("{}", do_later);
 //! # }
 //!```
+//! # Features
+//! The `Later` type (along with `LaterFn`, `LaterExt`, and `promise`) is always available,
+//! everything else is behind a cargo feature so that only what is used gets compiled.
+//!
+//! - `macros` *(default)*: the `l!` and `later!` macros, `wait_any!` along with `combinators`, and
+//!   `later_static!` along with `static`
+//! - `combinators`: `join_all`, `race_ok`, `wait_all`, `select`, `select_take`, `wait_any`,
+//!   `Later::zip`, `Later::race`, `LaterSet`, `LaterGroup`, `LaterMap`, and `Pipeline`
+//! - `cooperative`: `Later::cooperative`, for futures that are polled while their thread waits on
+//!   other `Later`s
+//! - `debug`: a registry of the threads currently blocked on `Later`s, for finding hangs
+//! - `stream`: `LaterStream` and `collect_later`
+//! - `io`: adapters between asynchronous and synchronous io
+//! - `oneshot`: `Later::from_oneshot` for the oneshot channels of the `futures` crate
+//! - `pool`: a thread pool for polling futures in the background, `spawn`, `spawn_blocking`,
+//!   `Later::eager`, `LaterGroup::spawned` (with `combinators`), and polling detached futures on
+//!   the global pool, along with `time` for the deadlines of spawned futures
+//! - `profile`: markers around polls and waits for instrumenting profilers such as puffin or Tracy
+//! - `reactor`: a reactor thread that wakes futures waiting on io readiness, using mio
+//! - `scope`: spawning futures that borrow local data onto scoped helper threads
+//! - `static`: `StaticLater`, a `Later` for a `static` that doesn't allocate
+//! - `stats`: process wide statistics of how long futures took and how long threads waited for them
+//! - `sync`: `sync::OnceCell`, a cell initialized once by a future from sync or async code, and
+//!   `sync::SyncLater` and `sync::SharedLater`, `Later`s that can be shared between threads
+//! - `testing`: utilities for tests, such as a virtual clock
+//! - `time`: a timer thread along with the `sleep`, `sleep_until`, and `timeout` futures, and
+//!   `interval`
+//! - `trace`: recording of poll and wait timings
+//! - `full`: all of the above
+//!
+//! The `location` feature keeps the location where a `Later` was created (see
+//! [`Location`](./struct.Location.html)) in release builds, it is always kept in debug builds.
+//!
+//! The `strict_deref` feature is not part of `full`. It changes dereferencing, displaying, and
+//! calling a `Later` whose future hasn't completed to panic rather than wait, so that waiting for
+//! an output is always done through an explicit accessor. `LaterFn` is exempt, as its closure is
+//! only ever called by the first access, so every output would otherwise be unreachable.
+//!
+//! The `allocator_api` feature is not part of `full` either. It adds `Later::boxed_in` and
+//! `DynLaterIn`, the form of `DynLater` whose future is boxed in memory from an allocator of the
+//! unstable `Alloc` trait.
+//!
 //! # Note
 //! Unfortunately alligator isn't #[no_std] compatable. `Later` uses the std thread and sync
 //! mechanics to wait for a future to poll to completion.
 
+#[cfg(any(feature = "stream", feature = "io", feature = "oneshot"))]
+extern crate futures;
+#[cfg(feature = "reactor")]
+extern crate mio;
+
+#[cfg(feature = "allocator_api")]
+mod alloc;
+mod bridge;
+pub mod cancel;
+mod clock;
+#[cfg(feature = "cooperative")]
+mod cooperative;
+#[cfg(feature = "debug")]
+pub mod debug;
+#[cfg(not(feature = "pool"))]
+mod detach;
+pub mod error;
+mod ext;
+#[cfg(feature = "io")]
+pub mod io;
+#[cfg(feature = "combinators")]
+pub mod join;
+mod later_fn;
+mod limit;
+mod location;
+mod map;
+#[cfg(feature = "combinators")]
+pub mod pipeline;
+#[cfg(feature = "pool")]
+pub mod pool;
+mod promise;
+#[cfg(feature = "static")]
+mod static_later;
+mod ready;
+#[cfg(feature = "profile")]
+pub mod profile;
+#[cfg(feature = "reactor")]
+pub mod reactor;
+#[cfg(feature = "scope")]
+pub mod scope;
+#[cfg(feature = "combinators")]
+pub mod select;
+#[cfg(feature = "combinators")]
+mod set;
+#[cfg(feature = "stats")]
+pub mod stats;
+#[cfg(feature = "stream")]
+pub mod stream;
+#[cfg(feature = "sync")]
+pub mod sync;
+#[cfg(feature = "testing")]
+pub mod testing;
+#[cfg(feature = "time")]
+pub mod time;
+#[cfg(feature = "trace")]
+pub mod trace;
 
+#[cfg(feature = "allocator_api")]
+pub use alloc::{BoxedIn, DynLaterIn};
+pub use bridge::{Joined, Received};
+pub use cancel::{AbortHandle, CancellationToken};
+#[cfg(feature = "cooperative")]
+pub use cooperative::Cooperative;
+pub use error::{AccessError, Cancelled, ContextError, DeadlineExceeded, Elapsed, TimeoutError};
+pub use ext::LaterExt;
+#[cfg(feature = "combinators")]
+pub use join::{join_all, race_ok, wait_all, WaitAll};
+pub use later_fn::LaterFn;
+pub use limit::{blocked_limit, can_block, blocked_threads, set_blocked_limit, set_blocked_limit_handler};
+pub use location::Location;
+pub use map::{AndThen, Flatten, Map, MapInto};
+#[cfg(feature = "combinators")]
+pub use pipeline::Pipeline;
+#[cfg(feature = "pool")]
+pub use pool::{cancel_all, par_map, spawn, spawn_blocking, Pool, Priority};
+pub use promise::{promise, Promise, Promised};
+pub use ready::{Pending, Ready};
+#[cfg(feature = "scope")]
+pub use scope::scope;
+#[cfg(feature = "combinators")]
+pub use select::{select, select_take, wait_any, Any2, Any3, Any4, WaitAny, Waitable};
+#[cfg(feature = "combinators")]
+pub use set::{LaterGroup, LaterMap, LaterSet};
+#[cfg(feature = "static")]
+pub use static_later::StaticLater;
+#[cfg(feature = "stream")]
+pub use stream::{collect_later, LaterStream};
 
+use std::borrow::Cow;
 use std::cell::Cell;
 use std::future::Future;
 use std::pin::Pin;
 use std::sync::{Arc, Mutex, Condvar};
-use std::task::{Wake, Poll, LocalWaker};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::task::{Wake, Waker, Poll, LocalWaker};
+use std::time::{Duration, Instant};
+
+use limit::BlockedPermit;
+use map::Source;
 
 struct MainWaker {
     locker: Mutex<bool>,
     cvar: Condvar,
+    /// Also woken by a wake up, this is the waker of whatever last polled the future from outside
+    /// of its `Later` (such as `select`) so that it's woken when the future is cancelled
+    forward: Mutex<Option<Waker>>,
 }
 
 impl MainWaker {
@@ -75,6 +218,7 @@ impl MainWaker {
         Arc::new( Self {
             locker: Mutex::new(true),
             cvar: Condvar::new(),
+            forward: Mutex::new(None),
         })
     }
 
@@ -87,17 +231,84 @@ impl MainWaker {
     ///
     /// The wait on the cvar is prone to spurious wakeups, but this is ok so long as `wait` is
     /// called after the Poll::Pending is returned from a call to `poll` on a future.
+    ///
+    /// The flag is reset before returning so that the next call to `wait` will wait for the next
+    /// wake up.
     fn wait(&self) {
-        let flag_lock = self.locker.lock().unwrap();
+        let mut flag_lock = self.locker.lock().unwrap();
 
         if *flag_lock {
-            let _unused = self.cvar.wait(flag_lock).unwrap();
+            flag_lock = self.cvar.wait(flag_lock).unwrap();
+        }
+
+        *flag_lock = true;
+    }
+
+    /// Must be called after local
+    ///
+    /// This is the same as `wait` except that it will not wait past `deadline`. The return is
+    /// false if the deadline was reached without a wake up.
+    ///
+    /// The deadline is measured by the crate's clock, so when it is the virtual clock of
+    /// `testing::FakeTime` this waits for the virtual clock to change instead of for real time to
+    /// pass.
+    fn wait_until(self: &Arc<Self>, deadline: Instant) -> bool {
+        let mut flag_lock = self.locker.lock().unwrap();
+
+        while *flag_lock {
+            let fake = clock::is_fake();
+
+            // Registered before reading the clock so that an advance after the read still wakes
+            // this thread. The waker is removed from the virtual clock once this iteration is done.
+            #[cfg(feature = "testing")]
+            let _waiter = if fake {
+                Some(clock::fake::wake_on_change(self.local().into_waker()))
+            } else {
+                None
+            };
+
+            let now = clock::now();
+
+            if now >= deadline {
+                return false;
+            }
+
+            flag_lock = if fake {
+                self.cvar.wait(flag_lock).unwrap()
+            } else {
+                self.cvar.wait_timeout(flag_lock, deadline - now).unwrap().0
+            };
         }
+
+        *flag_lock = true;
+
+        true
     }
 
     fn release(&self) {
         *self.locker.lock().unwrap() = false;
-        self.cvar.notify_one()
+        self.cvar.notify_one();
+
+        // Not woken under the lock in case the waker wakes this one in turn
+        let forward = self.forward.lock().unwrap().clone();
+
+        if let Some(waker) = forward {
+            waker.wake()
+        }
+    }
+
+    /// Wake `lw` along with this waker from now on
+    fn forward_to(&self, lw: &LocalWaker) {
+        let mut forward = self.forward.lock().unwrap();
+
+        let stale = match *forward {
+            Some(ref waker) => !waker.will_wake(lw.as_waker()),
+            None => true,
+        };
+
+        if stale {
+            *forward = Some(lw.as_waker().clone());
+        }
     }
 }
 
@@ -107,10 +318,132 @@ impl Wake for MainWaker {
     }
 }
 
+/// Block the current thread until `waker` is woken, as a thread blocked on `Later`s
+///
+/// This is the wait of everything that polls futures with a `MainWaker` of its own instead of
+/// through a `Poller`, such as `select`, `wait_all`, and `LaterSet`. See `block_until_woken`.
+#[cfg(any(feature = "combinators", feature = "stream"))]
+pub(crate) fn wait_blocking(waker: &Arc<MainWaker>) {
+    block_until_woken(&waker.local(), || waker.wait())
+}
+
+/// Block the current thread until `waker` is woken or `deadline` passes
+///
+/// This is `wait_blocking` for helpers that wait on `Later`s, with the earliest deadline of the
+/// `Later`s so that a `Later` is seen to be stopped once its deadline passes.
+#[cfg(feature = "combinators")]
+pub(crate) fn wait_blocking_until(waker: &Arc<MainWaker>, deadline: Option<Instant>) {
+    block_until_woken(&waker.local(), || match deadline {
+        Some(deadline) => {
+            waker.wait_until(deadline);
+        },
+        None => waker.wait(),
+    })
+}
+
+/// Block the current thread with `wait`, which must return once `lw` is woken
+///
+/// The wait holds a `BlockedPermit` so that it counts towards the blocked limit, and it is in the
+/// registry of blocked threads of the `debug` feature. The cooperative futures of the thread are
+/// polled with `lw` before it waits.
+///
+/// # Panic
+/// This panics if the current thread can't block. If the blocked limit is reached the blocked
+/// limit handler is called before waiting.
+#[cfg(any(feature = "combinators", feature = "stream", feature = "static"))]
+#[cfg_attr(not(feature = "cooperative"), allow(unused_variables))]
+pub(crate) fn block_until_woken<F>(lw: &LocalWaker, wait: F) where F: FnOnce() {
+    let _permit = BlockedPermit::acquire_or_handle();
+
+    #[cfg(feature = "debug")]
+    let _registration = debug::register(NEXT_ID.fetch_add(1, Ordering::Relaxed), None, None);
+
+    #[cfg(feature = "cooperative")]
+    cooperative::drive_siblings(lw);
+
+    wait()
+}
+
+#[cfg(feature = "pool")]
+thread_local! {
+    /// Whether the current thread is polling the future of a `Later` that is being forced
+    static FORCING: ::std::cell::Cell<bool> = ::std::cell::Cell::new(false);
+}
+
+/// Check if the current thread is polling the future of a `Later` that is being forced
+///
+/// The pool uses this to boost the futures that the forcing thread is waiting on.
+#[cfg(feature = "pool")]
+pub(crate) fn is_forcing() -> bool {
+    FORCING.with(|f| f.get())
+}
+
+/// Marks the current thread as forcing a `Later` until dropped
+#[cfg(feature = "pool")]
+struct Forcing {
+    previous: bool,
+}
+
+#[cfg(feature = "pool")]
+impl Forcing {
+    fn enter() -> Self {
+        Forcing { previous: FORCING.with(|f| f.replace(true)) }
+    }
+}
+
+#[cfg(feature = "pool")]
+impl Drop for Forcing {
+    fn drop(&mut self) {
+        let previous = self.previous;
+
+        FORCING.with(|f| f.set(previous))
+    }
+}
+
+/// The tokens and the deadline that stop a future, taken from one `Poller` to be watched by another
+#[derive(Default)]
+struct Stops {
+    cancel: Vec<CancellationToken>,
+    deadline: Option<Instant>,
+    /// Where the future puts the stops of the `Later`s it consumes while it's polled
+    adopted: Vec<Arc<Mutex<Stops>>>,
+}
+
+/// Get the earlier of two optional deadlines
+fn earliest(a: Option<Instant>, b: Option<Instant>) -> Option<Instant> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(if a < b { a } else { b }),
+        (a, None) => a,
+        (None, b) => b,
+    }
+}
+
+/// The id of the next created `Poller`
+static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
 /// A structure for polling a future
 struct Poller<T,O> where T: Future<Output=O> {
-    future: T,
+    /// Only `None` while the future is being moved out by `into_future`
+    future: Option<T>,
     waker: Arc<MainWaker>,
+    id: usize,
+    polls: usize,
+    budget: Option<usize>,
+    panicked: bool,
+    /// The tokens that cancel the future, with the keys of the waker registered with each
+    cancel: Vec<(CancellationToken, usize)>,
+    /// When the future is given up on if it hasn't completed
+    deadline: Option<Instant>,
+    /// The stops of the `Later`s consumed by the future, watched after every poll
+    adopted: Vec<Arc<Mutex<Stops>>>,
+    #[cfg(feature = "stats")]
+    first_poll: Option<Instant>,
+    #[cfg(feature = "stats")]
+    blocked: Duration,
+    #[cfg(any(feature = "profile", feature = "debug"))]
+    label: Option<Cow<'static, str>>,
+    #[cfg(any(feature = "profile", feature = "debug"))]
+    location: Option<Location>,
 }
 
 impl<T,O> Poller<T,O> where T: Future<Output=O> {
@@ -119,162 +452,2354 @@ impl<T,O> Poller<T,O> where T: Future<Output=O> {
         let waker = MainWaker::new();
 
         Poller {
-            future: future,
+            future: Some(future),
             waker: waker.clone(),
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            polls: 0,
+            budget: None,
+            panicked: false,
+            cancel: Vec::new(),
+            deadline: None,
+            adopted: Vec::new(),
+            #[cfg(feature = "stats")]
+            first_poll: None,
+            #[cfg(feature = "stats")]
+            blocked: Duration::from_secs(0),
+            #[cfg(any(feature = "profile", feature = "debug"))]
+            label: None,
+            #[cfg(any(feature = "profile", feature = "debug"))]
+            location: None,
+        }
+    }
+
+    /// Create a new `Poller` of a clone of the future with the same poll budget, cancellation, and
+    /// deadline
+    ///
+    /// The clone of the future continues from the state of the future, but its polls are counted
+    /// from zero.
+    fn duplicate(&self) -> Self where T: Clone {
+        let mut poller = Poller::new(self.future.clone().expect("Report a bug if you get this panic"));
+
+        poller.budget = self.budget;
+        poller.deadline = self.deadline;
+
+        for &(ref token, _) in self.cancel.iter() {
+            poller.watch(token.clone());
+        }
+
+        poller
+    }
+
+    /// Move the future out of the poller
+    ///
+    /// The waker of the poller is unregistered from the tokens that were watched, as the poller is
+    /// dropped.
+    fn into_future(mut self) -> T {
+        self.future.take().expect("Report a bug if you get this panic")
+    }
+
+    /// Cancel the future when `token` is cancelled
+    ///
+    /// The waker of the poller is registered with the token so that a thread waiting on the future
+    /// is woken by the cancellation.
+    fn watch(&mut self, token: CancellationToken) {
+        let key = token.register(self.waker.local().into_waker());
+
+        self.cancel.push((token, key));
+    }
+
+    /// Take the tokens and the deadline that stop the future, for another poller to watch instead
+    fn take_stops(&mut self) -> Stops {
+        self.adopt();
+
+        Stops {
+            cancel: self.cancel.drain(..).map(|(token, key)| {
+                token.unregister(key);
+                token
+            }).collect(),
+            deadline: self.deadline.take(),
+            adopted: ::std::mem::replace(&mut self.adopted, Vec::new()),
+        }
+    }
+
+    /// Also stop the future by the tokens and the deadline taken from another poller
+    ///
+    /// The deadline of the poller becomes the earlier of the two deadlines.
+    fn add_stops(&mut self, stops: Stops) {
+        for token in stops.cancel {
+            self.watch(token);
+        }
+
+        self.deadline = earliest(self.deadline, stops.deadline);
+
+        self.adopted.extend(stops.adopted);
+    }
+
+    /// Watch the stops that the future adopted from the `Later`s it consumed since the last poll
+    fn adopt(&mut self) {
+        let adopted: Vec<Stops> = self.adopted.iter()
+            .map(|stops| ::std::mem::replace(&mut *stops.lock().unwrap(), Stops::default()))
+            .collect();
+
+        for stops in adopted {
+            self.add_stops(stops);
+        }
+    }
+
+    /// Get the reason the future must not be polled again, if a token watched by the poller was
+    /// cancelled (`AccessError::Cancelled`) or the deadline passed (`AccessError::Elapsed`)
+    fn stopped(&self) -> Option<AccessError> {
+        if self.cancel.iter().any(|&(ref token, _)| token.is_cancelled()) {
+            return Some(AccessError::Cancelled);
+        }
+
+        match self.deadline {
+            Some(deadline) if clock::now() >= deadline => Some(AccessError::Elapsed),
+            _ => None,
+        }
+    }
+
+    /// Poll the future, recording the poll if tracing is started
+    fn poll(&mut self) -> Poll<O> {
+        let lw = self.waker.local();
+
+        self.poll_with(&lw)
+    }
+
+    /// Poll the future with a waker other than the poller's own
+    fn poll_with(&mut self, lw: &LocalWaker) -> Poll<O> {
+        self.polls += 1;
+
+        #[cfg(feature = "trace")]
+        let start = trace::now();
+
+        #[cfg(feature = "stats")]
+        let first_poll = *self.first_poll.get_or_insert_with(Instant::now);
+
+        #[cfg(feature = "profile")]
+        let _scope = self.profile(profile::Kind::Poll);
+
+        if self.panicked {
+            poisoned()
+        }
+
+        // Left set if the poll unwinds
+        self.panicked = true;
+
+        let poll = unsafe { Pin::new_unchecked(self.future.as_mut().expect("Report a bug if you get this panic")) }.poll(lw);
+
+        self.panicked = false;
+
+        if poll.is_ready() {
+            for (token, key) in self.cancel.drain(..) {
+                token.unregister(key);
+            }
+        } else {
+            self.adopt();
+        }
+
+        #[cfg(feature = "trace")]
+        {
+            if let Some(start) = start {
+                trace::record(trace::Kind::Poll, self.id, start);
+
+                if poll.is_ready() {
+                    trace::record(trace::Kind::Complete, self.id, Instant::now());
+                }
+            }
+        }
+
+        #[cfg(feature = "stats")]
+        {
+            if poll.is_ready() {
+                stats::record(first_poll.elapsed(), self.blocked);
+            }
+        }
+
+        poll
+    }
+
+    /// Wait for the future to wake this thread, recording the wait if tracing is started
+    ///
+    /// The cooperative futures of the thread are polled before it waits.
+    fn wait(&mut self) {
+        #[cfg(feature = "cooperative")]
+        cooperative::drive_siblings(&self.waker.local());
+
+        #[cfg(feature = "trace")]
+        let start = trace::now();
+
+        #[cfg(feature = "stats")]
+        let blocked = Instant::now();
+
+        #[cfg(feature = "profile")]
+        let _scope = self.profile(profile::Kind::Park);
+
+        self.waker.wait();
+
+        #[cfg(feature = "stats")]
+        {
+            self.blocked += blocked.elapsed();
+        }
+
+        #[cfg(feature = "trace")]
+        {
+            if let Some(start) = start {
+                trace::record(trace::Kind::Park, self.id, start);
+            }
+        }
+    }
+
+    /// Enter a profiler scope for this poller
+    #[cfg(feature = "profile")]
+    fn profile(&self, kind: profile::Kind) -> Option<Box<dyn ::std::any::Any>> {
+        profile::enter(kind, self.label.as_ref().map(|l| &**l), self.location.as_ref(), profile::type_name::<T>())
+    }
+
+    /// Register this poller as blocking the current thread until the returned guard is dropped
+    #[cfg(feature = "debug")]
+    fn register_blocked(&self) -> debug::Registration {
+        debug::register(self.id, self.label.clone(), self.location.clone())
+    }
+
+    /// Get the error for exceeding the poll budget, if it was exceeded
+    fn over_budget(&self) -> Option<AccessError> {
+        match self.budget {
+            Some(budget) if self.polls >= budget => Some( AccessError::PollBudgetExceeded {
+                type_name: unsafe { ::std::intrinsics::type_name::<T>() },
+                polls: self.polls,
+            }),
+            _ => None,
         }
     }
 
     fn poll_once(mut self) -> FuturePair<T,O> {
-        match unsafe { Pin::new_unchecked(&mut self.future) }.poll(&self.waker.local()) {
+        match self.poll() {
             Poll::Ready(val) => FuturePair::Val(val),
             Poll::Pending    => FuturePair::Fut(self),
         }
     }
 
-    fn poll_to_completion(mut self) -> O {
+    /// Wait for the future to wake this thread, but no longer than the deadline of the poller if
+    /// it has one
+    fn park(&mut self) {
+        match self.deadline {
+            Some(deadline) => {
+                self.wait_until(deadline);
+            },
+            None => self.wait(),
+        }
+    }
+
+    /// Wait for the future to wake this thread but no longer than `deadline`, recording the wait
+    /// if tracing is started
+    ///
+    /// The cooperative futures of the thread are polled before it waits. The return is false if
+    /// the deadline was reached.
+    fn wait_until(&mut self, deadline: Instant) -> bool {
+        #[cfg(feature = "cooperative")]
+        cooperative::drive_siblings(&self.waker.local());
+
+        #[cfg(feature = "trace")]
+        let start = trace::now();
+
+        #[cfg(feature = "stats")]
+        let blocked = Instant::now();
+
+        let woken = {
+            #[cfg(feature = "profile")]
+            let _scope = self.profile(profile::Kind::Park);
+
+            self.waker.wait_until(deadline)
+        };
+
+        #[cfg(feature = "stats")]
+        {
+            self.blocked += blocked.elapsed();
+        }
+
+        #[cfg(feature = "trace")]
+        {
+            if let Some(start) = start {
+                trace::record(trace::Kind::Park, self.id, start);
+            }
+        }
+
+        woken
+    }
+
+    /// Poll the future to completion
+    ///
+    /// If the blocked limit is reached when this thread would first wait, the blocked limit
+    /// handler is called before waiting.
+    ///
+    /// # Panic
+    /// This panics if the future is cancelled or its deadline passes
+    fn poll_to_completion(self) -> O {
+        self.poll_to_completion_or_stop().unwrap_or_else(|error| stopped(error))
+    }
+
+    /// Poll the future to completion unless it's cancelled or its deadline passes first
+    ///
+    /// The error is returned once a token watched by the poller is cancelled or the deadline of
+    /// the poller passes, the future isn't polled again after that even if it was woken at the same
+    /// time.
+    fn poll_to_completion_or_stop(mut self) -> Result<O, AccessError> {
+        #[cfg(feature = "pool")]
+        let _forcing = Forcing::enter();
+
+        let mut permit = None;
+
+        #[cfg(feature = "debug")]
+        let mut registration = None;
+
+        loop {
+            if let Some(error) = self.stopped() {
+                break Err(error);
+            }
+
+            match self.poll() {
+                Poll::Ready(val) => break Ok(val),
+                Poll::Pending    => {
+                    if let Some(error) = self.over_budget() {
+                        panic!("{}", error);
+                    }
+
+                    permit.get_or_insert_with(BlockedPermit::acquire_or_handle);
+
+                    #[cfg(feature = "debug")]
+                    registration.get_or_insert_with(|| self.register_blocked());
+
+                    self.park()
+                },
+            }
+        }
+    }
+
+    /// Poll the future to completion unless the blocked limit is reached or this thread can't block
+    ///
+    /// An error is returned if this thread would have waited while it can't.
+    fn try_poll_to_completion(&mut self) -> Result<O, AccessError> {
+        if self.panicked {
+            return Err(AccessError::Poisoned);
+        }
+
+        #[cfg(feature = "pool")]
+        let _forcing = Forcing::enter();
+
+        let mut permit = None;
+
+        #[cfg(feature = "debug")]
+        let mut registration = None;
+
+        loop {
+            if let Some(error) = self.stopped() {
+                break Err(error);
+            }
+
+            match self.poll() {
+                Poll::Ready(val) => break Ok(val),
+                Poll::Pending    => {
+                    if let Some(error) = self.over_budget() {
+                        break Err(error);
+                    }
+
+                    if permit.is_none() {
+                        permit = Some(BlockedPermit::try_acquire()?);
+                    }
+
+                    #[cfg(feature = "debug")]
+                    registration.get_or_insert_with(|| self.register_blocked());
+
+                    self.park()
+                },
+            }
+        }
+    }
+
+    /// Poll the future until it completes, the deadline is reached, or it's stopped
+    ///
+    /// The future is always polled at least once unless it was cancelled or the deadline of the
+    /// poller passed, even if `deadline` has passed. The wait is
+    /// counted as a blocked thread but is never refused by the blocked limit. On a thread that
+    /// can't block this returns after the first poll.
+    fn poll_until(&mut self, deadline: Instant) -> Poll<O> {
+        let deadline = match self.deadline {
+            Some(own) if own < deadline => own,
+            _ => deadline,
+        };
+
+        #[cfg(feature = "pool")]
+        let _forcing = Forcing::enter();
+
+        let mut permit = None;
+
+        #[cfg(feature = "debug")]
+        let mut registration = None;
+
         loop {
-            match unsafe { Pin::new_unchecked(&mut self.future) }.poll(&self.waker.local()) {
-                Poll::Ready(val) => break val,
-                Poll::Pending    => self.waker.wait(),
+            if self.stopped().is_some() {
+                break Poll::Pending;
+            }
+
+            match self.poll() {
+                Poll::Ready(val) => break Poll::Ready(val),
+                Poll::Pending => {
+                    if self.over_budget().is_some() {
+                        break Poll::Pending
+                    }
+
+                    if permit.is_none() {
+                        match BlockedPermit::acquire() {
+                            Some(acquired) => permit = Some(acquired),
+                            None => break Poll::Pending,
+                        }
+                    }
+
+                    #[cfg(feature = "debug")]
+                    registration.get_or_insert_with(|| self.register_blocked());
+
+                    if !self.wait_until(deadline) {
+                        break Poll::Pending
+                    }
+                },
             }
         }
     }
 }
 
+impl<T,O> Drop for Poller<T,O> where T: Future<Output=O> {
+    fn drop(&mut self) {
+        for (token, key) in self.cancel.drain(..) {
+            token.unregister(key);
+        }
+    }
+}
+
 /// An enum for switching between a Future object and its Output
+///
+/// `Empty` is for when the output was taken out of a `Later` that is still alive, where as `None`
+/// is only a placeholder while the cell is being worked on. A `None` found by an accessor means
+/// that the future panicked while it was being polled. `Stopped` replaces a `Fut` once the future
+/// is cancelled or its deadline passes, dropping the future, and holds the error for accessors.
 enum FuturePair<T,O> where T: Future<Output=O> {
     Fut(Poller<T,O>),
     Val(O),
+    Empty,
+    Stopped(AccessError),
     None,
 }
 
+/// Panic for accessing the output of a `Later` whose future panicked
+fn poisoned() -> ! {
+    panic!("the future of a Later panicked while it was polled")
+}
+
+/// Panic for accessing the output of a `Later` whose future was cancelled or whose deadline passed
+fn stopped(error: AccessError) -> ! {
+    panic!("{}", error)
+}
+
+/// Panic for accessing the output of a `Later` that was taken
+fn taken() -> ! {
+    panic!("the output of a Later was accessed after it was taken with `take_value` or `take_future`")
+}
+
 impl<T,O> FuturePair<T,O> where T: Future<Output=O> {
 
     /// If self is a `Fut` then the future is polled to completion and self turned into a `Val`
-    /// containing the future output.
+    /// containing the future output, or into `Stopped` if the future is stopped first.
     ///
     /// This panics if self is not a `Fut`
     fn poll_into_val(self) -> Self {
         match self {
-            FuturePair::Fut(poller) => {
-                FuturePair::Val(poller.poll_to_completion())
+            FuturePair::Fut(poller) => match poller.poll_to_completion_or_stop() {
+                Ok(val) => FuturePair::Val(val),
+                Err(error) => FuturePair::Stopped(error),
             },
             _ => panic!("Report a bug if you get this panic"),
         }
     }
 
+    /// Turn self into `Stopped` if it's the `Fut` of a stopped future
+    fn settled(self) -> Self {
+        let error = match self {
+            FuturePair::Fut(ref poller) => poller.stopped(),
+            _ => None,
+        };
+
+        match error {
+            Some(error) => FuturePair::Stopped(error),
+            None => self,
+        }
+    }
+
+    /// Set the cell to `Stopped` if it's the `Fut` of a stopped future
+    fn settle(cell: &Cell<Self>) {
+        let error = match unsafe { &*cell.as_ptr() } {
+            FuturePair::Fut(ref poller) => poller.stopped(),
+            _ => None,
+        };
+
+        if let Some(error) = error {
+            cell.set(FuturePair::Stopped(error));
+        }
+    }
+
     /// Get a reference to the contained value
     fn get_ref_from_cell(cell: &Cell<Self>) -> &mut O {
+        Self::settle(cell);
+
         match unsafe { &mut *cell.as_ptr() } {
             FuturePair::Val(ref mut val) => val,
             FuturePair::Fut(_) => {
                 cell.set(cell.take().poll_into_val());
                 Self::get_ref_from_cell(&cell)
             },
-            _ => panic!("Report a bug if you get this panic"),
+            FuturePair::Empty => taken(),
+            FuturePair::Stopped(error) => stopped(error),
+            FuturePair::None => poisoned(),
         }
     }
 
-    /// Convert self into O
-    fn into( self ) -> O {
-        match self {
-            FuturePair::Val(v) => v,
-            FuturePair::Fut(f) => f.poll_to_completion(),
-            _ => panic!("Report a bug if you get this panic"),
+    /// Get a reference to the contained value, waiting no longer than `deadline`
+    ///
+    /// `None` is returned if the future didn't complete before the deadline. The cell is left as
+    /// a `Fut` so that it can be waited on again.
+    fn get_ref_from_cell_until(cell: &Cell<Self>, deadline: Instant) -> Option<&mut O> {
+        Self::settle(cell);
+
+        let val = match unsafe { &mut *cell.as_ptr() } {
+            FuturePair::Val(_) => None,
+            FuturePair::Fut(ref mut poller) => match poller.poll_until(deadline) {
+                Poll::Ready(val) => Some(val),
+                // A stopped future is settled by `get_ref_from_cell`
+                Poll::Pending if poller.stopped().is_some() => None,
+                Poll::Pending => return None,
+            },
+            FuturePair::Empty => taken(),
+            FuturePair::Stopped(error) => stopped(error),
+            FuturePair::None => poisoned(),
+        };
+
+        if let Some(val) = val {
+            cell.set(FuturePair::Val(val));
         }
+
+        Some(Self::get_ref_from_cell(cell))
     }
-}
 
-impl<T,O> FuturePair<T,O>  where T: Future<Output=O>, O: Clone {
-    /// Return a clone from a cell
+    /// Get a reference to the contained value unless waiting for it would exceed the blocked
+    /// limit or the current thread can't block
     ///
-    /// If self is a FuturePair::Fut(Poller<T,O>) then the future is polled to completion and self
-    /// is set FuturePair::Val(O).
-    fn clone_in_cell(cell: &Cell<Self>) -> Self {
-        let val = match cell.take() {
-            FuturePair::Val(val) => val,
-            FuturePair::Fut(fut) => fut.poll_to_completion(),
-            _ => panic!("Report a bug if you get this panic"),
+    /// On an error the cell is left as a `Fut`, unless the error is that the future was stopped.
+    fn try_get_ref_from_cell(cell: &Cell<Self>) -> Result<&mut O, AccessError> {
+        Self::settle(cell);
+
+        let val = match unsafe { &mut *cell.as_ptr() } {
+            FuturePair::Val(_) => None,
+            FuturePair::Fut(ref mut poller) => Some(poller.try_poll_to_completion()),
+            FuturePair::Empty => return Err(AccessError::Taken),
+            FuturePair::Stopped(error) => return Err(error),
+            FuturePair::None => return Err(AccessError::Poisoned),
         };
 
-        cell.set(FuturePair::Val(val.clone()));
+        match val {
+            Some(Ok(val)) => cell.set(FuturePair::Val(val)),
+            Some(Err(error @ AccessError::Cancelled)) | Some(Err(error @ AccessError::Elapsed)) => {
+                cell.set(FuturePair::Stopped(error));
 
-        FuturePair::Val(val)
+                return Err(error);
+            },
+            Some(Err(error)) => return Err(error),
+            None => (),
+        }
+
+        Ok(Self::get_ref_from_cell(cell))
     }
-}
 
-impl<T,O> Default for FuturePair<T,O> where T: Future<Output=O> {
+    /// Get a reference to the contained value, polling the future once if it hasn't completed
+    ///
+    /// `None` is returned if the future is still pending, the value was taken, or the future was
+    /// stopped.
+    fn poll_ref_from_cell(cell: &Cell<Self>) -> Option<&mut O> {
+        Self::settle(cell);
+
+        let val = match unsafe { &mut *cell.as_ptr() } {
+            FuturePair::Val(_) => None,
+            FuturePair::Fut(ref mut poller) => match poller.poll() {
+                Poll::Ready(val) => Some(val),
+                Poll::Pending => return None,
+            },
+            FuturePair::Empty | FuturePair::Stopped(_) => return None,
+            FuturePair::None => poisoned(),
+        };
+
+        if let Some(val) = val {
+            cell.set(FuturePair::Val(val));
+        }
+
+        Some(Self::get_ref_from_cell(cell))
+    }
+
+    /// Take the value out of the cell, leaving it `Empty`
+    ///
+    /// If the cell is a `Fut` then the future is polled to completion. `None` is returned if the
+    /// cell is already `Empty`.
+    ///
+    /// This panics, leaving the cell `Stopped`, if the future was stopped
+    fn take_from_cell(cell: &Cell<Self>) -> Option<O> {
+        let val = match cell.replace(FuturePair::None).settled() {
+            FuturePair::Val(val) => Some(val),
+            FuturePair::Fut(poller) => match poller.poll_to_completion_or_stop() {
+                Ok(val) => Some(val),
+                Err(error) => {
+                    cell.set(FuturePair::Stopped(error));
+                    stopped(error)
+                },
+            },
+            FuturePair::Empty => None,
+            FuturePair::Stopped(error) => {
+                cell.set(FuturePair::Stopped(error));
+                stopped(error)
+            },
+            FuturePair::None => poisoned(),
+        };
+
+        cell.set(FuturePair::Empty);
+
+        val
+    }
+
+    /// Convert self into the remaining work of the future
+    fn into_source( self ) -> Source<T,O> {
+        match self.settled() {
+            FuturePair::Fut(poller) => Source::Fut(poller.into_future()),
+            FuturePair::Val(val) => Source::Val(Some(val)),
+            FuturePair::Empty => taken(),
+            FuturePair::Stopped(error) => stopped(error),
+            FuturePair::None => poisoned(),
+        }
+    }
+
+    /// Convert self into O
+    fn into( self ) -> O {
+        match self {
+            FuturePair::Val(v) => v,
+            FuturePair::Fut(f) => f.poll_to_completion(),
+            FuturePair::Empty => taken(),
+            FuturePair::Stopped(error) => stopped(error),
+            FuturePair::None => poisoned(),
+        }
+    }
+}
+
+impl<T,O> FuturePair<T,O>  where T: Future<Output=O>, O: Clone {
+    /// Return a clone from a cell
+    ///
+    /// If self is a FuturePair::Fut(Poller<T,O>) then the future is polled to completion and self
+    /// is set FuturePair::Val(O).
+    fn clone_in_cell(cell: &Cell<Self>) -> Self {
+        let val = match cell.take().settled() {
+            FuturePair::Val(val) => val,
+            FuturePair::Fut(fut) => match fut.poll_to_completion_or_stop() {
+                Ok(val) => val,
+                Err(error) => {
+                    cell.set(FuturePair::Stopped(error));
+                    stopped(error)
+                },
+            },
+            FuturePair::Empty => {
+                cell.set(FuturePair::Empty);
+                taken()
+            },
+            FuturePair::Stopped(error) => {
+                cell.set(FuturePair::Stopped(error));
+                stopped(error)
+            },
+            FuturePair::None => poisoned(),
+        };
+
+        cell.set(FuturePair::Val(val.clone()));
+
+        FuturePair::Val(val)
+    }
+}
+
+impl<T,O> Default for FuturePair<T,O> where T: Future<Output=O> {
     fn default() -> Self {
         FuturePair::None
     }
 }
 
-/// A wrapper for retreiving the output of a future
+/// What happens to the future of a `Later` that is dropped before the future completes
+///
+/// This is set with [`Later::on_drop`](./struct.Later.html#method.on_drop).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DropPolicy {
+    /// The future is dropped, this is the default
+    Cancel,
+    /// The future is handed over to a background thread that polls it to completion and drops
+    /// its output
+    Detach,
+    /// The thread dropping the `Later` waits for the future to complete and drops its output
+    ///
+    /// A thread that can't block (see [`can_block`](./fn.can_block.html)), such as a worker of a
+    /// pool dropping the `Later` within a spawned future, detaches the future instead.
+    BlockToCompletion,
+}
+
+/// Poll the future of a dropped `Later` to completion on the dropping thread, or detach it if the
+/// thread can't block
+fn block_on_drop<T,O>(poller: Poller<T,O>) where T: Future<Output=O> + Send + 'static {
+    if can_block() {
+        let _ = poller.poll_to_completion_or_stop();
+    } else {
+        detach_future(poller.into_future())
+    }
+}
+
+/// Hand the future of a dropped `Later` over to be polled in the background
+fn detach_on_drop<T,O>(poller: Poller<T,O>) where T: Future<Output=O> + Send + 'static {
+    detach_future(poller.into_future())
+}
+
+/// Hand a future over to be polled to completion in the background, dropping its output
+///
+/// The future is spawned onto the global pool if the `pool` feature is enabled, otherwise it's
+/// polled by the background thread of the `detach` module.
+fn detach_future<T>(future: T) where T: Future + Send + 'static {
+    #[cfg(feature = "pool")]
+    pool::Pool::global().detach(future);
+
+    #[cfg(not(feature = "pool"))]
+    detach::detach(future);
+}
+
+/// The state of a `Later`
+///
+/// This is returned by [`Later::status`](./struct.Later.html#method.status).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LaterStatus {
+    /// The future hasn't completed
+    Pending,
+    /// The output is available
+    Ready,
+    /// The output was taken with `take_value`, or the future with `take_future`
+    Taken,
+    /// The future panicked while it was polled, accessing the output panics
+    Poisoned,
+    /// The future was aborted with an [`AbortHandle`](./struct.AbortHandle.html) or the token given
+    /// to `Later::with_cancel` was cancelled, accessing the output panics
+    Cancelled,
+    /// The deadline given to `Later::with_deadline` passed before the future completed, accessing
+    /// the output panics
+    Elapsed,
+}
+
+/// A wrapper for retreiving the output of a future
+///
+/// The purpose of `Later` is to create a wrapper that polls its contained future object to
+/// completion only at the point where the output of the future is required. The first call to any
+/// implemented function of `Later` that returns the output or a reference to the output of the
+/// future will cause `Later` to poll the future (for any subsequent calls `Later` will not poll).
+///
+/// `Later` is always fused. Once the contained future returns `Poll::Ready` the future is dropped
+/// and only the output is kept, so the future is never polled again no matter how the output is
+/// accessed. [`is_terminated`](#method.is_terminated) tells if this has happened.
+///
+/// Dropping a `Later` before it's forced cancels its future, unless another
+/// [`DropPolicy`](./enum.DropPolicy.html) is set with [`on_drop`](#method.on_drop). Combinators
+/// never detach the futures they're built from, so the cancellation reaches every future of the
+/// chain, including futures spawned onto a [`Pool`](./pool/struct.Pool.html).
+///
+/// The output type of a `Later<T>` is the `Output` of its future `T`.
+///
+/// # Threads
+/// A `Later` is `Send` whenever both its future and its output are `Send`, whether or not the
+/// future has completed, so a pending `Later` can be handed to another thread (such as through a
+/// work queue) to be forced there. The waker of a `Later` is shared with whatever its future
+/// gave it to, so the future may be woken from any thread.
+///
+/// ```rust
+/// # #![feature(futures_api)]
+/// # #![feature(async_await)]
+/// # #[macro_use] extern crate alligator;
+/// # use std::sync::mpsc::channel;
+/// # async fn render(page: u32) -> String { format!("page {}", page) }
+/// # fn main() {
+/// let (queue, jobs) = channel();
+///
+/// let worker = ::std::thread::spawn(move || {
+///     jobs.iter().map(|page: alligator::Later<_>| page.into_inner()).collect::<Vec<String>>()
+/// });
+///
+/// for page in 0..3 {
+///     queue.send(l!{ render(page) }).unwrap();
+/// }
+///
+/// drop(queue);
+/// # assert_eq!(worker.join().unwrap().len(), 3);
+/// # }
+/// ```
+///
+/// A `Later` is never `Sync` as accessing the output through a shared reference may poll the
+/// future, use [`sync::SyncLater`](./sync/struct.SyncLater.html) to share one between threads.
+///
+/// ```rust,compile_fail
+/// # #![feature(futures_api)]
+/// # extern crate alligator;
+/// # use alligator::{Later, Ready};
+/// fn is_sync<S: Sync>() {}
+///
+/// # fn main() {
+/// is_sync::<Later<Ready<u32>>>();
+/// # }
+/// ```
+pub struct Later<T> where T: Future {
+    fut_pair: Cell<FuturePair<T,T::Output>>,
+    context: Vec<Cow<'static, str>>,
+    location: Option<Location>,
+    on_drop: Option<fn(Poller<T,T::Output>)>,
+}
+
+/// Fails to compile if a `Later` of a `Send` future and output isn't `Send`
+#[allow(dead_code)]
+fn later_is_send<T>() where T: Future + Send, T::Output: Send {
+    fn is_send<S: Send>() {}
+
+    is_send::<Later<T>>();
+}
+
+impl<T,O> Later<T> where T: Future<Output=O> {
+
+    /// Create a new `Later` with the provided future
+    pub fn new( future: T ) -> Self {
+        Later {
+            fut_pair: Cell::new( Poller::new(future).poll_once() ),
+            context: Vec::new(),
+            location: None,
+            on_drop: None,
+        }
+    }
+
+    /// Create a new `Later` without polling the future until the output is accessed
+    pub(crate) fn unpolled( future: T ) -> Self {
+        Later {
+            fut_pair: Cell::new( FuturePair::Fut(Poller::new(future)) ),
+            context: Vec::new(),
+            location: None,
+            on_drop: None,
+        }
+    }
+
+    /// Create a new `Later` with the provided future that is cancelled by `token`
+    ///
+    /// When the token is cancelled the future is dropped without being polled again, even by a
+    /// thread that is waiting for the output at the time. Afterwards the fallible accessors return
+    /// `AccessError::Cancelled` and any other accessor panics. The future isn't polled at all if
+    /// the token is already cancelled.
+    ///
+    /// ```rust
+    /// # #![feature(futures_api)]
+    /// # extern crate alligator;
+    /// # use alligator::{AccessError, CancellationToken, Later, Pending};
+    /// # fn main() {
+    /// let shutdown = CancellationToken::new();
+    ///
+    /// let request = Later::with_cancel(Pending::<u32>::new(), &shutdown);
+    ///
+    /// shutdown.cancel();
+    ///
+    /// assert_eq!(*request.try_force().unwrap_err().error(), AccessError::Cancelled);
+    /// # }
+    /// ```
+    pub fn with_cancel( future: T, token: &CancellationToken ) -> Self {
+        let mut poller = Poller::new(future);
+
+        poller.watch(token.clone());
+
+        let fut_pair = match poller.stopped() {
+            Some(error) => FuturePair::Stopped(error),
+            None => poller.poll_once(),
+        };
+
+        Later {
+            fut_pair: Cell::new(fut_pair),
+            context: Vec::new(),
+            location: None,
+            on_drop: None,
+        }
+    }
+
+    /// Create a new `Later` with the provided future that is given up on at `deadline`
+    ///
+    /// Every access waits for the output no later than the deadline, so call sites don't each need
+    /// a timed accessor such as [`wait_until`](#method.wait_until). Once the deadline passes
+    /// without the future completing the future is dropped, the fallible accessors return
+    /// `AccessError::Elapsed`, and any other accessor panics. The future isn't polled at all if the
+    /// deadline has already passed.
+    ///
+    /// ```rust
+    /// # #![feature(futures_api)]
+    /// # extern crate alligator;
+    /// # use alligator::{AccessError, Later, LaterStatus, Pending};
+    /// # use std::time::{Duration, Instant};
+    /// # fn main() {
+    /// let reply = Later::with_deadline(Pending::<u32>::new(), Instant::now() + Duration::from_millis(10));
+    ///
+    /// assert_eq!(*reply.try_force().unwrap_err().error(), AccessError::Elapsed);
+    /// assert_eq!(reply.status(), LaterStatus::Elapsed);
+    /// # }
+    /// ```
+    pub fn with_deadline( future: T, deadline: Instant ) -> Self {
+        let mut poller = Poller::new(future);
+
+        poller.deadline = Some(deadline);
+
+        let fut_pair = match poller.stopped() {
+            Some(error) => FuturePair::Stopped(error),
+            None => poller.poll_once(),
+        };
+
+        Later {
+            fut_pair: Cell::new(fut_pair),
+            context: Vec::new(),
+            location: None,
+            on_drop: None,
+        }
+    }
+
+    /// Create a new `Later` with the provided future and the location where it was created
+    ///
+    /// The location is only kept in debug builds or when the `location` feature is enabled. The
+    /// `l!` and `later!` macros call this with the location of the macro.
+    pub fn new_at( future: T, location: Location ) -> Self {
+        let mut later = Self::new(future);
+
+        if Location::is_enabled() {
+            later.location = Some(location);
+        }
+
+        later.label_poller();
+
+        later
+    }
+
+    /// Get the location where this `Later` was created
+    ///
+    /// This is `None` if the `Later` was not created with `new_at` or if locations are not kept.
+    pub fn location(&self) -> Option<&Location> {
+        self.location.as_ref()
+    }
+
+    /// Add a human readable context to the errors of this `Later`
+    ///
+    /// Errors returned by the fallible accessors of `Later` are a
+    /// [`ContextError`](./error/struct.ContextError.html) with every context added to the `Later`.
+    /// Calls can be chained, with each call adding an outer context.
+    ///
+    /// ```rust
+    /// # #![feature(futures_api)]
+    /// # #![feature(async_await)]
+    /// # #[macro_use] extern crate alligator;
+    /// # async fn load_settings() -> u32 { 0 }
+    /// # fn main() {
+    /// let settings = l!{ load_settings() }
+    ///     .context("loading user settings")
+    ///     .context("starting the session");
+    /// # }
+    /// ```
+    pub fn context<C>(mut self, context: C) -> Self where C: Into<Cow<'static, str>> {
+        self.context.push(context.into());
+        self.label_poller();
+        self
+    }
+
+    /// Set what happens to the future if this `Later` is dropped before the future completes
+    ///
+    /// By default the future is dropped (`DropPolicy::Cancel`), which stops it wherever it was.
+    /// For futures with side effects that must happen, `DropPolicy::BlockToCompletion` waits for
+    /// the future when the `Later` is dropped and `DropPolicy::Detach` finishes it on a background
+    /// thread instead. The policy is not carried over to `Later`s created by combinators such as
+    /// [`map`](#method.map), nor does it apply to a future that panicked. The future must be `Send`
+    /// and `'static` so that it can be detached.
+    ///
+    /// ```rust
+    /// # #![feature(futures_api)]
+    /// # #![feature(async_await)]
+    /// # #[macro_use] extern crate alligator;
+    /// # use alligator::DropPolicy;
+    /// # async fn write_audit_log(entry: &'static str) -> usize { entry.len() }
+    /// # fn main() {
+    /// let written = l!{ write_audit_log("login") }.on_drop(DropPolicy::Detach);
+    ///
+    /// // The entry is still written even though its length is never needed
+    /// drop(written);
+    /// # }
+    /// ```
+    pub fn on_drop(mut self, policy: DropPolicy) -> Self where T: Send + 'static {
+        self.on_drop = match policy {
+            DropPolicy::Cancel => None,
+            DropPolicy::Detach => Some(detach_on_drop::<T,O>),
+            DropPolicy::BlockToCompletion => Some(block_on_drop::<T,O>),
+        };
+
+        self
+    }
+
+    /// Hand the future over to a background thread that polls it to completion, dropping the output
+    ///
+    /// This is for a future whose side effects must happen but whose output is no longer needed,
+    /// it's the same as dropping a `Later` with `DropPolicy::Detach`. The future is spawned onto the
+    /// [global pool](./pool/struct.Pool.html#method.global) when the `pool` feature is enabled,
+    /// otherwise it's polled by a single background thread shared by every detached future.
+    /// Nothing is detached if the future
+    /// has already completed, panicked, or was stopped, and a detached future is no longer given up
+    /// on by the deadline of [`with_deadline`](#method.with_deadline) or by cancellation.
+    ///
+    /// ```rust
+    /// # #![feature(futures_api)]
+    /// # #![feature(async_await)]
+    /// # #[macro_use] extern crate alligator;
+    /// # async fn flush_metrics() -> usize { 0 }
+    /// # fn main() {
+    /// let flushed = l!{ flush_metrics() };
+    ///
+    /// // The count of flushed metrics isn't needed, but the metrics must still be flushed
+    /// flushed.detach();
+    /// # }
+    /// ```
+    pub fn detach(self) where T: Send + 'static {
+        match self.fut_pair.replace(FuturePair::Empty).settled() {
+            FuturePair::Fut(ref poller) if poller.panicked => (),
+            FuturePair::Fut(poller) => detach_future(poller.into_future()),
+            _ => (),
+        }
+    }
+
+    /// Limit the number of times the future is polled
+    ///
+    /// A future that is woken but keeps returning `Poll::Pending` would otherwise keep a forcing
+    /// thread polling it forever. Once the future has been polled `polls` times (counting every
+    /// poll since the `Later` was created) without completing, the fallible accessors return
+    /// `AccessError::PollBudgetExceeded` with the type of the future and the number of polls, and
+    /// the other accessors panic with it.
+    ///
+    /// This does nothing if the future has already completed.
+    ///
+    /// ```rust
+    /// # #![feature(futures_api)]
+    /// # #![feature(pin)]
+    /// # #![feature(arbitrary_self_types)]
+    /// # extern crate alligator;
+    /// # use alligator::{AccessError, Later};
+    /// # use std::future::Future;
+    /// # use std::pin::Pin;
+    /// # use std::task::{LocalWaker, Poll};
+    /// /// Wakes itself on every poll but never completes
+    /// struct Spin;
+    ///
+    /// impl Future for Spin {
+    ///     type Output = ();
+    ///
+    ///     fn poll(self: Pin<&mut Self>, lw: &LocalWaker) -> Poll<()> {
+    ///         lw.as_waker().wake();
+    ///
+    ///         Poll::Pending
+    ///     }
+    /// }
+    ///
+    /// # fn main() {
+    /// let spin = Later::new(Spin).poll_budget(3);
+    ///
+    /// match *spin.try_force().unwrap_err().error() {
+    ///     AccessError::PollBudgetExceeded { type_name, polls } => {
+    ///         assert!(type_name.ends_with("Spin"));
+    ///         assert!(polls >= 3);
+    ///     },
+    ///     ref error => panic!("unexpected error: {}", error),
+    /// }
+    /// # }
+    /// ```
+    pub fn poll_budget(mut self, polls: usize) -> Self {
+        if let FuturePair::Fut(ref mut poller) = *self.fut_pair.get_mut() {
+            poller.budget = Some(polls);
+        }
+
+        self
+    }
+
+    /// Get a handle for aborting the future from another thread
+    ///
+    /// A thread blocked on the output of the `Later` is woken when the handle aborts the future,
+    /// so a `Later` whose future would never complete doesn't have to block forever. Once aborted
+    /// the fallible accessors return `AccessError::Cancelled`, any other accessor panics, and
+    /// [`status`](#method.status) is `LaterStatus::Cancelled`. Laters built from this one by
+    /// combinators such as [`map`](#method.map) are aborted by the handle as well, as are clones.
+    ///
+    /// If the future has already completed the handle does nothing.
+    ///
+    /// ```rust
+    /// # #![feature(futures_api)]
+    /// # extern crate alligator;
+    /// # use alligator::{AccessError, Later, Pending};
+    /// # use std::time::Duration;
+    /// # fn main() {
+    /// let mut reply = Later::new(Pending::<String>::new());
+    ///
+    /// let abort = reply.abort_handle();
+    ///
+    /// ::std::thread::spawn(move || {
+    ///     ::std::thread::sleep(Duration::from_millis(10));
+    ///     abort.abort();
+    /// });
+    ///
+    /// assert_eq!(*reply.try_force().unwrap_err().error(), AccessError::Cancelled);
+    /// # }
+    /// ```
+    pub fn abort_handle(&mut self) -> AbortHandle {
+        let token = CancellationToken::new();
+
+        if let FuturePair::Fut(ref mut poller) = *self.fut_pair.get_mut() {
+            poller.watch(token.clone());
+        }
+
+        AbortHandle::new(token)
+    }
+
+    /// Give the poller the label and location used for profiler markers and the blocked registry
+    ///
+    /// The label is the first context of the `Later`.
+    #[cfg(any(feature = "profile", feature = "debug"))]
+    fn label_poller(&mut self) {
+        let label = self.context.first().cloned();
+        let location = self.location.clone();
+
+        if let FuturePair::Fut(ref mut poller) = *self.fut_pair.get_mut() {
+            poller.label = label;
+            poller.location = location;
+        }
+    }
+
+    #[cfg(not(any(feature = "profile", feature = "debug")))]
+    fn label_poller(&mut self) {}
+
+    /// Attach the context of this `Later` to an error
+    ///
+    /// The location where this `Later` was created is the innermost context.
+    fn error<E>(&self, error: E) -> ContextError<E> {
+        let error = match self.location {
+            Some(ref location) => ContextError::new(error).context(format!("Later created at {}", location)),
+            None => ContextError::new(error),
+        };
+
+        self.context.iter().cloned().fold(error, ContextError::context)
+    }
+
+    /// Describe this `Later` for panic messages
+    fn describe(&self) -> String {
+        match self.location {
+            Some(ref location) => format!("the Later created at {}", location),
+            None => String::from("a Later"),
+        }
+    }
+
+    /// Consume self and return the output of the contained future
+    pub fn into_inner(self) -> O {
+        self.fut_pair.replace(FuturePair::Empty).into()
+    }
+
+    /// Consume self and leak the output of the contained future, returning a reference to it
+    ///
+    /// This is like `Box::leak`, the output is never dropped. It's meant for values that live for
+    /// the rest of the program, such as configuration resolved at startup.
+    ///
+    /// ```rust
+    /// # #![feature(futures_api)]
+    /// # #![feature(async_await)]
+    /// # #[macro_use] extern crate alligator;
+    /// # struct Config { workers: usize }
+    /// # async fn load_config() -> Config { Config { workers: 8 } }
+    /// # fn main() {
+    /// let config: &'static Config = l!{ load_config() }.leak();
+    /// # assert_eq!(config.workers, 8);
+    /// # }
+    /// ```
+    pub fn leak(self) -> &'static mut O where O: 'static {
+        Box::leak(Box::new(self.into_inner()))
+    }
+
+    /// Swap this `Later` with another
+    ///
+    /// Neither future is polled. Everything about the two `Later`s is exchanged, the futures or
+    /// outputs along with their context and location.
+    pub fn swap(&mut self, other: &mut Self) {
+        ::std::mem::swap(self, other)
+    }
+
+    /// Replace this `Later` with a new `Later` of `future`, returning the old `Later`
+    ///
+    /// The new `Later` is created with `Later::new`, so `future` is polled once.
+    pub fn replace(&mut self, future: T) -> Self {
+        ::std::mem::replace(self, Later::new(future))
+    }
+
+    /// Set the output value, dropping the future if it hasn't completed
+    ///
+    /// This is for short-circuiting a `Later` with a value that arrived some other way. Any output
+    /// already in the `Later` is dropped, use [`replace_value`](#method.replace_value) to get it
+    /// back. The `Later` can be accessed again after the output was taken or the future panicked.
+    pub fn set(&mut self, val: O) {
+        self.replace_value(val);
+    }
+
+    /// Set the output value and return the output that was there before, if any
+    ///
+    /// `None` is returned when the future hadn't completed, in which case it's dropped without
+    /// being polled again.
+    pub fn replace_value(&mut self, val: O) -> Option<O> {
+        match self.fut_pair.replace(FuturePair::Val(val)) {
+            FuturePair::Val(old) => Some(old),
+            _ => None,
+        }
+    }
+
+    /// Get the future back if it hasn't completed, otherwise get the output
+    ///
+    /// `Ok` is the future, from where it left off, for handing over to an executor when waiting
+    /// for it isn't wanted after all. `Err` is the output when the future has already completed.
+    ///
+    /// # Panic
+    /// This panics if the output was taken with `take_value`
+    pub fn into_future(self) -> Result<T, O> {
+        match self.fut_pair.replace(FuturePair::Empty).settled() {
+            FuturePair::Fut(poller) => Ok(poller.into_future()),
+            FuturePair::Val(val) => Err(val),
+            FuturePair::Empty => taken(),
+            FuturePair::Stopped(error) => stopped(error),
+            FuturePair::None => poisoned(),
+        }
+    }
+
+    /// Convert self into a boxed future for the output
+    ///
+    /// If the contained future has completed the returned future is immediately ready with the
+    /// output, otherwise the returned future is the contained future. This is for moving the
+    /// remaining work of a `Later` into an asynchronous context.
+    ///
+    /// # Panic
+    /// This panics if the output was taken with `take_value`
+    pub fn into_boxed_future(self) -> Pin<Box<dyn Future<Output=O> + Send>>
+        where T: Send + 'static,
+              O: Send + 'static,
+    {
+        match self.fut_pair.replace(FuturePair::Empty).settled() {
+            FuturePair::Fut(poller) => Box::pinned(poller.into_future()),
+            FuturePair::Val(val) => Box::pinned(Ready::new(val)),
+            FuturePair::Empty => taken(),
+            FuturePair::Stopped(error) => stopped(error),
+            FuturePair::None => poisoned(),
+        }
+    }
+
+    /// Turn self into the remaining work of its future
+    ///
+    /// # Panic
+    /// This panics if the output was taken with `take_value`
+    pub(crate) fn into_source(self) -> Source<T,O> {
+        self.fut_pair.replace(FuturePair::Empty).into_source()
+    }
+
+    /// Take the tokens and the deadline that stop the future of self
+    ///
+    /// This is for consuming self into the future of another `Later`, which is then stopped by
+    /// them instead.
+    fn take_stops(&mut self) -> Stops {
+        match *self.fut_pair.get_mut() {
+            FuturePair::Fut(ref mut poller) => poller.take_stops(),
+            _ => Stops::default(),
+        }
+    }
+
+    /// Also stop the future of self by stops taken from another `Later`
+    fn add_stops(&mut self, stops: Stops) {
+        if let FuturePair::Fut(ref mut poller) = *self.fut_pair.get_mut() {
+            poller.add_stops(stops);
+        }
+    }
+
+    /// Turn self into a new `Later` of a future built from the remaining work of self
+    ///
+    /// The context and location of self are kept by the new `Later`. Unlike `Later::new` the new
+    /// future isn't polled until the new `Later` is accessed.
+    ///
+    /// # Panic
+    /// This panics if the output was taken with `take_value`
+    fn chain<G,U,F>(mut self, build: F) -> Later<G>
+        where G: Future<Output=U>,
+              F: FnOnce(Source<T,O>) -> G,
+    {
+        let mut fut_pair = self.fut_pair.replace(FuturePair::Empty).settled();
+
+        // The new future is stopped by whatever would have stopped the old one
+        let stops = match fut_pair {
+            FuturePair::Fut(ref mut poller) => poller.take_stops(),
+            _ => Stops::default(),
+        };
+
+        let mut poller = Poller::new(build(fut_pair.into_source()));
+
+        poller.add_stops(stops);
+
+        let mut later = Later {
+            fut_pair: Cell::new( FuturePair::Fut(poller) ),
+            context: ::std::mem::replace(&mut self.context, Vec::new()),
+            location: self.location,
+            on_drop: None,
+        };
+
+        later.label_poller();
+
+        later
+    }
+
+    /// Convert the output into `U` once it's available
+    ///
+    /// This doesn't wait for the output, the conversion is done when the output of the returned
+    /// `Later` is accessed.
+    ///
+    /// ```rust
+    /// # #![feature(futures_api)]
+    /// # #![feature(async_await)]
+    /// # #[macro_use] extern crate alligator;
+    /// # struct UserDto { name: String }
+    /// # struct User { name: String }
+    /// # impl From<UserDto> for User { fn from(dto: UserDto) -> User { User { name: dto.name } } }
+    /// # async fn fetch_user() -> UserDto { UserDto { name: String::from("ferris") } }
+    /// # fn main() {
+    /// let user = l!{ fetch_user() }.map_into::<User>();
+    /// # assert_eq!(user.name, "ferris");
+    /// # }
+    /// ```
+    ///
+    /// # Panic
+    /// This panics if the output was taken with `take_value`
+    pub fn map_into<U>(self) -> Later<MapInto<T,O,U>> where O: Into<U> {
+        self.chain(MapInto::new)
+    }
+
+    /// Apply `f` to the output once it's needed
+    ///
+    /// Neither the future nor `f` is run by this, `f` is called with the output of the future
+    /// when the output of the returned `Later` is accessed.
+    ///
+    /// ```rust
+    /// # #![feature(futures_api)]
+    /// # #![feature(async_await)]
+    /// # #[macro_use] extern crate alligator;
+    /// # async fn fetch_body() -> String { String::from("hello") }
+    /// # fn main() {
+    /// let length = l!{ fetch_body() }.map(|body| body.len());
+    /// # assert_eq!(*length, 5);
+    /// # }
+    /// ```
+    ///
+    /// # Panic
+    /// This panics if the output was taken with `take_value`
+    pub fn map<U,F>(self, f: F) -> Later<Map<T,O,F>> where F: FnOnce(O) -> U {
+        self.chain(|source| Map::new(source, f))
+    }
+
+    /// Continue with the future returned by `f` once the output is needed
+    ///
+    /// The returned `Later` drives the future of self, calls `f` with its output and then drives
+    /// the future returned by `f`, all within a single blocking wait when its output is accessed.
+    ///
+    /// ```rust
+    /// # #![feature(futures_api)]
+    /// # #![feature(async_await)]
+    /// # #[macro_use] extern crate alligator;
+    /// # async fn fetch_user(id: u32) -> String { format!("user{}", id) }
+    /// # async fn fetch_posts(user: String) -> Vec<String> { vec![user] }
+    /// # fn main() {
+    /// let posts = l!{ fetch_user(7) }.and_then(fetch_posts);
+    /// # assert_eq!(*posts, vec![String::from("user7")]);
+    /// # }
+    /// ```
+    ///
+    /// # Panic
+    /// This panics if the output was taken with `take_value`
+    pub fn and_then<U,G,F>(self, f: F) -> Later<AndThen<T,O,F,G>>
+        where G: Future<Output=U>,
+              F: FnOnce(O) -> G,
+    {
+        self.chain(|source| AndThen::new(source, f))
+    }
+
+    /// Combine self with `other` into a `Later` of both outputs
+    ///
+    /// Both futures are polled with the same waker when the output of the returned `Later` is
+    /// accessed, so they make progress concurrently and the wait is as long as the slower of the
+    /// two rather than both one after the other. The returned `Later` is cancelled by the tokens of
+    /// both `Later`s, and its deadline is the earlier of their deadlines.
+    ///
+    /// ```rust
+    /// # #![feature(futures_api)]
+    /// # #![feature(async_await)]
+    /// # #[macro_use] extern crate alligator;
+    /// # async fn fetch_user() -> &'static str { "gpace" }
+    /// # async fn fetch_settings() -> u32 { 3 }
+    /// # fn main() {
+    /// let both = l!{ fetch_user() }.zip(l!{ fetch_settings() });
+    ///
+    /// let (user, settings) = both.into_inner();
+    /// # assert_eq!((user, settings), ("gpace", 3));
+    /// # }
+    /// ```
+    ///
+    /// # Panic
+    /// This panics if the output of either `Later` was taken with `take_value`
+    #[cfg(feature = "combinators")]
+    pub fn zip<T2,B>(self, mut other: Later<T2>) -> Later<join::Zip<T,O,T2,B>> where T2: Future<Output=B> {
+        let stops = other.take_stops();
+
+        let other = other.into_source();
+
+        let mut later = self.chain(|source| join::Zip::new(source, other));
+
+        later.add_stops(stops);
+
+        later
+    }
+
+    /// Race self against `other`, the returned `Later` has the output of whichever completes first
+    ///
+    /// Only the future that was woken is polled on a wake up, and the future that didn't complete
+    /// is dropped along with any work it still had to do. If both futures are woken at the same
+    /// time then the future of self wins. The returned `Later` is cancelled by the tokens of both
+    /// `Later`s, and its deadline is the earlier of their deadlines.
+    ///
+    /// ```rust
+    /// # #![feature(futures_api)]
+    /// # #![feature(async_await)]
+    /// # #[macro_use] extern crate alligator;
+    /// # async fn from_cache() -> u32 { 7 }
+    /// # async fn from_origin() -> u32 { 7 }
+    /// # fn main() {
+    /// let value = l!{ from_cache() }.race(l!{ from_origin() });
+    /// # assert_eq!(*value, 7);
+    /// # }
+    /// ```
+    ///
+    /// # Panic
+    /// This panics if the output of either `Later` was taken with `take_value`
+    #[cfg(feature = "combinators")]
+    pub fn race<T2>(self, mut other: Later<T2>) -> Later<join::Race<T,T2,O>> where T2: Future<Output=O> {
+        let stops = other.take_stops();
+
+        let other = other.into_source();
+
+        let mut later = self.chain(|source| join::Race::new(source, other));
+
+        later.add_stops(stops);
+
+        later
+    }
+
+    /// Turn self into a `SharedLater` whose clones all share the future
+    ///
+    /// The remaining work of the future is continued by the first clone to access the output.
+    ///
+    /// # Panic
+    /// This panics if the output was taken with `take_value`
+    #[cfg(feature = "sync")]
+    pub fn shared(self) -> sync::SharedLater<T> {
+        self.into()
+    }
+
+    /// Get a reference to the output value of the contained future
+    ///
+    /// This is the same as [`force`](#method.force).
+    pub fn get_ref(&self) -> &O {
+        self.force()
+    }
+
+    /// Get a mutable reference to the output value of the contained future
+    ///
+    /// This is the same as [`force_mut`](#method.force_mut).
+    pub fn get_mut(&mut self) -> &mut O {
+        self.force_mut()
+    }
+
+    /// Get a reference to the output value of the contained future, or an error if the output
+    /// can't be accessed
+    ///
+    /// This is the same as [`try_force`](#method.try_force).
+    pub fn try_get_ref(&self) -> Result<&O, ContextError<AccessError>> {
+        self.try_force()
+    }
+
+    /// Get a reference to the output value of the contained future
+    ///
+    /// This is the same as dereferencing the `Later`, but makes it explicit that the current thread
+    /// will wait for the future if it hasn't completed.
+    pub fn force(&self) -> &O {
+        FuturePair::get_ref_from_cell(&self.fut_pair)
+    }
+
+    /// Get a mutable reference to the output value of the contained future
+    ///
+    /// This is the explicit form of `deref_mut`, see [`force`](#method.force).
+    pub fn force_mut(&mut self) -> &mut O {
+        FuturePair::get_ref_from_cell(&self.fut_pair)
+    }
+
+    /// Get a reference to the output value of the contained future, or an error if the output
+    /// can't be accessed
+    ///
+    /// This waits for the future to complete like [`force`](#method.force), but instead of
+    /// panicking it returns an [`AccessError`](./error/enum.AccessError.html) with the context of
+    /// the `Later`. `AccessError::Taken` is returned if the output was taken,
+    /// `AccessError::Poisoned` if the future panicked while it was polled,
+    /// `AccessError::Cancelled` if the future was aborted with an
+    /// [`AbortHandle`](./struct.AbortHandle.html) or cancelled by the token given to
+    /// [`with_cancel`](#method.with_cancel), `AccessError::Elapsed` once the deadline given to
+    /// [`with_deadline`](#method.with_deadline) passes, `AccessError::BlockedLimit` instead of waiting when the limit set by
+    /// [`set_blocked_limit`](./fn.set_blocked_limit.html) is reached, and
+    /// `AccessError::WouldDeadlock` when the current thread can't block (see
+    /// [`can_block`](./fn.can_block.html)).
+    pub fn try_force(&self) -> Result<&O, ContextError<AccessError>> {
+        FuturePair::try_get_ref_from_cell(&self.fut_pair)
+            .map(|val| &*val)
+            .map_err(|e| self.error(e))
+    }
+
+    /// Get a reference to the output value, waiting for it no later than `deadline`
+    ///
+    /// This is for a latency budget that is a point in time, such as the end of a frame. When the
+    /// deadline is reached the `Later` is left as it was, so the call can be repeated with the same
+    /// deadline (which will poll the future once more before returning the error again) or with a
+    /// later one.
+    ///
+    /// # Panic
+    /// This panics if the output was taken with `take_value`, if the future panicked while it was
+    /// polled (by this call or an earlier access), if the future was aborted with an
+    /// [`AbortHandle`](./struct.AbortHandle.html) or cancelled by the token given to
+    /// [`with_cancel`](#method.with_cancel), or if the deadline given to
+    /// [`with_deadline`](#method.with_deadline) passes before `deadline`. These are the errors
+    /// that [`try_force`](#method.try_force) returns instead of panicking.
+    pub fn wait_until(&self, deadline: Instant) -> Result<&O, ContextError<TimeoutError>> {
+        FuturePair::get_ref_from_cell_until(&self.fut_pair, deadline)
+            .map(|val| &*val)
+            .ok_or_else(|| self.error(TimeoutError::new()))
+    }
+
+    /// Get a reference to the output value without waiting for it
+    ///
+    /// If the future hasn't completed it is polled exactly once, and `None` is returned if it is
+    /// still pending. The thread is never parked. `None` is also returned if the output was taken
+    /// with `take_value`.
+    pub fn try_ref(&self) -> Option<&O> {
+        FuturePair::poll_ref_from_cell(&self.fut_pair).map(|val| &*val)
+    }
+
+    /// Poll the future once and return true if the output is available
+    ///
+    /// This is for making progress on the future from a loop of the caller's own instead of
+    /// waiting for it. The future is not polled if it has already completed. False is returned if
+    /// the output was taken with `take_value`.
+    pub fn poll_now(&mut self) -> bool {
+        FuturePair::poll_ref_from_cell(&self.fut_pair).is_some()
+    }
+
+    /// Move the output value out of this `Later`
+    ///
+    /// The future is polled to completion if it hasn't completed. Afterwards this `Later` is empty,
+    /// the next call to `take_value` returns `None`, fallible accessors return
+    /// `AccessError::Taken`, and any other accessor panics.
+    pub fn take_value(&mut self) -> Option<O> {
+        FuturePair::take_from_cell(&self.fut_pair)
+    }
+
+    /// Move the future out of this `Later` if it hasn't completed
+    ///
+    /// Afterwards this `Later` is empty, just like after [`take_value`](#method.take_value). This is
+    /// for cancelling (by dropping the returned future) or moving elsewhere the work of a `Later`
+    /// that is owned by a long lived structure. `None` is returned and the `Later` is left as it was
+    /// if the future has completed or the `Later` is already empty.
+    pub fn take_future(&mut self) -> Option<T> {
+        match self.fut_pair.replace(FuturePair::None) {
+            FuturePair::Fut(poller) => {
+                self.fut_pair.set(FuturePair::Empty);
+
+                Some(poller.into_future())
+            },
+            other => {
+                self.fut_pair.set(other);
+
+                None
+            },
+        }
+    }
+
+    /// Check if the output value was taken with [`take_value`](#method.take_value) (or the future
+    /// with [`take_future`](#method.take_future))
+    pub fn is_taken(&self) -> bool {
+        match unsafe { &*self.fut_pair.as_ptr() } {
+            FuturePair::Empty => true,
+            _ => false,
+        }
+    }
+
+    /// Poll the future once with `lw` without waiting, returning `Poll::Ready` once the output is
+    /// available or the future is stopped
+    ///
+    /// `lw` is also woken when a token that cancels the future is cancelled, but not when the
+    /// deadline passes. A caller that waits has to wait no longer than `deadline`.
+    pub(crate) fn poll_ready(&mut self, lw: &LocalWaker) -> Poll<()> {
+        FuturePair::settle(&self.fut_pair);
+
+        let poll = match *self.fut_pair.get_mut() {
+            FuturePair::Fut(ref mut poller) => {
+                poller.waker.forward_to(lw);
+
+                poller.poll_with(lw)
+            },
+            FuturePair::Val(_) | FuturePair::Empty | FuturePair::Stopped(_) => return Poll::Ready(()),
+            FuturePair::None => poisoned(),
+        };
+
+        match poll {
+            Poll::Ready(val) => self.fut_pair.set(FuturePair::Val(val)),
+            Poll::Pending => {
+                // The poll may have adopted the tokens of a `Later` that was already cancelled
+                FuturePair::settle(&self.fut_pair);
+
+                if let FuturePair::Fut(_) = *self.fut_pair.get_mut() {
+                    return Poll::Pending;
+                }
+            },
+        }
+
+        Poll::Ready(())
+    }
+
+    /// Get the deadline of the future if it has one and hasn't completed
+    #[cfg(feature = "combinators")]
+    pub(crate) fn deadline(&self) -> Option<Instant> {
+        match unsafe { &*self.fut_pair.as_ptr() } {
+            FuturePair::Fut(ref poller) => poller.deadline,
+            _ => None,
+        }
+    }
+
+    /// Get the state of the `Later` without polling the future
+    pub fn status(&self) -> LaterStatus {
+        match unsafe { &*self.fut_pair.as_ptr() } {
+            FuturePair::Fut(ref poller) if poller.panicked => LaterStatus::Poisoned,
+            FuturePair::Fut(ref poller) => match poller.stopped() {
+                Some(AccessError::Elapsed) => LaterStatus::Elapsed,
+                Some(_) => LaterStatus::Cancelled,
+                None => LaterStatus::Pending,
+            },
+            FuturePair::Val(_) => LaterStatus::Ready,
+            FuturePair::Empty => LaterStatus::Taken,
+            FuturePair::Stopped(AccessError::Elapsed) => LaterStatus::Elapsed,
+            FuturePair::Stopped(_) => LaterStatus::Cancelled,
+            FuturePair::None => LaterStatus::Poisoned,
+        }
+    }
+
+    /// Check if the output is available without waiting
+    ///
+    /// This does not poll the future, use [`try_ref`](#method.try_ref) to poll it once.
+    pub fn is_ready(&self) -> bool {
+        self.status() == LaterStatus::Ready
+    }
+
+    /// Check if the contained future has completed
+    ///
+    /// Once this returns true the future has been dropped and will never be polled again. This
+    /// does not poll the future.
+    pub fn is_terminated(&self) -> bool {
+        match unsafe { &*self.fut_pair.as_ptr() } {
+            FuturePair::Val(_) | FuturePair::Empty | FuturePair::Stopped(_) => true,
+            _ => false,
+        }
+    }
+
+    /// Panic if the `strict_deref` feature is enabled and the future hasn't completed
+    #[cfg(feature = "strict_deref")]
+    fn strict_deref(&self) {
+        if !self.is_terminated() {
+            panic!("{} was dereferenced before its future completed, use `Later::force` to wait \
+                for the output", self.describe());
+        }
+    }
+
+    #[cfg(not(feature = "strict_deref"))]
+    fn strict_deref(&self) {}
+}
+
+/// A `Later` of a boxed future
 ///
-/// The purpose of `Later` is to create a wrapper that polls its contained future object to
-/// completion only at the point where the output of the future is required. The first call to any
-/// implemented function of `Later` that returns the output or a reference to the output of the
-/// future will cause `Later` to poll the future (for any subsequent calls `Later` will not poll).
-pub struct Later<T,O> where T: Future<Output=O>{
-    fut_pair: Cell<FuturePair<T,O>>,
+/// The type of the future is erased, so `Later`s of different futures with the same output can be
+/// stored in the same field or collection.
+pub type DynLater<O> = Later<Pin<Box<dyn Future<Output=O> + Send>>>;
+
+impl<O> Later<Pin<Box<dyn Future<Output=O> + Send>>> {
+
+    /// Create a `Later` of `future` boxed
+    ///
+    /// ```rust
+    /// # #![feature(futures_api)]
+    /// # #![feature(async_await)]
+    /// # extern crate alligator;
+    /// # use alligator::{DynLater, Later};
+    /// # async fn from_disk() -> Vec<u8> { vec![1] }
+    /// # async fn from_network() -> Vec<u8> { vec![2] }
+    /// # fn main() {
+    /// let sources: Vec<DynLater<Vec<u8>>> = vec![
+    ///     Later::boxed(from_disk()),
+    ///     Later::boxed(from_network()),
+    /// ];
+    /// # assert_eq!(*sources[1], vec![2]);
+    /// # }
+    /// ```
+    pub fn boxed<T>(future: T) -> Self where T: Future<Output=O> + Send + 'static {
+        Later::new(Box::pinned(future) as Pin<Box<dyn Future<Output=O> + Send>>)
+    }
 }
 
-impl<T,O> Later<T,O> where T: Future<Output=O> {
+/// A `Later` that was created with its output
+pub type ReadyLater<O> = Later<Ready<O>>;
 
-    /// Create a new `Later` with the provided future
-    pub fn new( future: T ) -> Self {
+impl<O> Later<Ready<O>> {
+
+    /// Create a `Later` that already has its output
+    ///
+    /// This is for functions that return a `Later` but sometimes have the output right away, such
+    /// as from a cache. Nothing is polled.
+    ///
+    /// ```rust
+    /// # extern crate alligator;
+    /// # use alligator::{Later, ReadyLater};
+    /// # use std::collections::HashMap;
+    /// fn cached(cache: &HashMap<u32, String>, key: u32) -> Option<ReadyLater<String>> {
+    ///     cache.get(&key).cloned().map(Later::ready)
+    /// }
+    /// # fn main() {}
+    /// ```
+    pub fn ready(val: O) -> Self {
         Later {
-            fut_pair: Cell::new( Poller::new(future).poll_once() ),
+            fut_pair: Cell::new(FuturePair::Val(val)),
+            context: Vec::new(),
+            location: None,
+            on_drop: None,
         }
     }
+}
 
-    /// Consume self and return the output of the contained future
-    pub fn into_inner(self) -> O {
-        self.fut_pair.into_inner().into()
+/// A `Later` whose future never completes
+pub type PendingLater<O> = Later<Pending<O>>;
+
+impl<O> Later<Pending<O>> {
+
+    /// Create a `Later` whose future never completes
+    ///
+    /// This is a placeholder, such as the default of a field that is given a real `Later` later
+    /// on, and for testing the paths of code where an output never arrives. Accessors that wait
+    /// without a limit wait forever, but the bounded accessors ([`get_timeout`](#method.get_timeout),
+    /// [`wait_until`](#method.wait_until), [`try_ref`](#method.try_ref), formatting with a
+    /// [format timeout](./fn.set_format_timeout.html)) return as they would for any slow future, and
+    /// so does [`select`](./fn.select.html) when another waitable becomes ready.
+    ///
+    /// ```rust
+    /// # extern crate alligator;
+    /// # use alligator::{Later, PendingLater};
+    /// # use std::time::Duration;
+    /// # fn main() {
+    /// let never: PendingLater<u32> = Later::pending();
+    ///
+    /// assert!(never.get_timeout(Duration::from_millis(10)).is_err());
+    /// # }
+    /// ```
+    pub fn pending() -> Self {
+        Later::new(Pending::new())
+    }
+}
+
+impl<T,T2,O> Later<T> where T: Future<Output=Later<T2>>, T2: Future<Output=O> {
+
+    /// Collapse a `Later` of a `Later` into a single `Later` of the inner output
+    ///
+    /// Both futures are driven within a single blocking wait when the output of the returned
+    /// `Later` is accessed, instead of blocking once for the outer `Later` and again for the inner
+    /// one. Once the inner `Later` is reached its tokens and deadline stop the returned `Later` as
+    /// well.
+    ///
+    /// ```rust
+    /// # #![feature(futures_api)]
+    /// # #![feature(async_await)]
+    /// # #[macro_use] extern crate alligator;
+    /// # use alligator::Later;
+    /// # async fn load(key: u32) -> u32 { key * 2 }
+    /// async fn lookup(key: u32) -> Later<impl ::std::future::Future<Output=u32>> {
+    ///     l!{ load(key) }
+    /// }
+    ///
+    /// # fn main() {
+    /// let value = l!{ lookup(21) }.flatten();
+    /// # assert_eq!(*value, 42);
+    /// # }
+    /// ```
+    ///
+    /// # Panic
+    /// This panics if the output of either `Later` was taken with `take_value`
+    pub fn flatten(self) -> Later<Flatten<T,T2,O>> {
+        let adopted = Arc::new(Mutex::new(Stops::default()));
+
+        let mut later = self.chain(|source| Flatten::new(source, adopted.clone()));
+
+        later.add_stops(Stops { adopted: vec![adopted], ..Stops::default() });
+
+        later
     }
 }
 
-impl<T,O> Later<T,O> where T: Future<Output=O>, O: Clone {
+impl<T,O> Later<T> where T: Future<Output=O>, O: Clone {
 
     /// Get the output value of the contained future
     pub fn get(&self) -> O {
         FuturePair::clone_in_cell(&self.fut_pair).into()
     }
+
+    /// Get the output value, or `default` if it can't be accessed
+    ///
+    /// This is the infallible form of [`try_force`](#method.try_force), `default` is returned for
+    /// any error `try_force` would return. For a fallback when waiting takes too long use
+    /// `get_timeout(timeout).unwrap_or(default)`.
+    pub fn get_or(&self, default: O) -> O {
+        self.try_force().map(|val| val.clone()).unwrap_or(default)
+    }
+
+    /// Get the output value, or the value returned by `f` for the error if it can't be accessed
+    ///
+    /// See [`get_or`](#method.get_or).
+    pub fn get_or_else<F>(&self, f: F) -> O where F: FnOnce(ContextError<AccessError>) -> O {
+        self.try_force().map(|val| val.clone()).unwrap_or_else(f)
+    }
+
+    /// Get the output value, or the default value of `O` if it can't be accessed
+    ///
+    /// See [`get_or`](#method.get_or).
+    pub fn get_or_default(&self) -> O where O: Default {
+        self.try_force().map(|val| val.clone()).unwrap_or_default()
+    }
+
+    /// Get the output value without waiting for it
+    ///
+    /// This is the cloning version of [`try_ref`](#method.try_ref), the future is polled at most
+    /// once and `None` is returned if it is still pending.
+    pub fn try_get(&self) -> Option<O> {
+        self.try_ref().cloned()
+    }
+
+    /// Get the output value, waiting no longer than `timeout` for it
+    ///
+    /// A timeout leaves the `Later` as it was, the future continues from where it left off the next
+    /// time the `Later` is accessed, so the call can be retried.
+    ///
+    /// # Panic
+    /// This panics for the same reasons as [`wait_until`](#method.wait_until): if the output was
+    /// taken with `take_value`, if the future panicked while it was polled, if the future was
+    /// aborted or cancelled, or if the deadline given to [`with_deadline`](#method.with_deadline)
+    /// passes before the timeout.
+    pub fn get_timeout(&self, timeout: Duration) -> Result<O, ContextError<TimeoutError>> {
+        FuturePair::get_ref_from_cell_until(&self.fut_pair, clock::now() + timeout)
+            .map(|val| val.clone())
+            .ok_or_else(|| self.error(TimeoutError::new()))
+    }
+}
+
+/// Clone a `Later` along with its future or output
+///
+/// If the future hasn't completed then the clone has a clone of the future, so the work done by
+/// the future is done again by the clone. Otherwise the output is cloned. A `Later` that was taken
+/// or poisoned is cloned as it is.
+impl<T,O> Clone for Later<T> where T: Future<Output=O> + Clone, O: Clone {
+    fn clone(&self) -> Self {
+        let fut_pair = match unsafe { &*self.fut_pair.as_ptr() } {
+            FuturePair::Fut(ref poller) if poller.panicked => FuturePair::None,
+            FuturePair::Fut(ref poller) => match poller.stopped() {
+                Some(error) => FuturePair::Stopped(error),
+                None => FuturePair::Fut(poller.duplicate()),
+            },
+            FuturePair::Val(ref val) => FuturePair::Val(val.clone()),
+            FuturePair::Empty => FuturePair::Empty,
+            FuturePair::Stopped(error) => FuturePair::Stopped(error),
+            FuturePair::None => FuturePair::None,
+        };
+
+        let mut later = Later {
+            fut_pair: Cell::new(fut_pair),
+            context: self.context.clone(),
+            location: self.location,
+            on_drop: self.on_drop,
+        };
+
+        later.label_poller();
+
+        later
+    }
+}
+
+/// Apply the drop policy set with [`on_drop`](./struct.Later.html#method.on_drop) if the future
+/// hasn't completed
+impl<T> Drop for Later<T> where T: Future {
+    fn drop(&mut self) {
+        if let Some(on_drop) = self.on_drop {
+            match self.fut_pair.replace(FuturePair::Empty) {
+                FuturePair::Fut(ref poller) if poller.panicked || poller.stopped().is_some() => (),
+                FuturePair::Fut(poller) => on_drop(poller),
+                _ => (),
+            }
+        }
+    }
+}
+
+/// Wrap a future in a `Later`
+///
+/// This is the same as `Later::new`, so that functions can take any `impl Into<Later<T>>`.
+///
+/// ```rust
+/// # #![feature(futures_api)]
+/// # #![feature(async_await)]
+/// # extern crate alligator;
+/// # use alligator::Later;
+/// # use std::future::Future;
+/// # async fn fetch_token() -> u64 { 42 }
+/// fn authenticate<T>(token: impl Into<Later<T>>) -> bool where T: Future<Output=u64> {
+///     *token.into() != 0
+/// }
+///
+/// # fn main() {
+/// authenticate(fetch_token());
+/// # }
+/// ```
+impl<T> From<T> for Later<T> where T: Future {
+    fn from(future: T) -> Self {
+        Later::new(future)
+    }
+}
+
+/// Await the output of a `Later` from an asynchronous context
+///
+/// Polling a `Later` polls its future if it hasn't completed, or is immediately ready if it has.
+/// The output is moved out of the `Later` when it's returned, the same as with
+/// [`take_value`](./struct.Later.html#method.take_value), so the same `Later` can be either
+/// accessed from synchronous code or awaited from asynchronous code.
+///
+/// ```rust
+/// # #![feature(futures_api)]
+/// # #![feature(async_await)]
+/// # #![feature(await_macro)]
+/// # #[macro_use] extern crate alligator;
+/// # use alligator::Later;
+/// # use std::future::Future;
+/// # async fn fetch_price() -> u32 { 10 }
+/// async fn with_tax<T>(price: Later<T>) -> u32 where T: Future<Output=u32> {
+///     await!(price) * 2
+/// }
+///
+/// # fn main() {
+/// let total = l!{ with_tax(l!{ fetch_price() }) };
+/// # assert_eq!(*total, 20);
+/// # }
+/// ```
+///
+/// # Panic
+/// Polling panics if the output was already taken with `take_value` or returned by a poll
+impl<T,O> Future for Later<T> where T: Future<Output=O> {
+    type Output = O;
+
+    fn poll(self: Pin<&mut Self>, lw: &LocalWaker) -> Poll<O> {
+        let this = unsafe { Pin::get_mut_unchecked(self) };
+
+        match this.poll_ready(lw) {
+            Poll::Ready(()) => Poll::Ready(this.take_value().unwrap_or_else(|| taken())),
+            Poll::Pending => Poll::Pending,
+        }
+    }
 }
 
-impl<T,O> ::std::ops::Deref for Later<T,O> where T: Future<Output=O> {
+/// Dereference the output value of the contained future
+///
+/// The current thread waits for the future to complete if it hasn't already. With the
+/// `strict_deref` feature enabled this panics instead of waiting, and the output must first be
+/// waited for with an explicit accessor such as [`force`](./struct.Later.html#method.force).
+impl<T,O> ::std::ops::Deref for Later<T> where T: Future<Output=O> {
     type Target = O;
 
     fn deref(&self) -> &O {
+        self.strict_deref();
+
         FuturePair::get_ref_from_cell(&self.fut_pair)
     }
 }
 
-impl<T,O> ::std::ops::DerefMut for Later<T,O> where T: Future<Output=O> {
+impl<T,O> ::std::ops::DerefMut for Later<T> where T: Future<Output=O> {
     fn deref_mut(&mut self) -> &mut O {
+        self.strict_deref();
+
         FuturePair::get_ref_from_cell(&self.fut_pair)
     }
 }
 
+/// Force the output when the `Later` is returned from `main`
+///
+/// The output is the result of the program, so a `Later` of a `Result` makes the program exit with
+/// a failure code if the output is an error.
+///
+/// ```rust
+/// # #![feature(futures_api)]
+/// # #![feature(pin)]
+/// # #![feature(arbitrary_self_types)]
+/// # extern crate alligator;
+/// # use alligator::Later;
+/// # use std::future::Future;
+/// # use std::pin::Pin;
+/// # use std::task::{LocalWaker, Poll};
+/// # struct Serve;
+/// # impl Future for Serve {
+/// #     type Output = Result<(), String>;
+/// #     fn poll(self: Pin<&mut Self>, _: &LocalWaker) -> Poll<Self::Output> { Poll::Ready(Ok(())) }
+/// # }
+/// fn serve() -> Serve {
+///     // ...
+/// #   Serve
+/// }
+///
+/// fn main() -> Later<Serve> {
+///     Later::new(serve())
+/// }
+/// ```
+impl<T,O> ::std::process::Termination for Later<T>
+    where T: Future<Output=O>,
+          O: ::std::process::Termination,
+{
+    fn report(self) -> i32 {
+        self.into_inner().report()
+    }
+}
+
+/// The format timeout in microseconds plus one, zero is for no timeout
+static FORMAT_TIMEOUT: AtomicUsize = AtomicUsize::new(0);
+
+/// Set the longest time that formatting a `Later` will wait for its output
+///
+/// By default formatting a `Later` with `Display` or `Debug` waits for the contained future to
+/// complete no matter how long it takes. With a timeout set, if the future doesn't complete within
+/// the timeout the placeholder `<timed out after {timeout}>` is written instead, and the future
+/// will continue from where it left off the next time the `Later` is accessed. A timeout of `None`
+/// goes back to waiting indefinitely.
+///
+/// This is for the `Later`s of log statements, where formatting should never hang the logging
+/// thread.
+pub fn set_format_timeout(timeout: Option<Duration>) {
+    let micros = timeout.map_or(0, |t| {
+        (t.as_secs() as usize).saturating_mul(1_000_000)
+            .saturating_add(t.subsec_micros() as usize)
+            .saturating_add(1)
+    });
+
+    FORMAT_TIMEOUT.store(micros, Ordering::Relaxed);
+}
+
+/// Get the timeout set by [`set_format_timeout`](./fn.set_format_timeout.html)
+pub fn format_timeout() -> Option<Duration> {
+    match FORMAT_TIMEOUT.load(Ordering::Relaxed) {
+        0 => None,
+        micros => Some(Duration::from_micros(micros as u64 - 1)),
+    }
+}
+
+impl<T,O> Later<T> where T: Future<Output=O> {
+
+    /// Format the output with `fmt`, or the timeout placeholder if the format timeout is reached
+    fn fmt_with<F>(&self, f: &mut ::std::fmt::Formatter, fmt: F) -> ::std::fmt::Result
+        where F: FnOnce(&O, &mut ::std::fmt::Formatter) -> ::std::fmt::Result
+    {
+        self.strict_deref();
+
+        match format_timeout() {
+            None => fmt(FuturePair::get_ref_from_cell(&self.fut_pair), f),
+            Some(timeout) => {
+                match FuturePair::get_ref_from_cell_until(&self.fut_pair, clock::now() + timeout) {
+                    Some(val) => fmt(val, f),
+                    None => write!(f, "<timed out after {:?}>", timeout),
+                }
+            },
+        }
+    }
+}
+
 /// Display the output value of the contained future
-impl<T,O> ::std::fmt::Display for Later<T,O> where T: Future<Output=O>, O: ::std::fmt::Display {
+///
+/// See [`set_format_timeout`](./fn.set_format_timeout.html) for limiting how long this waits.
+impl<T,O> ::std::fmt::Display for Later<T> where T: Future<Output=O>, O: ::std::fmt::Display {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        self.fmt_with(f, ::std::fmt::Display::fmt)
+    }
+}
+
+/// Debug the output value of the contained future
+///
+/// See [`set_format_timeout`](./fn.set_format_timeout.html) for limiting how long this waits.
+impl<T,O> ::std::fmt::Debug for Later<T> where T: Future<Output=O>, O: ::std::fmt::Debug {
     fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
-        use std::ops::Deref;
+        self.fmt_with(f, ::std::fmt::Debug::fmt)
+    }
+}
+
+/// Block the current thread polling a future to completion and return its output
+///
+/// This is the same waiting `Later` does when its output is first accessed, without creating a
+/// `Later`. The blocked limit and statistics apply as they would to a `Later`.
+///
+/// # Panic
+/// This panics if the future doesn't complete at the first poll and the current thread can't
+/// block (see [`can_block`](./fn.can_block.html)), such as within a future spawned onto a
+/// [`Pool`](./pool/struct.Pool.html), which should `await!` the future instead. It also panics
+/// if the blocked limit handler does.
+///
+/// ```rust
+/// # #![feature(futures_api)]
+/// # #![feature(async_await)]
+/// # extern crate alligator;
+/// # async fn migrate() -> usize { 3 }
+/// # fn main() {
+/// let applied = alligator::block_on(migrate());
+/// # assert_eq!(applied, 3);
+/// # }
+/// ```
+pub fn block_on<T,O>(future: T) -> O where T: Future<Output=O> {
+    Poller::new(future).poll_to_completion()
+}
+
+/// Block the current thread polling a future to completion
+///
+/// This is for long running futures, such as the loop of a small background service, that are
+/// run for their side effects rather than their output. Use
+/// [`run_with_cancel`](./fn.run_with_cancel.html) to be able to stop the future from another
+/// thread.
+pub fn run<T>(future: T) where T: Future<Output=()> {
+    Poller::new(future).poll_to_completion()
+}
+
+/// Block the current thread polling a future until it completes or `token` is cancelled
+///
+/// When the token is cancelled the future is dropped, and the return is `Err(Cancelled)`. The
+/// future isn't polled again once the token is cancelled, even if it was woken at the same time.
+pub fn run_with_cancel<T>(future: T, token: &CancellationToken) -> Result<(), Cancelled>
+    where T: Future<Output=()>
+{
+    let mut poller = Poller::new(future);
+
+    let key = token.register(poller.waker.local().into_waker());
+
+    let mut permit = None;
+
+    #[cfg(feature = "debug")]
+    let mut registration = None;
+
+    let result = loop {
+        if token.is_cancelled() {
+            break Err(Cancelled::new());
+        }
+
+        match poller.poll() {
+            Poll::Ready(()) => break Ok(()),
+            Poll::Pending => {
+                permit.get_or_insert_with(BlockedPermit::acquire_or_handle);
+
+                #[cfg(feature = "debug")]
+                registration.get_or_insert_with(|| poller.register_blocked());
+
+                poller.wait()
+            },
+        }
+    };
+
+    token.unregister(key);
+
+    result
+}
+
+/// Force every `Later` and collect the successful outputs, stopping at the first error
+///
+/// The `Later`s are forced in order. At the first error the remaining `Later`s are dropped
+/// without being forced and the error is returned.
+pub fn collect_ok<I,T,V,E>(laters: I) -> Result<Vec<V>, E>
+    where I: IntoIterator<Item=Later<T>>,
+          T: Future<Output=Result<V,E>>,
+{
+    laters.into_iter().map(Later::into_inner).collect()
+}
+
+/// Force every `Later` and collect either all the successful outputs or all the errors
+///
+/// Unlike [`collect_ok`](./fn.collect_ok.html), every `Later` is forced even after an error. The
+/// errors are in the same order as their `Later`s.
+pub fn collect_all_ok<I,T,V,E>(laters: I) -> Result<Vec<V>, Vec<E>>
+    where I: IntoIterator<Item=Later<T>>,
+          T: Future<Output=Result<V,E>>,
+{
+    let mut vals = Vec::new();
+    let mut errors = Vec::new();
+
+    for output in laters.into_iter().map(Later::into_inner) {
+        match output {
+            Ok(val) => vals.push(val),
+            Err(e) => errors.push(e),
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(vals)
+    } else {
+        Err(errors)
+    }
+}
+
+/// Call the output value of the contained future
+///
+/// This is for when the output is a closure (or anything else that implements `FnOnce`). The
+/// contained future is polled to completion at the call. With the `strict_deref` feature enabled
+/// the call panics instead if the future hasn't completed, as does dereferencing.
+impl<T,O,Args> FnOnce<Args> for Later<T> where T: Future<Output=O>, O: FnOnce<Args> {
+    type Output = O::Output;
+
+    extern "rust-call" fn call_once(self, args: Args) -> O::Output {
+        self.strict_deref();
+
+        self.into_inner().call_once(args)
+    }
+}
+
+/// Call the output value of the contained future
+///
+/// The contained future is polled to completion at the first call. With the `strict_deref` feature
+/// enabled the call panics instead if the future hasn't completed.
+impl<T,O,Args> FnMut<Args> for Later<T> where T: Future<Output=O>, O: FnMut<Args> {
+    extern "rust-call" fn call_mut(&mut self, args: Args) -> O::Output {
+        self.strict_deref();
+
+        FuturePair::get_ref_from_cell(&self.fut_pair).call_mut(args)
+    }
+}
+
+/// Call the output value of the contained future
+///
+/// The contained future is polled to completion at the first call. With the `strict_deref` feature
+/// enabled the call panics instead if the future hasn't completed.
+impl<T,O,Args> Fn<Args> for Later<T> where T: Future<Output=O>, O: Fn<Args> {
+    extern "rust-call" fn call(&self, args: Args) -> O::Output {
+        self.strict_deref();
 
-        self.deref().fmt(f)
+        FuturePair::get_ref_from_cell(&self.fut_pair).call(args)
     }
 }
 
 /// A sortcut for [`Later::new`](./struct.Later.html#method.new)
+#[cfg(feature = "macros")]
 #[macro_export]
 macro_rules! later {
     ( $future:expr ) => {
-        ::alligator::Later::new($future)
+        ::alligator::Later::new_at($future, ::alligator::Location::new(file!(), line!(), column!()))
     };
 }
 
 /// The sortest sortcut for [`Later::new`](./struct.Later.html#method.new)
+#[cfg(feature = "macros")]
 #[macro_export]
 macro_rules! l {
     ( $future:expr ) => { later!($future)}
 }
+
+/// A shortcut for [`wait_any`](./fn.wait_any.html) that takes the `Later`s by name
+///
+/// `wait_any!(a, b)` is the same as `alligator::wait_any((&mut a, &mut b))`.
+#[cfg(all(feature = "macros", feature = "combinators"))]
+#[macro_export]
+macro_rules! wait_any {
+    ( $($later:expr),+ $(,)* ) => {
+        ::alligator::wait_any(( $(&mut $later,)+ ))
+    };
+}
+
+/// Declare `static`s whose values are the outputs of futures
+///
+/// This is like `lazy_static`, but the value is created by a future. The first access to the
+/// `static` polls the future to completion on the accessing thread, and any other thread that
+/// accesses it at the same time waits for it. The future is only ever created once. The `static`
+/// dereferences to the output, which must be `Send` and `Sync`, and the future must be `Send`.
+///
+/// Each `static` is backed by a [`StaticLater`](./struct.StaticLater.html) of the boxed future.
+/// The threads that wait for another thread to complete the future are parked rather than
+/// spinning, and they count as blocked threads towards the
+/// [blocked limit](./fn.set_blocked_limit.html) as does the thread polling the future.
+///
+/// # Panic
+/// If the future panics, the `static` is poisoned for good: the access that polled it panics, as
+/// does every access from any thread after that (including those waiting on it at the time), and
+/// the future is never created again. Accessing the `static` before it's complete also panics on
+/// a thread that can't block, such as a worker of a [`Pool`](./pool/struct.Pool.html).
+///
+/// # Example
+/// ```rust
+/// # #![feature(futures_api)]
+/// # #![feature(async_await)]
+/// # #[macro_use] extern crate alligator;
+/// # pub struct Config { pub workers: usize }
+/// # async fn load_config() -> Config { Config { workers: 4 } }
+/// later_static! {
+///     static CONFIG: Config = load_config();
+/// }
+///
+/// # fn main() {
+/// println!("running {} workers", CONFIG.workers);
+/// # }
+/// ```
+#[cfg(all(feature = "macros", feature = "static"))]
+#[macro_export]
+macro_rules! later_static {
+    ( $( $(#[$attr:meta])* $vis:vis static $name:ident : $ty:ty = $init:expr ; )* ) => {
+        $(
+            $(#[$attr])*
+            #[allow(non_camel_case_types)]
+            $vis struct $name { _priv: () }
+
+            $(#[$attr])*
+            $vis static $name: $name = $name { _priv: () };
+
+            impl ::std::ops::Deref for $name {
+                type Target = $ty;
+
+                fn deref(&self) -> &$ty {
+                    fn make_future() -> ::std::pin::Pin<Box<dyn ::std::future::Future<Output=$ty> + Send>> {
+                        Box::pinned($init)
+                    }
+
+                    static LATER: ::alligator::StaticLater<
+                        ::std::pin::Pin<Box<dyn ::std::future::Future<Output=$ty> + Send>>,
+                        $ty
+                    > = ::alligator::StaticLater::new(make_future);
+
+                    LATER.get()
+                }
+            }
+        )*
+    };
+}
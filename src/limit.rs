@@ -0,0 +1,202 @@
+//! The limit on the number of threads blocked within the accessors of `Later`s, and the threads
+//! that must never block
+
+use std::cell::Cell;
+use std::sync::{Mutex, Once, ONCE_INIT};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use super::error::AccessError;
+
+thread_local! {
+    /// Why the current thread must never block, if it must not
+    static FORBIDDEN: Cell<Option<&'static str>> = Cell::new(None);
+}
+
+/// Mark the current thread as one that must never block on a `Later`
+///
+/// This is for the threads of the crate's own subsystems, where waiting for a future would wait
+/// on the very thread that has to make the future progress. `reason` describes the thread for the
+/// panic message.
+pub(crate) fn forbid_blocking(reason: &'static str) {
+    FORBIDDEN.with(|f| f.set(Some(reason)))
+}
+
+/// Get why the current thread can't block, `None` if it can
+fn forbidden() -> Option<&'static str> {
+    if cfg!(all(target_arch = "wasm32", not(target_feature = "atomics"))) {
+        return Some("the main thread of a wasm32 target without threads");
+    }
+
+    FORBIDDEN.with(|f| f.get())
+}
+
+/// Check if the current thread can block waiting for a `Later`
+///
+/// This is false on targets where a thread can't be parked (such as wasm32 without atomics) and
+/// on the threads of the crate's own subsystems. Accessors of a `Later` whose future hasn't
+/// completed panic on such a thread rather than deadlock, or return
+/// `AccessError::WouldDeadlock` if they're fallible.
+pub fn can_block() -> bool {
+    forbidden().is_none()
+}
+
+/// The number of threads holding a `BlockedPermit`
+static BLOCKED: AtomicUsize = AtomicUsize::new(0);
+
+/// The limit plus one, zero is for no limit
+static LIMIT: AtomicUsize = AtomicUsize::new(0);
+
+fn handler() -> &'static Mutex<fn(usize)> {
+    static INIT: Once = ONCE_INIT;
+    static mut HANDLER: *const Mutex<fn(usize)> = 0 as *const _;
+
+    unsafe {
+        INIT.call_once(|| {
+            HANDLER = Box::into_raw(Box::new(Mutex::new(default_handler as fn(usize))));
+        });
+
+        &*HANDLER
+    }
+}
+
+fn default_handler(limit: usize) {
+    panic!("a Later would have blocked a thread while {} threads are already blocked on Laters", limit)
+}
+
+/// Set the most threads that may be blocked waiting within the accessors of `Later`s at once
+///
+/// When a thread would have to wait for the future of a `Later` while the limit is reached, the
+/// fallible accessors (such as [`Later::try_force`](./struct.Later.html#method.try_force)) return
+/// [`AccessError::BlockedLimit`](./error/enum.AccessError.html) instead of waiting, and the other
+/// accessors call the handler set with
+/// [`set_blocked_limit_handler`](./fn.set_blocked_limit_handler.html). Waits with a deadline, like
+/// formatting with a [format timeout](./fn.set_format_timeout.html), are counted but never refused.
+///
+/// This keeps a fixed size group of threads from all being blocked on `Later`s that wait on the
+/// same slow dependency. A limit of `None` (the default) allows any number of blocked threads.
+///
+/// # Example
+/// ```rust
+/// # #![feature(futures_api)]
+/// # extern crate alligator;
+/// # use alligator::{AccessError, Later, Pending};
+/// # use std::sync::atomic::{AtomicUsize, Ordering};
+/// # use std::thread;
+/// # use std::time::Duration;
+/// static OVER_LIMIT: AtomicUsize = AtomicUsize::new(0);
+///
+/// fn count_over_limit(_limit: usize) {
+///     OVER_LIMIT.fetch_add(1, Ordering::Relaxed);
+/// }
+///
+/// # fn main() {
+/// alligator::set_blocked_limit(Some(0));
+/// alligator::set_blocked_limit_handler(count_over_limit);
+///
+/// let reply = Later::new(Pending::<u32>::new());
+///
+/// assert_eq!(*reply.try_force().unwrap_err().error(), AccessError::BlockedLimit);
+///
+/// let report = Later::from_join_handle(thread::spawn(|| {
+///     thread::sleep(Duration::from_millis(20));
+///
+///     7
+/// }));
+///
+/// // The handler returns, so the thread waits for the report anyway
+/// assert_eq!(*report.as_ref().unwrap(), 7);
+/// assert_eq!(OVER_LIMIT.load(Ordering::Relaxed), 1);
+/// # }
+/// ```
+pub fn set_blocked_limit(limit: Option<usize>) {
+    LIMIT.store(limit.map_or(0, |l| l.saturating_add(1)), Ordering::Relaxed);
+}
+
+/// Get the limit set by [`set_blocked_limit`](./fn.set_blocked_limit.html)
+pub fn blocked_limit() -> Option<usize> {
+    match LIMIT.load(Ordering::Relaxed) {
+        0 => None,
+        limit => Some(limit - 1),
+    }
+}
+
+/// Set the handler called when an infallible accessor of a `Later` reaches the blocked limit
+///
+/// The handler is called with the limit. If the handler returns, the thread waits for the future
+/// anyway. The default handler panics.
+pub fn set_blocked_limit_handler(handler_fn: fn(usize)) {
+    *handler().lock().unwrap() = handler_fn;
+}
+
+/// The number of threads currently blocked within the accessors of `Later`s
+pub fn blocked_threads() -> usize {
+    BLOCKED.load(Ordering::Relaxed)
+}
+
+/// Counts the holding thread as blocked until dropped
+pub(crate) struct BlockedPermit {
+    _priv: (),
+}
+
+impl BlockedPermit {
+
+    /// Get a permit if the current thread can block and the limit isn't reached
+    pub(crate) fn try_acquire() -> Result<Self, AccessError> {
+        if forbidden().is_some() {
+            return Err(AccessError::WouldDeadlock);
+        }
+
+        let mut blocked = BLOCKED.load(Ordering::Relaxed);
+
+        loop {
+            if let Some(limit) = blocked_limit() {
+                if blocked >= limit {
+                    return Err(AccessError::BlockedLimit);
+                }
+            }
+
+            match BLOCKED.compare_exchange_weak(blocked, blocked + 1, Ordering::AcqRel, Ordering::Relaxed) {
+                Ok(_) => return Ok(BlockedPermit { _priv: () }),
+                Err(current) => blocked = current,
+            }
+        }
+    }
+
+    /// Get a permit even if the limit is reached, `None` if the current thread can't block
+    pub(crate) fn acquire() -> Option<Self> {
+        if forbidden().is_some() {
+            return None;
+        }
+
+        BLOCKED.fetch_add(1, Ordering::AcqRel);
+
+        Some(BlockedPermit { _priv: () })
+    }
+
+    /// Get a permit, calling the handler first if the limit is reached
+    ///
+    /// # Panic
+    /// This panics if the current thread can't block
+    pub(crate) fn acquire_or_handle() -> Self {
+        match Self::try_acquire() {
+            Ok(permit) => permit,
+            Err(AccessError::WouldDeadlock) => panic!("a Later would block {}, where the wait could \
+                never end", forbidden().unwrap_or("this thread")),
+            Err(_) => {
+                let handler_fn = *handler().lock().unwrap();
+
+                handler_fn(blocked_limit().unwrap_or(0));
+
+                BLOCKED.fetch_add(1, Ordering::AcqRel);
+
+                BlockedPermit { _priv: () }
+            },
+        }
+    }
+}
+
+impl Drop for BlockedPermit {
+    fn drop(&mut self) {
+        BLOCKED.fetch_sub(1, Ordering::AcqRel);
+    }
+}
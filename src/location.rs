@@ -0,0 +1,53 @@
+use std::fmt;
+
+/// The location in the source code where a `Later` was created
+///
+/// Locations are recorded by the `l!` and `later!` macros (or by calling `Later::new_at`) in debug
+/// builds, or in any build with the `location` feature enabled. The location is part of the
+/// context of errors returned by the `Later` and of its panic messages.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Location {
+    file: &'static str,
+    line: u32,
+    column: u32,
+}
+
+impl Location {
+
+    /// Create a new `Location`
+    ///
+    /// This is normally created with the `file!`, `line!`, and `column!` macros.
+    pub fn new(file: &'static str, line: u32, column: u32) -> Self {
+        Location {
+            file: file,
+            line: line,
+            column: column,
+        }
+    }
+
+    /// The name of the source file
+    pub fn file(&self) -> &'static str {
+        self.file
+    }
+
+    /// The line within the source file
+    pub fn line(&self) -> u32 {
+        self.line
+    }
+
+    /// The column within the line
+    pub fn column(&self) -> u32 {
+        self.column
+    }
+
+    /// Check if locations are recorded
+    pub(crate) fn is_enabled() -> bool {
+        cfg!(any(debug_assertions, feature = "location"))
+    }
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}:{}", self.file, self.line, self.column)
+    }
+}
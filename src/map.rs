@@ -0,0 +1,185 @@
+//! The futures of `Later`s that were turned into other `Later`s, such as by `map` and `and_then`
+
+use std::future::Future;
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Poll, LocalWaker};
+
+use super::{earliest, Later, Stops};
+
+/// The remaining work of a `Later` that was turned into another `Later`
+///
+/// This is either the future of the `Later`, or its output if the future had already completed.
+pub(crate) enum Source<T,O> {
+    Fut(T),
+    Val(Option<O>),
+}
+
+impl<T,O> Future for Source<T,O> where T: Future<Output=O> {
+    type Output = O;
+
+    fn poll(self: Pin<&mut Self>, lw: &LocalWaker) -> Poll<O> {
+        match unsafe { Pin::get_mut_unchecked(self) } {
+            Source::Fut(ref mut future) => unsafe { Pin::new_unchecked(future) }.poll(lw),
+            Source::Val(ref mut val) => Poll::Ready(val.take().expect("Report a bug if you get this panic")),
+        }
+    }
+}
+
+/// A future that converts the output of a `Later` with `Into`
+///
+/// This is created by [`Later::map_into`](./struct.Later.html#method.map_into).
+pub struct MapInto<T,O,U> {
+    source: Source<T,O>,
+    into: PhantomData<fn(O) -> U>,
+}
+
+impl<T,O,U> MapInto<T,O,U> {
+    pub(crate) fn new(source: Source<T,O>) -> Self {
+        MapInto {
+            source: source,
+            into: PhantomData,
+        }
+    }
+}
+
+impl<T,O,U> Future for MapInto<T,O,U> where T: Future<Output=O>, O: Into<U> {
+    type Output = U;
+
+    fn poll(self: Pin<&mut Self>, lw: &LocalWaker) -> Poll<U> {
+        let this = unsafe { Pin::get_mut_unchecked(self) };
+
+        unsafe { Pin::new_unchecked(&mut this.source) }.poll(lw).map(Into::into)
+    }
+}
+
+/// A future that applies a function to the output of a `Later`
+///
+/// This is created by [`Later::map`](./struct.Later.html#method.map).
+pub struct Map<T,O,F> {
+    source: Source<T,O>,
+    f: Option<F>,
+}
+
+impl<T,O,F> Map<T,O,F> {
+    pub(crate) fn new(source: Source<T,O>, f: F) -> Self {
+        Map {
+            source: source,
+            f: Some(f),
+        }
+    }
+}
+
+impl<T,O,F,U> Future for Map<T,O,F> where T: Future<Output=O>, F: FnOnce(O) -> U {
+    type Output = U;
+
+    fn poll(self: Pin<&mut Self>, lw: &LocalWaker) -> Poll<U> {
+        let this = unsafe { Pin::get_mut_unchecked(self) };
+
+        match unsafe { Pin::new_unchecked(&mut this.source) }.poll(lw) {
+            Poll::Ready(val) => Poll::Ready((this.f.take().expect("Map polled after completion"))(val)),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+/// The stage an `AndThen` is at
+enum Stage<T,O,F,G> {
+    First(Source<T,O>, Option<F>),
+    Second(G),
+}
+
+/// A future that drives the future returned by a function of the output of a `Later`
+///
+/// This is created by [`Later::and_then`](./struct.Later.html#method.and_then).
+pub struct AndThen<T,O,F,G> {
+    stage: Stage<T,O,F,G>,
+}
+
+impl<T,O,F,G> AndThen<T,O,F,G> {
+    pub(crate) fn new(source: Source<T,O>, f: F) -> Self {
+        AndThen {
+            stage: Stage::First(source, Some(f)),
+        }
+    }
+}
+
+impl<T,O,F,G,U> Future for AndThen<T,O,F,G>
+    where T: Future<Output=O>,
+          F: FnOnce(O) -> G,
+          G: Future<Output=U>,
+{
+    type Output = U;
+
+    fn poll(self: Pin<&mut Self>, lw: &LocalWaker) -> Poll<U> {
+        let this = unsafe { Pin::get_mut_unchecked(self) };
+
+        loop {
+            let next = match this.stage {
+                Stage::First(ref mut source, ref mut f) => match unsafe { Pin::new_unchecked(source) }.poll(lw) {
+                    Poll::Ready(val) => (f.take().expect("AndThen polled after completion"))(val),
+                    Poll::Pending => break Poll::Pending,
+                },
+                Stage::Second(ref mut future) => break unsafe { Pin::new_unchecked(future) }.poll(lw),
+            };
+
+            this.stage = Stage::Second(next);
+        }
+    }
+}
+
+/// The stage a `Flatten` is at
+enum Nested<T,T2,O> where T2: Future<Output=O> {
+    Outer(Source<T, Later<T2>>),
+    Inner(Source<T2,O>),
+}
+
+/// A future for the output of the inner `Later` of a `Later` of a `Later`
+///
+/// This is created by [`Later::flatten`](./struct.Later.html#method.flatten).
+pub struct Flatten<T,T2,O> where T2: Future<Output=O> {
+    nested: Nested<T,T2,O>,
+    /// Where the stops of the inner `Later` are put for the poller of the `Later` of this
+    adopted: Arc<Mutex<Stops>>,
+}
+
+impl<T,T2,O> Flatten<T,T2,O> where T2: Future<Output=O> {
+    pub(crate) fn new(source: Source<T, Later<T2>>, adopted: Arc<Mutex<Stops>>) -> Self {
+        Flatten {
+            nested: Nested::Outer(source),
+            adopted: adopted,
+        }
+    }
+}
+
+impl<T,T2,O> Future for Flatten<T,T2,O> where T: Future<Output=Later<T2>>, T2: Future<Output=O> {
+    type Output = O;
+
+    fn poll(self: Pin<&mut Self>, lw: &LocalWaker) -> Poll<O> {
+        let this = unsafe { Pin::get_mut_unchecked(self) };
+
+        loop {
+            let inner = match this.nested {
+                Nested::Outer(ref mut source) => match unsafe { Pin::new_unchecked(source) }.poll(lw) {
+                    Poll::Ready(mut later) => {
+                        // The inner `Later` stops the flattened `Later` as well
+                        let stops = later.take_stops();
+
+                        let mut adopted = this.adopted.lock().unwrap();
+
+                        adopted.cancel.extend(stops.cancel);
+                        adopted.deadline = earliest(adopted.deadline, stops.deadline);
+                        adopted.adopted.extend(stops.adopted);
+
+                        later.into_source()
+                    },
+                    Poll::Pending => break Poll::Pending,
+                },
+                Nested::Inner(ref mut source) => break unsafe { Pin::new_unchecked(source) }.poll(lw),
+            };
+
+            this.nested = Nested::Inner(inner);
+        }
+    }
+}
@@ -0,0 +1,241 @@
+//! Staged lazy computations
+//!
+//! A [`Pipeline`](./struct.Pipeline.html) is a chain of named stages where every stage is an
+//! asynchronous step that consumes the output of the stage before it. Building the pipeline
+//! produces a single `Later` for the output of the last stage along with a
+//! [`PipelineTimings`](./struct.PipelineTimings.html) that records how each stage performed once
+//! the `Later` is forced.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Poll, LocalWaker};
+use std::time::{Duration, Instant};
+
+use super::Later;
+
+/// The recorded timing of a single pipeline stage
+#[derive(Clone, Debug)]
+pub struct StageTiming {
+    /// The name given to the stage
+    pub name: &'static str,
+    /// The number of times the future of the stage was polled
+    pub polls: usize,
+    /// The total time spent within calls to `poll` of the stage's future
+    pub poll_time: Duration,
+    /// The time from the first poll of the stage's future until it was ready
+    pub elapsed: Duration,
+}
+
+/// The timings of the completed stages of a pipeline
+///
+/// Stages are added in the order they complete, which is always the order in which they were
+/// declared. A stage that has not yet completed will not be listed.
+#[derive(Clone, Default)]
+pub struct PipelineTimings {
+    stages: Arc<Mutex<Vec<StageTiming>>>,
+}
+
+impl PipelineTimings {
+
+    /// Get the timings of all completed stages
+    pub fn stages(&self) -> Vec<StageTiming> {
+        self.stages.lock().unwrap().clone()
+    }
+
+    /// Get the timing of the stage with the given name
+    pub fn stage(&self, name: &str) -> Option<StageTiming> {
+        self.stages.lock().unwrap().iter().find(|t| t.name == name).cloned()
+    }
+
+    /// The sum of the elapsed times of all completed stages
+    pub fn total(&self) -> Duration {
+        self.stages.lock().unwrap().iter().fold(Duration::from_secs(0), |acc, t| acc + t.elapsed)
+    }
+
+    fn push(&self, timing: StageTiming) {
+        self.stages.lock().unwrap().push(timing)
+    }
+}
+
+/// A future that records its poll timings into a `PipelineTimings`
+///
+/// Once the future completes it is never polled again, further polls return `Poll::Pending`.
+pub struct Timed<T> {
+    future: Option<T>,
+    name: &'static str,
+    polls: usize,
+    poll_time: Duration,
+    started: Option<Instant>,
+    timings: PipelineTimings,
+}
+
+impl<T,O> Timed<T> where T: Future<Output=O> {
+
+    fn new(name: &'static str, future: T, timings: PipelineTimings) -> Self {
+        Timed {
+            future: Some(future),
+            name: name,
+            polls: 0,
+            poll_time: Duration::from_secs(0),
+            started: None,
+            timings: timings,
+        }
+    }
+}
+
+impl<T,O> Future for Timed<T> where T: Future<Output=O> {
+    type Output = O;
+
+    fn poll(self: Pin<&mut Self>, lw: &LocalWaker) -> Poll<O> {
+        let this = unsafe { Pin::get_mut_unchecked(self) };
+
+        let now = Instant::now();
+
+        let started = *this.started.get_or_insert(now);
+
+        let poll = match this.future {
+            Some(ref mut future) => unsafe { Pin::new_unchecked(future) }.poll(lw),
+            None => return Poll::Pending,
+        };
+
+        this.polls += 1;
+        this.poll_time += now.elapsed();
+
+        if let Poll::Ready(_) = poll {
+            this.future = None;
+
+            this.timings.push( StageTiming {
+                name: this.name,
+                polls: this.polls,
+                poll_time: this.poll_time,
+                elapsed: started.elapsed(),
+            });
+        }
+
+        poll
+    }
+}
+
+enum StageState<P,S,G> {
+    Previous(P, Option<S>),
+    Current(Timed<G>),
+}
+
+/// The future of a pipeline stage
+///
+/// This polls the previous stage to completion, then passes its output to the step of this stage
+/// and polls the returned future.
+pub struct Stage<P,S,G> {
+    state: StageState<P,S,G>,
+    name: &'static str,
+    timings: PipelineTimings,
+}
+
+impl<P,S,G,I,O> Future for Stage<P,S,G>
+    where P: Future<Output=I>,
+          S: FnOnce(I) -> G,
+          G: Future<Output=O>,
+{
+    type Output = O;
+
+    fn poll(self: Pin<&mut Self>, lw: &LocalWaker) -> Poll<O> {
+        let this = unsafe { Pin::get_mut_unchecked(self) };
+
+        loop {
+            let next = match this.state {
+                StageState::Previous(ref mut previous, ref mut step) => {
+                    match unsafe { Pin::new_unchecked(previous) }.poll(lw) {
+                        Poll::Ready(val) => {
+                            let step = step.take().expect("Report a bug if you get this panic");
+
+                            Timed::new(this.name, step(val), this.timings.clone())
+                        },
+                        Poll::Pending => break Poll::Pending,
+                    }
+                },
+                StageState::Current(ref mut current) => {
+                    break unsafe { Pin::new_unchecked(current) }.poll(lw)
+                },
+            };
+
+            this.state = StageState::Current(next);
+        }
+    }
+}
+
+/// A builder of staged lazy computations
+///
+/// Every stage is given a name that is used to identify its timing within the
+/// [`PipelineTimings`](./struct.PipelineTimings.html) returned by `build`.
+///
+/// # Example
+/// ```rust
+/// # #![feature(futures_api)]
+/// # #![feature(async_await)]
+/// # extern crate alligator;
+/// # use alligator::Pipeline;
+/// # async fn fetch() -> u32 { 20 }
+/// # async fn double(v: u32) -> u32 { v * 2 }
+/// # async fn describe(v: u32) -> String { format!("value: {}", v) }
+/// # fn main() {
+/// let (later, timings) = Pipeline::new("fetch", fetch())
+///     .stage("double", double)
+///     .stage("describe", describe)
+///     .build();
+///
+/// println!("{}", later);
+///
+/// for stage in timings.stages() {
+///     println!("{} took {:?}", stage.name, stage.elapsed);
+/// }
+/// # }
+/// ```
+pub struct Pipeline<T> {
+    future: T,
+    timings: PipelineTimings,
+}
+
+impl<T,O> Pipeline<Timed<T>> where T: Future<Output=O> {
+
+    /// Create a new `Pipeline` with the future of the first stage
+    pub fn new( name: &'static str, future: T ) -> Self {
+        let timings = PipelineTimings::default();
+
+        Pipeline {
+            future: Timed::new(name, future, timings.clone()),
+            timings: timings,
+        }
+    }
+}
+
+impl<T,O> Pipeline<T> where T: Future<Output=O> {
+
+    /// Add a stage to the end of the pipeline
+    ///
+    /// The `step` is called with the output of the previous stage only once the previous stage has
+    /// completed.
+    pub fn stage<S,G,U>( self, name: &'static str, step: S ) -> Pipeline<Stage<T,S,G>>
+        where S: FnOnce(O) -> G,
+              G: Future<Output=U>,
+    {
+        Pipeline {
+            future: Stage {
+                state: StageState::Previous(self.future, Some(step)),
+                name: name,
+                timings: self.timings.clone(),
+            },
+            timings: self.timings,
+        }
+    }
+
+    /// Get the timings of the pipeline
+    pub fn timings(&self) -> PipelineTimings {
+        self.timings.clone()
+    }
+
+    /// Build the pipeline into a `Later` for the output of the last stage
+    pub fn build(self) -> (Later<T>, PipelineTimings) {
+        (Later::new(self.future), self.timings)
+    }
+}
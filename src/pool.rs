@@ -0,0 +1,1304 @@
+//! A pool of threads for polling futures in the background
+//!
+//! A [`Pool`](./struct.Pool.html) polls spawned futures on its worker threads, so the future
+//! makes progress without the need for its `Later` to be forced. When the `Later` is forced the
+//! output is either already there, or the forcing thread only waits for the remaining work.
+//!
+//! # Scheduling
+//! Every worker has its own queue of tasks. Tasks spawned from outside of the pool are spread over
+//! the worker queues and a task woken from a worker thread is put into the queue of that worker. A
+//! worker takes tasks from the front of its own queue, and when its queue is empty it steals tasks
+//! from the back of the queues of the other workers. This keeps the workers from contending over a
+//! single queue when many small futures are spawned.
+//!
+//! Futures are spawned with a [`Priority`](./enum.Priority.html). Every queue is split by priority,
+//! and a worker will always take (or steal) a task of a higher priority before a task of a lower
+//! priority.
+//!
+//! When a thread forces the `Later` of a spawned future that is waiting in a queue, the task of
+//! the future is moved to the front of a queue with the high priority, and stays at the high
+//! priority from then on. This keeps the forcing thread from waiting on all of the background work
+//! queued ahead of the future. The same happens to futures spawned onto a pool that are polled by
+//! the future of a `Later` being forced, such as the futures of a `join_all`.
+//!
+//! Futures can also be spawned with a deadline. These are kept in a single queue ordered by
+//! deadline that the workers check before their own queues, so the future with the earliest
+//! deadline is always polled first. A future that hasn't completed by its deadline is woken by the
+//! [timer thread](../time/index.html), then dropped, and its output becomes a
+//! [`DeadlineExceeded`](../error/struct.DeadlineExceeded.html) error.
+//!
+//! # Cancellation
+//! Dropping the `Later` of a spawned future before it completes cancels the future. The future is
+//! dropped by the next worker to take its task instead of being polled, so the work it holds (along
+//! with every future it's built from) is released instead of being polled to completion for an
+//! output that nobody will read.
+//!
+//! All of the work spawned onto a pool can be cancelled at once with
+//! [`Pool::cancel_all`](./struct.Pool.html#method.cancel_all), or the work spawned onto every pool
+//! with [`cancel_all`](../fn.cancel_all.html). This is for the drain phase of a service, to stop
+//! speculative background work quickly. Forcing the `Later` of a cancelled future panics.
+//!
+//! # Blocking
+//! The workers of a pool must never block on a `Later`, as the future being waited for may be
+//! queued behind the very task that is waiting. Within a spawned future the accessors of a `Later`
+//! whose future hasn't completed panic, and the fallible ones return
+//! [`AccessError::WouldDeadlock`](../error/enum.AccessError.html). A spawned future should await
+//! other futures instead.
+//!
+//! ```rust
+//! # #![feature(futures_api)]
+//! # #![feature(async_await)]
+//! # extern crate alligator;
+//! # use alligator::{Later, Pending, Pool};
+//! # use alligator::error::AccessError;
+//! async fn peek() -> Result<u32, AccessError> {
+//!     let reply = Later::new(Pending::<u32>::new());
+//!
+//!     reply.try_force().map(|val| *val).map_err(|e| *e.error())
+//! }
+//!
+//! # fn main() {
+//! let pool = Pool::new(1);
+//!
+//! assert_eq!(*pool.spawn(peek()), Err(AccessError::WouldDeadlock));
+//! # }
+//! ```
+
+use std::any::Any;
+use std::cell::Cell;
+use std::cmp::Ordering as CmpOrdering;
+use std::collections::{BinaryHeap, VecDeque};
+use std::future::Future;
+use std::panic::{self, AssertUnwindSafe};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, Condvar, Once, ONCE_INIT, TryLockError, Weak};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::task::{Wake, Waker, Poll, LocalWaker};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use super::{limit, CancellationToken, Later};
+#[cfg(feature = "combinators")]
+use super::LaterGroup;
+use super::error::DeadlineExceeded;
+use super::time::{self, Sleep};
+
+type BoxedTask = Pin<Box<dyn Future<Output=()> + Send>>;
+
+thread_local! {
+    /// The pool (by the address of its shared state) and the index of the worker of this thread
+    static WORKER: Cell<Option<(usize, usize)>> = Cell::new(None);
+}
+
+/// The priority of a future spawned onto a pool
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Priority {
+    /// For futures whose output is needed as soon as possible
+    High,
+    /// The priority used by `Pool::spawn`
+    Normal,
+    /// For background work such as prefetching
+    Low,
+}
+
+impl Priority {
+    const COUNT: usize = 3;
+
+    /// The index of the queue for this priority, lower is serviced first
+    fn index(self) -> usize {
+        match self {
+            Priority::High   => 0,
+            Priority::Normal => 1,
+            Priority::Low    => 2,
+        }
+    }
+}
+
+impl Default for Priority {
+    fn default() -> Self {
+        Priority::Normal
+    }
+}
+
+/// The queue of a worker
+///
+/// There is one list of tasks for every priority.
+struct Queue {
+    tasks: [VecDeque<Arc<Task>>; Priority::COUNT],
+}
+
+impl Queue {
+    fn new() -> Self {
+        Queue {
+            tasks: [VecDeque::new(), VecDeque::new(), VecDeque::new()],
+        }
+    }
+}
+
+/// A task within the deadline queue
+///
+/// The ordering is reversed so that the `BinaryHeap` of the deadline queue pops the earliest
+/// deadline first. Tasks with the same deadline are popped in the order they were pushed.
+struct DeadlineEntry {
+    deadline: Instant,
+    sequence: usize,
+    task: Arc<Task>,
+}
+
+impl PartialEq for DeadlineEntry {
+    fn eq(&self, other: &Self) -> bool {
+        self.deadline == other.deadline && self.sequence == other.sequence
+    }
+}
+
+impl Eq for DeadlineEntry {}
+
+impl PartialOrd for DeadlineEntry {
+    fn partial_cmp(&self, other: &Self) -> Option<CmpOrdering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for DeadlineEntry {
+    fn cmp(&self, other: &Self) -> CmpOrdering {
+        other.deadline.cmp(&self.deadline).then(other.sequence.cmp(&self.sequence))
+    }
+}
+
+/// A future spawned onto a pool
+///
+/// The task is registered with the cancellation tokens of `cancel_all` and of its pool from when
+/// it's spawned until its future is dropped, so that cancelling either token runs the task to drop
+/// its future.
+///
+/// The pool is only referenced weakly, as the queues of the pool hold the task. Once the pool and
+/// its workers are gone the task is never queued again.
+struct Task {
+    future: Mutex<Option<BoxedTask>>,
+    scheduled: AtomicBool,
+    priority: Priority,
+    deadline: Option<Instant>,
+    shared: Weak<Shared>,
+    tokens: Mutex<Vec<(CancellationToken, usize)>>,
+    boosted: AtomicBool,
+}
+
+impl Task {
+
+    /// Put the task into a queue of the pool if it isn't already in one
+    fn schedule(self: &Arc<Self>) {
+        if !self.scheduled.swap(true, Ordering::AcqRel) {
+            if let Some(shared) = self.shared.upgrade() {
+                shared.push(self.clone())
+            }
+        }
+    }
+
+    /// The priority of the queue the task is put into
+    fn priority(&self) -> Priority {
+        if self.boosted.load(Ordering::Acquire) { Priority::High } else { self.priority }
+    }
+
+    /// Move the task to the front of a high priority queue, a thread is blocked on its output
+    fn boost(self: &Arc<Self>) {
+        if self.deadline.is_some() || self.boosted.swap(true, Ordering::AcqRel) {
+            return;
+        }
+
+        let shared = match self.shared.upgrade() {
+            Some(shared) => shared,
+            None => return,
+        };
+
+        // A task that isn't in a queue is either being polled or waiting to be woken, either way
+        // it will be queued at the high priority the next time it is scheduled.
+        if shared.remove(self) {
+            shared.push(self.clone())
+        }
+    }
+
+    /// Register the task with the cancellation tokens
+    fn register(self: &Arc<Self>, tokens: Vec<CancellationToken>) {
+        let registered = tokens.into_iter().map(|token| {
+            let key = token.register(::std::task::local_waker_from_nonlocal(self.clone()).into_waker());
+
+            (token, key)
+        })
+        .collect();
+
+        *self.tokens.lock().unwrap() = registered;
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.tokens.lock().unwrap().iter().any(|&(ref token, _)| token.is_cancelled())
+    }
+
+    /// Remove the task from the cancellation tokens, which also drops the wakers of the task
+    /// held by the tokens
+    fn unregister(&self) {
+        for (token, key) in self.tokens.lock().unwrap().drain(..) {
+            token.unregister(key)
+        }
+    }
+
+    fn run(self: &Arc<Self>) {
+        // A task woken while it's polled is queued again. The worker that takes it puts it back
+        // into a queue rather than waiting for the poll to finish.
+        let mut future = match self.future.try_lock() {
+            Ok(future) => future,
+            Err(TryLockError::WouldBlock) => {
+                if let Some(shared) = self.shared.upgrade() {
+                    shared.push(self.clone())
+                }
+
+                return;
+            },
+            Err(TryLockError::Poisoned(error)) => panic!("{}", error),
+        };
+
+        // Cleared once the future is locked, so any wake up from here on queues the task for the
+        // next poll
+        self.scheduled.store(false, Ordering::Release);
+
+        if future.is_some() && self.is_cancelled() {
+            *future = None;
+
+            return self.unregister();
+        }
+
+        let done = match *future {
+            Some(ref mut future) => {
+                let lw = ::std::task::local_waker_from_nonlocal(self.clone());
+
+                future.as_mut().poll(&lw).is_ready()
+            },
+            None => false,
+        };
+
+        if done {
+            *future = None;
+
+            self.unregister();
+        }
+    }
+}
+
+/// The token cancelled by `cancel_all`
+fn global_token() -> &'static Mutex<CancellationToken> {
+    static INIT: Once = ONCE_INIT;
+    static mut TOKEN: *const Mutex<CancellationToken> = 0 as *const _;
+
+    unsafe {
+        INIT.call_once(|| {
+            TOKEN = Box::into_raw(Box::new(Mutex::new(CancellationToken::new())));
+        });
+
+        &*TOKEN
+    }
+}
+
+/// Cancel every future that is spawned onto any pool
+///
+/// Every future that hasn't completed is dropped the next time a worker would poll it, and the
+/// workers are woken to do so right away. Futures spawned after this returns are not affected.
+/// Forcing the `Later` of a cancelled future panics.
+pub fn cancel_all() {
+    let token = ::std::mem::replace(&mut *global_token().lock().unwrap(), CancellationToken::new());
+
+    token.cancel()
+}
+
+impl Wake for Task {
+    fn wake(arc_self: &Arc<Self>) {
+        arc_self.schedule()
+    }
+}
+
+/// The state shared between a pool and its workers
+struct Shared {
+    cancel: Mutex<CancellationToken>,
+    queues: Vec<Mutex<Queue>>,
+    deadlines: Mutex<BinaryHeap<DeadlineEntry>>,
+    /// The number of tasks in `deadlines`, a search only takes the lock when this isn't zero
+    deadline_tasks: AtomicUsize,
+    next: AtomicUsize,
+    /// The number of workers that are parked or about to park, a push only takes the `sleep` lock
+    /// to wake one up when this isn't zero
+    sleepers: AtomicUsize,
+    sleep: Mutex<usize>,
+    cvar: Condvar,
+    shutdown: AtomicBool,
+}
+
+impl Shared {
+
+    fn id(self: &Arc<Self>) -> usize {
+        &**self as *const Shared as usize
+    }
+
+    fn push(self: &Arc<Self>, task: Arc<Task>) {
+        if let Some(deadline) = task.deadline {
+            {
+                let mut deadlines = self.deadlines.lock().unwrap();
+
+                deadlines.push( DeadlineEntry {
+                    deadline: deadline,
+                    sequence: self.next.fetch_add(1, Ordering::Relaxed),
+                    task: task,
+                });
+
+                self.deadline_tasks.fetch_add(1, Ordering::SeqCst);
+            }
+
+            return self.notify();
+        }
+
+        let id = self.id();
+
+        let index = match WORKER.with(|w| w.get()) {
+            Some((pool, worker)) if pool == id => worker,
+            _ => self.next.fetch_add(1, Ordering::Relaxed) % self.queues.len(),
+        };
+
+        let priority = task.priority();
+
+        {
+            let mut queue = self.queues[index].lock().unwrap();
+
+            if task.boosted.load(Ordering::Acquire) {
+                queue.tasks[priority.index()].push_front(task)
+            } else {
+                queue.tasks[priority.index()].push_back(task)
+            }
+        }
+
+        self.notify();
+    }
+
+    /// Remove the task from the queue it is in, the return is false if it isn't in one
+    fn remove(&self, task: &Arc<Task>) -> bool {
+        for queue in self.queues.iter() {
+            let mut queue = queue.lock().unwrap();
+
+            for tasks in queue.tasks.iter_mut() {
+                if let Some(position) = tasks.iter().position(|t| Arc::ptr_eq(t, task)) {
+                    tasks.remove(position);
+
+                    return true;
+                }
+            }
+        }
+
+        false
+    }
+
+    /// Wake up a sleeping worker, if there is one
+    fn notify(&self) {
+        if self.sleepers.load(Ordering::SeqCst) == 0 {
+            return;
+        }
+
+        let mut tokens = self.sleep.lock().unwrap();
+
+        if *tokens < self.queues.len() {
+            *tokens += 1;
+        }
+
+        self.cvar.notify_one();
+    }
+
+    /// Get a task from the deadline queue, the queue of `worker`, or steal one from another worker
+    ///
+    /// Every queue is searched for a task of a higher priority before any queue is searched for a
+    /// task of a lower priority.
+    fn find_task(&self, worker: usize) -> Option<Arc<Task>> {
+        if self.deadline_tasks.load(Ordering::SeqCst) != 0 {
+            let mut deadlines = self.deadlines.lock().unwrap();
+
+            if let Some(entry) = deadlines.pop() {
+                self.deadline_tasks.fetch_sub(1, Ordering::SeqCst);
+
+                return Some(entry.task);
+            }
+        }
+
+        let len = self.queues.len();
+
+        (0..Priority::COUNT).filter_map(|priority| {
+            if let Some(task) = self.queues[worker].lock().unwrap().tasks[priority].pop_front() {
+                return Some(task);
+            }
+
+            (1..len).filter_map(|offset| {
+                self.queues[(worker + offset) % len].lock().unwrap().tasks[priority].pop_back()
+            })
+            .next()
+        })
+        .next()
+    }
+
+    /// Wait for a task to be pushed into a queue
+    ///
+    /// The queues are searched again once the worker is counted as a sleeper, as a push between
+    /// the last search and the count skipped waking anyone. The task found by that search is
+    /// returned instead of waiting.
+    fn park(&self, worker: usize) -> Option<Arc<Task>> {
+        self.sleepers.fetch_add(1, Ordering::SeqCst);
+
+        let task = self.find_task(worker);
+
+        if task.is_none() {
+            let mut tokens = self.sleep.lock().unwrap();
+
+            while *tokens == 0 && !self.shutdown.load(Ordering::Acquire) {
+                tokens = self.cvar.wait(tokens).unwrap();
+            }
+
+            if *tokens != 0 {
+                *tokens -= 1;
+            }
+        }
+
+        self.sleepers.fetch_sub(1, Ordering::SeqCst);
+
+        task
+    }
+
+    fn run_worker(self: Arc<Self>, worker: usize) {
+        limit::forbid_blocking("a pool worker thread");
+
+        WORKER.with(|w| w.set(Some((self.id(), worker))));
+
+        loop {
+            match self.find_task(worker) {
+                Some(task) => task.run(),
+                None if self.shutdown.load(Ordering::Acquire) => break,
+                None => if let Some(task) = self.park(worker) {
+                    task.run()
+                },
+            }
+        }
+    }
+}
+
+/// A pool of worker threads for polling futures
+///
+/// Dropping the pool cancels every future spawned onto it that hasn't completed, as by
+/// [`cancel_all`](#method.cancel_all), and the workers exit once they have dropped the futures.
+/// Forcing the `Later` of such a future panics rather than waiting for a pool that is gone.
+pub struct Pool {
+    shared: Arc<Shared>,
+}
+
+impl Pool {
+
+    /// Create a new `Pool` with `threads` worker threads
+    ///
+    /// # Panic
+    /// This panics if `threads` is zero
+    pub fn new(threads: usize) -> Self {
+        assert!(threads != 0, "a pool must have at least one thread");
+
+        let shared = Arc::new( Shared {
+            cancel: Mutex::new(CancellationToken::new()),
+            queues: (0..threads).map(|_| Mutex::new(Queue::new())).collect(),
+            deadlines: Mutex::new(BinaryHeap::new()),
+            deadline_tasks: AtomicUsize::new(0),
+            next: AtomicUsize::new(0),
+            sleepers: AtomicUsize::new(0),
+            sleep: Mutex::new(0),
+            cvar: Condvar::new(),
+            shutdown: AtomicBool::new(false),
+        });
+
+        for worker in 0..threads {
+            let shared = shared.clone();
+
+            thread::Builder::new()
+                .name(format!("alligator-worker-{}", worker))
+                .spawn(move || shared.run_worker(worker))
+                .expect("failed to spawn a pool worker thread");
+        }
+
+        Pool { shared: shared }
+    }
+
+    /// The number of worker threads of the pool
+    pub fn threads(&self) -> usize {
+        self.shared.queues.len()
+    }
+
+    /// Spawn a future onto the pool
+    ///
+    /// The future is polled by the pool's workers right away. The returned `Later` will only wait
+    /// for the output if the future hasn't completed by the time the `Later` is forced. If the
+    /// future panics, the panic is resumed on the thread that forces the `Later`.
+    pub fn spawn<T,O>(&self, future: T) -> Later<Handle<O>>
+        where T: Future<Output=O> + Send + 'static,
+              O: Send + 'static,
+    {
+        self.spawn_with_priority(future, Priority::Normal)
+    }
+
+    /// Spawn a future onto the pool with the given priority
+    ///
+    /// This is the same as `spawn` except that the future is polled before any future of a lower
+    /// priority that is waiting to be polled.
+    pub fn spawn_with_priority<T,O>(&self, future: T, priority: Priority) -> Later<Handle<O>>
+        where T: Future<Output=O> + Send + 'static,
+              O: Send + 'static,
+    {
+        self.spawn_task(future, priority, None)
+    }
+
+    /// Spawn a future onto the pool that must complete before `deadline`
+    ///
+    /// Futures with a deadline are polled before all other futures, earliest deadline first. The
+    /// future is woken at the deadline, so even a future that is waiting on something that never
+    /// happens is dropped once the deadline passes and the output is a `DeadlineExceeded` error.
+    ///
+    /// ```rust
+    /// # #![feature(futures_api)]
+    /// # extern crate alligator;
+    /// # use alligator::{Pending, Pool};
+    /// # use std::time::{Duration, Instant};
+    /// # fn main() {
+    /// let pool = Pool::new(1);
+    ///
+    /// let reply = pool.spawn_with_deadline(Pending::<u32>::new(), Instant::now() + Duration::from_millis(10));
+    ///
+    /// assert!(reply.is_err());
+    /// # }
+    /// ```
+    pub fn spawn_with_deadline<T,O>(&self, future: T, deadline: Instant)
+        -> Later<Handle<Result<O,DeadlineExceeded>>>
+        where T: Future<Output=O> + Send + 'static,
+              O: Send + 'static,
+    {
+        let future = Deadline {
+            future: future,
+            sleep: time::sleep_until(deadline),
+        };
+
+        self.spawn_task(future, Priority::Normal, Some(deadline))
+    }
+
+    fn spawn_task<T,O>(&self, future: T, priority: Priority, deadline: Option<Instant>)
+        -> Later<Handle<O>>
+        where T: Future<Output=O> + Send + 'static,
+              O: Send + 'static,
+    {
+        Later::new(self.spawn_handle(future, priority, deadline))
+    }
+
+    /// Spawn a future onto the pool and return the handle without a `Later`
+    fn spawn_handle<T,O>(&self, future: T, priority: Priority, deadline: Option<Instant>) -> Handle<O>
+        where T: Future<Output=O> + Send + 'static,
+              O: Send + 'static,
+    {
+        let slot = Arc::new(Mutex::new(Slot { output: None, waker: None, task: None, runner: None, cancelled: false }));
+
+        let completion = Completion {
+            future: future,
+            slot: slot.clone(),
+            completed: false,
+        };
+
+        let task = self.task(Box::pinned(completion), priority, deadline);
+
+        slot.lock().unwrap().runner = Some(Arc::downgrade(&task));
+
+        self.start(&task);
+
+        Handle { slot: slot }
+    }
+
+    /// Spawn a future onto the pool without a handle, dropping its output
+    ///
+    /// The future is polled to completion even though nothing waits for it, a panic of the future
+    /// is dropped along with it. It's only cancelled by `cancel_all`. This is where
+    /// `Later::detach` and `DropPolicy::Detach` hand over futures when the `pool` feature is
+    /// enabled.
+    pub fn detach<T>(&self, future: T) where T: Future + Send + 'static {
+        let task = self.task(Box::pinned(Detached { future: future }), Priority::Normal, None);
+
+        self.start(&task);
+    }
+
+    fn task(&self, future: BoxedTask, priority: Priority, deadline: Option<Instant>) -> Arc<Task> {
+        Arc::new( Task {
+            future: Mutex::new(Some(future)),
+            scheduled: AtomicBool::new(false),
+            priority: priority,
+            deadline: deadline,
+            shared: Arc::downgrade(&self.shared),
+            tokens: Mutex::new(Vec::new()),
+            boosted: AtomicBool::new(false),
+        })
+    }
+
+    /// Register the task with the cancellation tokens and put it into a queue
+    fn start(&self, task: &Arc<Task>) {
+        task.register(vec![
+            global_token().lock().unwrap().clone(),
+            self.shared.cancel.lock().unwrap().clone(),
+        ]);
+
+        task.schedule();
+    }
+
+    /// Apply `f` to every item and poll the returned futures on the pool
+    ///
+    /// See [`par_map`](../fn.par_map.html).
+    pub fn par_map<I,F,G,O>(&self, items: I, f: F) -> Later<ParMap<I::IntoIter,F,O>>
+        where I: IntoIterator,
+              F: FnMut(I::Item) -> G,
+              G: Future<Output=O> + Send + 'static,
+              O: Send + 'static,
+    {
+        Later::new(ParMap::new(self, items, f))
+    }
+
+    /// Cancel every future spawned onto this pool
+    ///
+    /// This is the same as [`cancel_all`](../fn.cancel_all.html) but only for the futures of this
+    /// pool.
+    pub fn cancel_all(&self) {
+        let token = ::std::mem::replace(&mut *self.shared.cancel.lock().unwrap(), CancellationToken::new());
+
+        token.cancel()
+    }
+
+    /// Get the pool shared by the process
+    ///
+    /// The pool is created on the first call, with the number of threads set by
+    /// [`set_global_threads`](#method.set_global_threads) or otherwise `GLOBAL_THREADS` threads.
+    pub fn global() -> &'static Pool {
+        static INIT: Once = ONCE_INIT;
+        static mut GLOBAL: *const Pool = 0 as *const _;
+
+        unsafe {
+            INIT.call_once(|| {
+                let threads = match GLOBAL_CONFIG.swap(GLOBAL_CREATED, Ordering::AcqRel) {
+                    0 => GLOBAL_THREADS,
+                    threads => threads,
+                };
+
+                GLOBAL = Box::into_raw(Box::new(Pool::new(threads)));
+            });
+
+            &*GLOBAL
+        }
+    }
+
+    /// Set the number of worker threads of the pool returned by `global`
+    ///
+    /// This must be called before the global pool is first used, such as at the start of `main`,
+    /// as the number of threads of a pool can't change. The return is false if the global pool was
+    /// already created, in which case nothing is changed.
+    ///
+    /// # Panic
+    /// This panics if `threads` is zero
+    pub fn set_global_threads(threads: usize) -> bool {
+        assert!(threads != 0, "a pool must have at least one thread");
+
+        let mut current = GLOBAL_CONFIG.load(Ordering::Acquire);
+
+        while current != GLOBAL_CREATED {
+            match GLOBAL_CONFIG.compare_exchange(current, threads, Ordering::AcqRel, Ordering::Acquire) {
+                Ok(_) => return true,
+                Err(actual) => current = actual,
+            }
+        }
+
+        false
+    }
+}
+
+/// The number of worker threads of the pool returned by `Pool::global`, unless it's set with
+/// `Pool::set_global_threads`
+pub const GLOBAL_THREADS: usize = 4;
+
+/// The number of threads set with `set_global_threads`, zero if it wasn't set, or `GLOBAL_CREATED`
+/// once the global pool is created
+static GLOBAL_CONFIG: AtomicUsize = AtomicUsize::new(0);
+
+const GLOBAL_CREATED: usize = ::std::usize::MAX;
+
+impl<O> Later<Handle<O>> {
+
+    /// Create a `Later` of a future that is polled in the background right away
+    ///
+    /// The future is spawned onto the [global pool](./pool/struct.Pool.html#method.global), so it
+    /// makes progress while the current thread does other work, and accessing the output only waits
+    /// if the future hasn't completed by then. This is the same as `Pool::global().spawn(future)`.
+    ///
+    /// ```rust
+    /// # #![feature(futures_api)]
+    /// # #![feature(async_await)]
+    /// # extern crate alligator;
+    /// # use alligator::Later;
+    /// # async fn build_index() -> Vec<u32> { vec![1, 2, 3] }
+    /// # fn parse_arguments() {}
+    /// # fn main() {
+    /// let index = Later::eager(build_index());
+    ///
+    /// // The index is built while the arguments are parsed
+    /// parse_arguments();
+    ///
+    /// assert_eq!(index.len(), 3);
+    /// # }
+    /// ```
+    pub fn eager<T>(future: T) -> Self
+        where T: Future<Output=O> + Send + 'static,
+              O: Send + 'static,
+    {
+        Pool::global().spawn(future)
+    }
+}
+
+#[cfg(feature = "combinators")]
+impl<O> LaterGroup<Handle<O>, O> where O: Send + 'static {
+
+    /// Create an empty `LaterGroup` whose futures are spawned onto a pool
+    ///
+    /// The futures are added with [`spawn`](#method.spawn) or [`spawn_on`](#method.spawn_on), so
+    /// they make progress in the background from when they're added instead of only while an
+    /// output of the group is waited for. Waiting on the group only waits for the futures that
+    /// haven't completed by then, and dropping the group cancels them.
+    ///
+    /// ```rust
+    /// # #![feature(futures_api)]
+    /// # #![feature(async_await)]
+    /// # extern crate alligator;
+    /// # use alligator::LaterGroup;
+    /// # async fn upload(chunk: u32) -> u32 { chunk }
+    /// # fn main() {
+    /// let mut uploads = LaterGroup::spawned();
+    ///
+    /// for chunk in 0..3 {
+    ///     uploads.spawn(upload(chunk));
+    /// }
+    ///
+    /// let done = uploads.resolve_all();
+    /// # assert_eq!(done, vec![0, 1, 2]);
+    /// # }
+    /// ```
+    pub fn spawned() -> Self {
+        LaterGroup::new()
+    }
+
+    /// Spawn a future onto the global pool and add it to the group
+    pub fn spawn<T>(&mut self, future: T) where T: Future<Output=O> + Send + 'static {
+        self.spawn_on(Pool::global(), future)
+    }
+
+    /// Spawn a future onto `pool` and add it to the group
+    pub fn spawn_on<T>(&mut self, pool: &Pool, future: T) where T: Future<Output=O> + Send + 'static {
+        self.push(pool.spawn_handle(future, Priority::Normal, None))
+    }
+}
+
+impl Drop for Pool {
+    fn drop(&mut self) {
+        // Every task that hasn't completed is registered with the token, so cancelling it puts
+        // them all into the queues before the workers are told to exit once the queues are empty
+        self.cancel_all();
+
+        self.shared.shutdown.store(true, Ordering::Release);
+
+        let _lock = self.shared.sleep.lock().unwrap();
+
+        self.shared.cvar.notify_all();
+    }
+}
+
+/// A future that fails once its deadline passes
+///
+/// The timer of the deadline is set with the waker of the task, so the task is polled at the
+/// deadline even if the future is never woken again.
+struct Deadline<T> {
+    future: T,
+    sleep: Sleep,
+}
+
+impl<T,O> Future for Deadline<T> where T: Future<Output=O> {
+    type Output = Result<O,DeadlineExceeded>;
+
+    fn poll(self: Pin<&mut Self>, lw: &LocalWaker) -> Poll<Result<O,DeadlineExceeded>> {
+        let this = unsafe { Pin::get_mut_unchecked(self) };
+
+        if this.sleep.is_elapsed() {
+            return Poll::Ready(Err(DeadlineExceeded::new()));
+        }
+
+        if let Poll::Ready(val) = unsafe { Pin::new_unchecked(&mut this.future) }.poll(lw) {
+            return Poll::Ready(Ok(val));
+        }
+
+        match unsafe { Pin::new_unchecked(&mut this.sleep) }.poll(lw) {
+            Poll::Ready(()) => Poll::Ready(Err(DeadlineExceeded::new())),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+/// The future of a task spawned with `Pool::detach`
+///
+/// The output is dropped, and so is a panic so that it doesn't unwind the worker.
+struct Detached<T> {
+    future: T,
+}
+
+impl<T> Future for Detached<T> where T: Future {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, lw: &LocalWaker) -> Poll<()> {
+        let future = &mut unsafe { Pin::get_mut_unchecked(self) }.future;
+
+        match panic::catch_unwind(AssertUnwindSafe(|| unsafe { Pin::new_unchecked(future) }.poll(lw))) {
+            Ok(Poll::Pending) => Poll::Pending,
+            Ok(Poll::Ready(_)) | Err(_) => Poll::Ready(()),
+        }
+    }
+}
+
+/// Where the output of a spawned future is put for its handle
+///
+/// `task` is the waker of the task polling the future, for waking the task when the handle is
+/// dropped so that the future is dropped promptly. `runner` is the task itself, for boosting it
+/// when a thread is forcing the handle.
+struct Slot<O> {
+    output: Option<thread::Result<O>>,
+    waker: Option<Waker>,
+    task: Option<Waker>,
+    runner: Option<Weak<Task>>,
+    cancelled: bool,
+}
+
+impl<O> Slot<O> {
+    fn complete(&mut self, output: thread::Result<O>) {
+        self.output = Some(output);
+
+        if let Some(waker) = self.waker.take() {
+            waker.wake()
+        }
+    }
+}
+
+/// The future that is polled by the pool
+///
+/// This passes the output (or the panic) of the spawned future to its handle.
+struct Completion<T,O> where T: Future<Output=O> {
+    future: T,
+    slot: Arc<Mutex<Slot<O>>>,
+    completed: bool,
+}
+
+impl<T,O> Future for Completion<T,O> where T: Future<Output=O> {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, lw: &LocalWaker) -> Poll<()> {
+        let this = unsafe { Pin::get_mut_unchecked(self) };
+
+        {
+            let mut slot = this.slot.lock().unwrap();
+
+            if slot.cancelled {
+                this.completed = true;
+
+                return Poll::Ready(());
+            }
+
+            slot.task = Some(lw.as_waker().clone());
+        }
+
+        let future = &mut this.future;
+
+        let output = match panic::catch_unwind(AssertUnwindSafe(|| unsafe { Pin::new_unchecked(future) }.poll(lw))) {
+            Ok(Poll::Ready(val)) => Ok(val),
+            Ok(Poll::Pending) => return Poll::Pending,
+            Err(payload) => Err(payload),
+        };
+
+        this.slot.lock().unwrap().complete(output);
+
+        this.completed = true;
+
+        Poll::Ready(())
+    }
+}
+
+impl<T,O> Drop for Completion<T,O> where T: Future<Output=O> {
+    fn drop(&mut self) {
+        if !self.completed {
+            let payload: Box<dyn Any + Send> = Box::new("the future was dropped by the pool before it completed, \
+                either the pool was shut down or the future was cancelled");
+
+            self.slot.lock().unwrap().complete(Err(payload));
+        }
+    }
+}
+
+/// A future for the output of a future spawned onto a pool
+///
+/// Dropping the handle before it's ready cancels the spawned future.
+pub struct Handle<O> {
+    slot: Arc<Mutex<Slot<O>>>,
+}
+
+impl<O> Future for Handle<O> {
+    type Output = O;
+
+    fn poll(self: Pin<&mut Self>, lw: &LocalWaker) -> Poll<O> {
+        let runner = {
+            let mut slot = self.slot.lock().unwrap();
+
+            match slot.output.take() {
+                Some(Ok(val)) => return Poll::Ready(val),
+                Some(Err(payload)) => panic::resume_unwind(payload),
+                None => (),
+            }
+
+            slot.waker = Some(lw.as_waker().clone());
+
+            if super::is_forcing() {
+                slot.runner.as_ref().and_then(Weak::upgrade)
+            } else {
+                None
+            }
+        };
+
+        if let Some(task) = runner {
+            task.boost()
+        }
+
+        Poll::Pending
+    }
+}
+
+impl<O> Drop for Handle<O> {
+    fn drop(&mut self) {
+        let task = {
+            let mut slot = self.slot.lock().unwrap();
+
+            slot.cancelled = true;
+            slot.waker = None;
+
+            slot.task.take()
+        };
+
+        if let Some(task) = task {
+            task.wake()
+        }
+    }
+}
+
+/// A future for the outputs of futures created from the items of an iterator and polled on a pool
+///
+/// At most `limit` of the futures are on the pool at once, by default this is the number of threads
+/// of the pool. The outputs are in the same order as the items.
+pub struct ParMap<'a,I,F,O> where I: Iterator {
+    pool: &'a Pool,
+    items: I,
+    f: F,
+    limit: usize,
+    in_flight: Vec<(usize, Handle<O>)>,
+    outputs: Vec<Option<O>>,
+}
+
+impl<'a,I,F,G,O> ParMap<'a,I,F,O>
+    where I: Iterator,
+          F: FnMut(I::Item) -> G,
+          G: Future<Output=O> + Send + 'static,
+          O: Send + 'static,
+{
+    /// Create a new `ParMap` for the futures returned by `f` for every item
+    pub fn new<T>(pool: &'a Pool, items: T, f: F) -> Self where T: IntoIterator<IntoIter=I, Item=I::Item> {
+        ParMap {
+            limit: pool.threads(),
+            pool: pool,
+            items: items.into_iter(),
+            f: f,
+            in_flight: Vec::new(),
+            outputs: Vec::new(),
+        }
+    }
+
+    /// Set the most futures that are on the pool at once
+    ///
+    /// # Panic
+    /// This panics if `limit` is zero
+    pub fn limit(mut self, limit: usize) -> Self {
+        assert!(limit != 0, "the limit must not be zero");
+
+        self.limit = limit;
+        self
+    }
+
+    /// Spawn futures for the next items until the limit is reached or there are no more items
+    fn fill(&mut self) {
+        while self.in_flight.len() < self.limit {
+            match self.items.next() {
+                Some(item) => {
+                    let handle = self.pool.spawn_handle((self.f)(item), Priority::Normal, None);
+
+                    self.in_flight.push((self.outputs.len(), handle));
+                    self.outputs.push(None);
+                },
+                None => break,
+            }
+        }
+    }
+}
+
+impl<'a,I,F,G,O> Future for ParMap<'a,I,F,O>
+    where I: Iterator,
+          F: FnMut(I::Item) -> G,
+          G: Future<Output=O> + Send + 'static,
+          O: Send + 'static,
+{
+    type Output = Vec<O>;
+
+    fn poll(self: Pin<&mut Self>, lw: &LocalWaker) -> Poll<Vec<O>> {
+        let this = unsafe { Pin::get_mut_unchecked(self) };
+
+        loop {
+            this.fill();
+
+            if this.in_flight.is_empty() {
+                break Poll::Ready(
+                    this.outputs.drain(..).map(|o| o.expect("Report a bug if you get this panic")).collect()
+                );
+            }
+
+            let before = this.in_flight.len();
+
+            let mut i = 0;
+
+            // Polled through the handle so that the task is boosted when this is forced
+            while i < this.in_flight.len() {
+                match Pin::new(&mut this.in_flight[i].1).poll(lw) {
+                    Poll::Ready(val) => {
+                        let (index, _) = this.in_flight.swap_remove(i);
+
+                        this.outputs[index] = Some(val);
+                    },
+                    Poll::Pending => i += 1,
+                }
+            }
+
+            if this.in_flight.len() == before {
+                break Poll::Pending;
+            }
+        }
+    }
+}
+
+/// Apply an asynchronous function to every item with the futures polled on the global pool
+///
+/// The returned futures are spawned onto [`Pool::global`](./pool/struct.Pool.html#method.global),
+/// with at most as many of them on the pool at once as the pool has threads. Use
+/// [`ParMap::limit`](./pool/struct.ParMap.html#method.limit) with `Later::new` for a different
+/// limit. The outputs are in the same order as the items.
+///
+/// # Example
+/// ```rust
+/// # #![feature(futures_api)]
+/// # #![feature(async_await)]
+/// # extern crate alligator;
+/// # async fn fetch(page: u32) -> String { format!("page {}", page) }
+/// # fn main() {
+/// let pages = alligator::par_map(1..=10, fetch);
+///
+/// for page in pages.iter() {
+///     println!("{}", page);
+/// }
+/// # }
+/// ```
+pub fn par_map<I,F,G,O>(items: I, f: F) -> Later<ParMap<'static,I::IntoIter,F,O>>
+    where I: IntoIterator,
+          F: FnMut(I::Item) -> G,
+          G: Future<Output=O> + Send + 'static,
+          O: Send + 'static,
+{
+    Pool::global().par_map(items, f)
+}
+
+/// Spawn a future onto the global pool and get a `Later` of its output
+///
+/// The future is polled by [`Pool::global`](./pool/struct.Pool.html#method.global) right away, the
+/// returned `Later` only waits for whatever work remains when it's accessed. Dropping the `Later`
+/// before the future completes cancels the future. This is the same as
+/// [`Later::eager`](./struct.Later.html#method.eager).
+///
+/// # Example
+/// ```rust
+/// # #![feature(futures_api)]
+/// # #![feature(async_await)]
+/// # extern crate alligator;
+/// # async fn resize(image: Vec<u8>) -> Vec<u8> { image }
+/// # fn main() {
+/// let thumbnail = alligator::spawn(resize(vec![0; 64]));
+///
+/// // ...
+///
+/// assert_eq!(thumbnail.len(), 64);
+/// # }
+/// ```
+pub fn spawn<T,O>(future: T) -> Later<Handle<O>>
+    where T: Future<Output=O> + Send + 'static,
+          O: Send + 'static,
+{
+    Pool::global().spawn(future)
+}
+
+/// Run a blocking function on a blocking thread and get a `Later` of its return
+///
+/// This is for expensive synchronous work, such as hashing a large file, which would hold up a
+/// worker of a pool for as long as it runs. `f` is run by one of the blocking threads, which are
+/// separate from the workers of every pool and are allowed to block. The returned `Later` only
+/// waits for `f` to return if it hasn't by the time the `Later` is accessed. If `f` panics, the
+/// panic is resumed on the thread that forces the `Later`. Dropping the `Later` doesn't stop `f`,
+/// its return is dropped once it's done.
+///
+/// # Threads
+/// A blocking thread is started when a function is queued while every blocking thread is busy, up
+/// to [`BLOCKING_THREADS`](./pool/constant.BLOCKING_THREADS.html) of them. Past that the functions
+/// wait in a queue for a thread to be free, so a function that waits on another function queued
+/// behind it can wait forever. A blocking thread that has been idle for
+/// [`BLOCKING_KEEP_ALIVE_SECS`](./pool/constant.BLOCKING_KEEP_ALIVE_SECS.html) exits.
+///
+/// # Example
+/// ```rust
+/// # #![feature(futures_api)]
+/// # extern crate alligator;
+/// # fn checksum(data: &[u8]) -> u64 { data.iter().map(|b| *b as u64).sum() }
+/// # fn main() {
+/// let data = vec![1; 4096];
+///
+/// let sum = alligator::spawn_blocking(move || checksum(&data));
+///
+/// assert_eq!(*sum, 4096);
+/// # }
+/// ```
+pub fn spawn_blocking<F,O>(f: F) -> Later<Handle<O>>
+    where F: FnOnce() -> O + Send + 'static,
+          O: Send + 'static,
+{
+    let slot = Arc::new(Mutex::new(Slot { output: None, waker: None, task: None, runner: None, cancelled: false }));
+
+    let thread_slot = slot.clone();
+
+    blocking().run(move || {
+        let output = panic::catch_unwind(AssertUnwindSafe(f));
+
+        thread_slot.lock().unwrap().complete(output);
+    });
+
+    Later::new(Handle { slot: slot })
+}
+
+/// Run `f` on a blocking thread without a `Later` for its return
+pub(crate) fn run_blocking<F>(f: F) where F: FnOnce() + Send + 'static {
+    blocking().run(f)
+}
+
+/// The most threads that run the functions of `spawn_blocking` at once
+pub const BLOCKING_THREADS: usize = 64;
+
+/// How long a blocking thread waits for a function to run before it exits
+pub const BLOCKING_KEEP_ALIVE_SECS: u64 = 10;
+
+/// A function queued by `spawn_blocking`, it's only ever called once
+type BlockingJob = Box<dyn FnMut() + Send>;
+
+/// The threads that run the functions of `spawn_blocking`
+struct Blocking {
+    state: Mutex<BlockingState>,
+    cvar: Condvar,
+}
+
+struct BlockingState {
+    queue: VecDeque<BlockingJob>,
+    threads: usize,
+    idle: usize,
+}
+
+fn blocking() -> &'static Blocking {
+    static INIT: Once = ONCE_INIT;
+    static mut BLOCKING: *const Blocking = 0 as *const _;
+
+    unsafe {
+        INIT.call_once(|| {
+            BLOCKING = Box::into_raw(Box::new( Blocking {
+                state: Mutex::new( BlockingState {
+                    queue: VecDeque::new(),
+                    threads: 0,
+                    idle: 0,
+                }),
+                cvar: Condvar::new(),
+            }));
+        });
+
+        &*BLOCKING
+    }
+}
+
+impl Blocking {
+
+    /// Queue `f` and start a thread for it if every thread is busy and there's room for another
+    fn run<F>(&'static self, f: F) where F: FnOnce() + Send + 'static {
+        let mut f = Some(f);
+
+        let mut state = self.state.lock().unwrap();
+
+        state.queue.push_back(Box::new(move || if let Some(f) = f.take() { f() }));
+
+        if state.queue.len() > state.idle && state.threads < BLOCKING_THREADS {
+            state.threads += 1;
+
+            thread::Builder::new()
+                .name(String::from("alligator-blocking"))
+                .spawn(move || self.run_thread())
+                .expect("failed to spawn a blocking thread");
+        } else {
+            self.cvar.notify_one();
+        }
+    }
+
+    /// The loop of a blocking thread
+    fn run_thread(&self) {
+        let keep_alive = Duration::from_secs(BLOCKING_KEEP_ALIVE_SECS);
+
+        let mut state = self.state.lock().unwrap();
+
+        loop {
+            match state.queue.pop_front() {
+                Some(mut job) => {
+                    drop(state);
+
+                    // The functions of `spawn_blocking` catch their own panics, this keeps the
+                    // thread for the other waits handed over to it
+                    let _ = panic::catch_unwind(AssertUnwindSafe(|| job()));
+
+                    state = self.state.lock().unwrap();
+                },
+                None => {
+                    state.idle += 1;
+
+                    let (woken, timeout) = self.cvar.wait_timeout(state, keep_alive).unwrap();
+
+                    state = woken;
+                    state.idle -= 1;
+
+                    if timeout.timed_out() && state.queue.is_empty() {
+                        state.threads -= 1;
+
+                        return;
+                    }
+                },
+            }
+        }
+    }
+}
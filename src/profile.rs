@@ -0,0 +1,147 @@
+//! Profiler markers for polls and waits
+//!
+//! With a [`Profiler`](./trait.Profiler.html) set by [`set_profiler`](./fn.set_profiler.html),
+//! every poll of the future of a `Later` and every time a thread waits for the future to wake it
+//! is wrapped in a profiler scope. This is the glue for instrumenting profilers such as puffin or
+//! Tracy, so that the regions where a thread was blocked on a `Later` show up in their captures.
+//!
+//! A scope is described by a [`Marker`](./struct.Marker.html) with the label of the `Later`, where
+//! it was created, and the type name of its future. The label is the first context given to the
+//! `Later` with [`Later::context`](../struct.Later.html#method.context). The first poll of a future
+//! happens when its `Later` is created, before the `Later` can be given a context or a location, so
+//! that scope is never labelled.
+//!
+//! # Example
+//! A profiler for puffin might look like the following.
+//! ```rust,ignore
+//! use std::any::Any;
+//! use alligator::profile::{Marker, Profiler};
+//!
+//! struct Puffin;
+//!
+//! impl Profiler for Puffin {
+//!     fn enter(&self, marker: &Marker) -> Box<dyn Any> {
+//!         let data = marker.label().unwrap_or(marker.type_name());
+//!
+//!         Box::new(puffin::ProfilerScope::new(marker.kind().name(), file!(), data))
+//!     }
+//! }
+//!
+//! static PUFFIN: Puffin = Puffin;
+//!
+//! alligator::profile::set_profiler(&PUFFIN);
+//! ```
+
+use std::any::Any;
+use std::sync::{Once, ONCE_INIT};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use super::Location;
+
+/// What a profiler scope covers
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Kind {
+    /// A call to `poll` of a future
+    Poll,
+    /// A wait for the future to wake the waiting thread
+    Park,
+}
+
+impl Kind {
+
+    /// The name of the kind, `"alligator::poll"` or `"alligator::park"`
+    pub fn name(self) -> &'static str {
+        match self {
+            Kind::Poll => "alligator::poll",
+            Kind::Park => "alligator::park",
+        }
+    }
+}
+
+/// The description of a profiler scope
+#[derive(Clone, Copy, Debug)]
+pub struct Marker<'a> {
+    kind: Kind,
+    label: Option<&'a str>,
+    location: Option<&'a Location>,
+    type_name: &'static str,
+}
+
+impl<'a> Marker<'a> {
+
+    /// What the scope covers
+    pub fn kind(&self) -> Kind {
+        self.kind
+    }
+
+    /// The label of the `Later`, if it has one
+    pub fn label(&self) -> Option<&'a str> {
+        self.label
+    }
+
+    /// Where the `Later` was created, if it is known
+    pub fn location(&self) -> Option<&'a Location> {
+        self.location
+    }
+
+    /// The type name of the future of the `Later`
+    pub fn type_name(&self) -> &'static str {
+        self.type_name
+    }
+}
+
+/// A profiler that records scopes
+pub trait Profiler: Sync {
+
+    /// Enter a scope described by `marker`
+    ///
+    /// The scope is exited when the returned guard is dropped, which is always on the thread that
+    /// entered the scope.
+    fn enter(&self, marker: &Marker) -> Box<dyn Any>;
+}
+
+static SET: AtomicBool = AtomicBool::new(false);
+
+static INIT: Once = ONCE_INIT;
+
+static mut PROFILER: Option<&'static dyn Profiler> = None;
+
+/// Set the profiler for the process
+///
+/// The profiler can only be set once, the return is false if a profiler was already set.
+pub fn set_profiler(profiler: &'static dyn Profiler) -> bool {
+    let mut set = false;
+
+    INIT.call_once(|| {
+        unsafe { PROFILER = Some(profiler) };
+
+        SET.store(true, Ordering::Release);
+
+        set = true;
+    });
+
+    set
+}
+
+/// Get the type name of `T`
+pub(crate) fn type_name<T>() -> &'static str {
+    unsafe { ::std::intrinsics::type_name::<T>() }
+}
+
+/// Enter a scope of the profiler, if one is set
+pub(crate) fn enter(kind: Kind, label: Option<&str>, location: Option<&Location>, type_name: &'static str)
+    -> Option<Box<dyn Any>>
+{
+    if !SET.load(Ordering::Acquire) {
+        return None;
+    }
+
+    let marker = Marker {
+        kind: kind,
+        label: label,
+        location: location,
+        type_name: type_name,
+    };
+
+    unsafe { PROFILER }.map(|profiler| profiler.enter(&marker))
+}
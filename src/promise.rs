@@ -0,0 +1,108 @@
+//! A `Later` whose output is handed over by another thread
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Poll, LocalWaker, Waker};
+
+use super::Later;
+
+/// The state shared by a `Promise` and its future
+struct Shared<O> {
+    value: Option<O>,
+    waker: Option<Waker>,
+    broken: bool,
+}
+
+impl<O> Shared<O> {
+    fn wake(&mut self) {
+        if let Some(waker) = self.waker.take() {
+            waker.wake()
+        }
+    }
+}
+
+/// The sending half of [`promise`](./fn.promise.html)
+///
+/// Dropping a `Promise` without fulfilling it makes the `Later` of the promise panic when it's
+/// accessed instead of waiting forever.
+pub struct Promise<O> {
+    shared: Arc<Mutex<Shared<O>>>,
+}
+
+impl<O> Promise<O> {
+
+    /// Hand the value to the `Later` of the promise, waking a thread waiting for it
+    pub fn fulfill(self, value: O) {
+        let mut shared = self.shared.lock().unwrap();
+
+        shared.value = Some(value);
+
+        shared.wake();
+    }
+}
+
+impl<O> Drop for Promise<O> {
+    fn drop(&mut self) {
+        let mut shared = self.shared.lock().unwrap();
+
+        if shared.value.is_none() {
+            shared.broken = true;
+
+            shared.wake();
+        }
+    }
+}
+
+/// The future of the `Later` of a promise
+///
+/// This is created by [`promise`](./fn.promise.html).
+///
+/// # Panic
+/// Polling panics if the `Promise` was dropped without being fulfilled
+pub struct Promised<O> {
+    shared: Arc<Mutex<Shared<O>>>,
+}
+
+impl<O> Future for Promised<O> {
+    type Output = O;
+
+    fn poll(self: Pin<&mut Self>, lw: &LocalWaker) -> Poll<O> {
+        let mut shared = self.shared.lock().unwrap();
+
+        match shared.value.take() {
+            Some(value) => Poll::Ready(value),
+            None if shared.broken => panic!("the Promise of a Later was dropped without being fulfilled"),
+            None => {
+                shared.waker = Some(lw.as_waker().clone());
+
+                Poll::Pending
+            },
+        }
+    }
+}
+
+/// Create a `Later` along with the `Promise` for handing it a value
+///
+/// This is a one-shot hand over between threads. Accessing the output of the `Later` waits until
+/// the value is given with [`Promise::fulfill`](./struct.Promise.html#method.fulfill).
+///
+/// # Example
+/// ```rust
+/// # #![feature(futures_api)]
+/// # extern crate alligator;
+/// # fn main() {
+/// let (promise, answer) = alligator::promise();
+///
+/// ::std::thread::spawn(move || promise.fulfill(42));
+///
+/// assert_eq!(*answer, 42);
+/// # }
+/// ```
+pub fn promise<O>() -> (Promise<O>, Later<Promised<O>>) {
+    let shared = Arc::new(Mutex::new(Shared { value: None, waker: None, broken: false }));
+
+    let promise = Promise { shared: shared.clone() };
+
+    (promise, Later::new(Promised { shared: shared }))
+}
@@ -0,0 +1,266 @@
+//! A reactor for futures that wait on io readiness
+//!
+//! Futures of sockets and other io handles need something to tell them when the handle is ready,
+//! otherwise they are never woken while their `Later` waits. The reactor is a single thread that
+//! waits on the readiness events of the OS (through mio) and wakes the futures that are waiting on
+//! them. The thread is started the first time a handle is registered.
+//!
+//! A future registers its handle with [`Registration::new`](./struct.Registration.html#method.new)
+//! and, whenever an operation on the handle would block, clears the readiness and waits on it with
+//! [`poll_readable`](./struct.Registration.html#method.poll_readable) or
+//! [`poll_writable`](./struct.Registration.html#method.poll_writable).
+//!
+//! # Example
+//! ```rust
+//! # #![feature(futures_api)]
+//! # #![feature(pin)]
+//! # #![feature(arbitrary_self_types)]
+//! # extern crate alligator;
+//! # extern crate mio;
+//! # use alligator::Later;
+//! # use alligator::reactor::Registration;
+//! # use mio::net::UdpSocket;
+//! # use std::future::Future;
+//! # use std::io;
+//! # use std::pin::Pin;
+//! # use std::task::{LocalWaker, Poll};
+//! struct Datagram {
+//!     socket: UdpSocket,
+//!     registration: Registration,
+//! }
+//!
+//! impl Future for Datagram {
+//!     type Output = io::Result<Vec<u8>>;
+//!
+//!     fn poll(self: Pin<&mut Self>, lw: &LocalWaker) -> Poll<io::Result<Vec<u8>>> {
+//!         let mut buf = vec![0; 1500];
+//!
+//!         loop {
+//!             if self.registration.poll_readable(lw).is_pending() {
+//!                 return Poll::Pending;
+//!             }
+//!
+//!             match self.socket.recv(&mut buf) {
+//!                 Ok(len) => return Poll::Ready(Ok(buf[..len].to_vec())),
+//!                 Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => self.registration.clear_readable(),
+//!                 Err(e) => return Poll::Ready(Err(e)),
+//!             }
+//!         }
+//!     }
+//! }
+//!
+//! # fn main() {
+//! let socket = UdpSocket::bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+//! let address = socket.local_addr().unwrap();
+//! let registration = Registration::new(&socket).unwrap();
+//!
+//! let datagram = Later::new(Datagram { socket: socket, registration: registration });
+//!
+//! UdpSocket::bind(&"127.0.0.1:0".parse().unwrap()).unwrap().send_to(b"ping", &address).unwrap();
+//!
+//! assert_eq!(datagram.as_ref().unwrap(), b"ping");
+//! # }
+//! ```
+
+use std::collections::HashMap;
+use std::io;
+use std::sync::{Arc, Mutex, Once, ONCE_INIT};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::task::{Poll, LocalWaker, Waker};
+use std::thread;
+
+use mio::{Events, Evented, PollOpt, Ready, Token};
+
+use super::limit;
+
+/// The readiness of a registered handle and the wakers of the futures waiting on it
+struct Entry {
+    ready: Ready,
+    reader: Option<Waker>,
+    writer: Option<Waker>,
+}
+
+impl Entry {
+
+    /// The handle can be read, or it's in a state (such as closed) where a read won't block
+    fn readable(&self) -> bool {
+        !(self.ready - Ready::writable()).is_empty()
+    }
+
+    /// The handle can be written, or it's in a state where a write won't block
+    fn writable(&self) -> bool {
+        !(self.ready - Ready::readable()).is_empty()
+    }
+}
+
+struct Reactor {
+    poll: ::mio::Poll,
+    entries: Mutex<HashMap<usize, Arc<Mutex<Entry>>>>,
+    next: AtomicUsize,
+}
+
+impl Reactor {
+    fn run(&self) {
+        let mut events = Events::with_capacity(1024);
+
+        loop {
+            if let Err(e) = self.poll.poll(&mut events, None) {
+                if e.kind() == io::ErrorKind::Interrupted {
+                    continue;
+                }
+
+                panic!("the alligator reactor failed to poll for events: {}", e);
+            }
+
+            for event in events.iter() {
+                let entry = match self.entries.lock().unwrap().get(&event.token().0) {
+                    Some(entry) => entry.clone(),
+                    None => continue,
+                };
+
+                let mut entry = entry.lock().unwrap();
+
+                entry.ready = entry.ready | event.readiness();
+
+                if entry.readable() {
+                    if let Some(waker) = entry.reader.take() {
+                        waker.wake()
+                    }
+                }
+
+                if entry.writable() {
+                    if let Some(waker) = entry.writer.take() {
+                        waker.wake()
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Get the reactor, starting its thread on the first call
+fn reactor() -> &'static Reactor {
+    static INIT: Once = ONCE_INIT;
+    static mut REACTOR: *const Reactor = 0 as *const _;
+
+    unsafe {
+        INIT.call_once(|| {
+            let reactor = Reactor {
+                poll: ::mio::Poll::new().expect("failed to create the alligator reactor"),
+                entries: Mutex::new(HashMap::new()),
+                next: AtomicUsize::new(0),
+            };
+
+            REACTOR = Box::into_raw(Box::new(reactor));
+
+            let reactor: &'static Reactor = &*REACTOR;
+
+            thread::Builder::new()
+                .name(String::from("alligator-reactor"))
+                .spawn(move || {
+                    limit::forbid_blocking("the alligator reactor thread");
+
+                    reactor.run()
+                })
+                .expect("failed to spawn the alligator reactor thread");
+        });
+
+        &*REACTOR
+    }
+}
+
+/// The registration of an io handle with the reactor
+///
+/// The handle is registered for both reading and writing, edge triggered. That means the reactor
+/// only reports the handle as ready when its readiness changes, so a future must keep using the
+/// handle until an operation would block (and clear the readiness) before waiting on it again.
+/// Dropping the registration stops the reactor from waking anything for the handle.
+///
+/// Dropping doesn't deregister the handle from the OS, as the registration doesn't hold the
+/// handle. The OS drops it once the handle is closed, so a registration dropped along with its
+/// handle needs nothing more. A handle that outlives its registration should be deregistered with
+/// [`deregister`](#method.deregister) instead, otherwise its readiness events keep waking the
+/// reactor thread only to be ignored.
+pub struct Registration {
+    key: usize,
+    entry: Arc<Mutex<Entry>>,
+}
+
+impl Registration {
+
+    /// Register a handle with the reactor
+    ///
+    /// The handle starts out as ready for both reading and writing, so the first operation on it
+    /// is always tried.
+    pub fn new<E>(io: &E) -> io::Result<Self> where E: Evented {
+        let reactor = reactor();
+
+        let key = reactor.next.fetch_add(1, Ordering::Relaxed);
+
+        let entry = Arc::new(Mutex::new( Entry {
+            ready: Ready::readable() | Ready::writable(),
+            reader: None,
+            writer: None,
+        }));
+
+        reactor.entries.lock().unwrap().insert(key, entry.clone());
+
+        if let Err(e) = reactor.poll.register(io, Token(key), Ready::readable() | Ready::writable(), PollOpt::edge()) {
+            reactor.entries.lock().unwrap().remove(&key);
+
+            return Err(e);
+        }
+
+        Ok(Registration { key: key, entry: entry })
+    }
+
+    /// Check if the handle is ready to be read, waking the current task once it is if it isn't
+    pub fn poll_readable(&self, lw: &LocalWaker) -> Poll<()> {
+        let mut entry = self.entry.lock().unwrap();
+
+        if entry.readable() {
+            Poll::Ready(())
+        } else {
+            entry.reader = Some(lw.as_waker().clone());
+
+            Poll::Pending
+        }
+    }
+
+    /// Check if the handle is ready to be written, waking the current task once it is if it isn't
+    pub fn poll_writable(&self, lw: &LocalWaker) -> Poll<()> {
+        let mut entry = self.entry.lock().unwrap();
+
+        if entry.writable() {
+            Poll::Ready(())
+        } else {
+            entry.writer = Some(lw.as_waker().clone());
+
+            Poll::Pending
+        }
+    }
+
+    /// Deregister the handle from the reactor and the OS
+    ///
+    /// `io` must be the handle given to [`new`](#method.new). The registration is dropped even if
+    /// the OS fails to deregister the handle.
+    pub fn deregister<E>(self, io: &E) -> io::Result<()> where E: Evented {
+        reactor().poll.deregister(io)
+    }
+
+    /// Mark the handle as not readable, this must be called when a read would block
+    pub fn clear_readable(&self) {
+        self.entry.lock().unwrap().ready.remove(Ready::readable())
+    }
+
+    /// Mark the handle as not writable, this must be called when a write would block
+    pub fn clear_writable(&self) {
+        self.entry.lock().unwrap().ready.remove(Ready::writable())
+    }
+}
+
+impl Drop for Registration {
+    fn drop(&mut self) {
+        reactor().entries.lock().unwrap().remove(&self.key);
+    }
+}
@@ -0,0 +1,60 @@
+use std::future::Future;
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::task::{Poll, LocalWaker};
+
+/// A future that is immediately ready with a value
+///
+/// # Panic
+/// Polling after the value was returned panics
+pub struct Ready<O> {
+    val: Option<O>,
+}
+
+impl<O> Ready<O> {
+
+    /// Create a new `Ready` with the value
+    pub fn new(val: O) -> Self {
+        Ready { val: Some(val) }
+    }
+}
+
+// The value is never pinned
+impl<O> Unpin for Ready<O> {}
+
+impl<O> Future for Ready<O> {
+    type Output = O;
+
+    fn poll(mut self: Pin<&mut Self>, _: &LocalWaker) -> Poll<O> {
+        Poll::Ready(self.val.take().expect("Ready polled after completion"))
+    }
+}
+
+/// A future that never completes
+///
+/// The waker given to `poll` is never woken.
+pub struct Pending<O> {
+    output: PhantomData<fn() -> O>,
+}
+
+impl<O> Pending<O> {
+
+    /// Create a new `Pending`
+    pub fn new() -> Self {
+        Pending { output: PhantomData }
+    }
+}
+
+impl<O> Default for Pending<O> {
+    fn default() -> Self {
+        Pending::new()
+    }
+}
+
+impl<O> Future for Pending<O> {
+    type Output = O;
+
+    fn poll(self: Pin<&mut Self>, _: &LocalWaker) -> Poll<O> {
+        Poll::Pending
+    }
+}
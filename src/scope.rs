@@ -0,0 +1,204 @@
+//! Lazy values of futures that borrow from the stack
+//!
+//! A future that borrows local data can't be polled by a thread that might outlive the data. Within
+//! [`scope`](./fn.scope.html) such futures can be spawned onto helper threads anyway, as every
+//! helper thread is joined before `scope` returns. Futures that don't need a helper thread can be
+//! wrapped with [`Scope::later`](./struct.Scope.html#method.later) instead, which ties the `Later`
+//! to the scope so that it is always forced or dropped before `scope` returns.
+
+use std::any::Any;
+use std::cell::RefCell;
+use std::future::Future;
+use std::marker::PhantomData;
+use std::mem;
+use std::panic::{self, AssertUnwindSafe};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Poll, LocalWaker, Waker};
+use std::thread::{self, JoinHandle};
+
+use super::{Later, Poller};
+
+/// A scope for spawning futures that borrow from outside of the scope
+///
+/// This is created by [`scope`](./fn.scope.html).
+pub struct Scope<'env> {
+    threads: RefCell<Vec<JoinHandle<()>>>,
+    env: PhantomData<&'env mut &'env ()>,
+}
+
+impl<'env> Scope<'env> {
+
+    /// Spawn a future onto a helper thread of the scope
+    ///
+    /// The helper thread polls the future to completion right away. The returned `Later` will only
+    /// wait for the output if the future hasn't completed by the time the `Later` is forced. If the
+    /// future panics, the panic is resumed on the thread that forces the `Later`.
+    pub fn spawn<'scope,T,O>(&'scope self, future: T) -> Later<ScopedHandle<'scope,O>>
+        where T: Future<Output=O> + Send + 'env,
+              O: Send + 'env,
+    {
+        let slot = Arc::new(Mutex::new(Slot { output: None, waker: None }));
+
+        let thread_slot = slot.clone();
+
+        let run: Box<dyn FnOnce() + Send + 'env> = Box::new(move || {
+            let output = panic::catch_unwind(AssertUnwindSafe(|| Poller::new(future).poll_to_completion()));
+
+            thread_slot.lock().unwrap().complete(output);
+        });
+
+        // The thread is always joined before `scope` returns, so nothing borrowed for 'env is used
+        // after 'env ends.
+        let run: Box<dyn FnOnce() + Send + 'static> = unsafe { mem::transmute(run) };
+
+        let thread = thread::Builder::new()
+            .name(String::from("alligator-scope"))
+            .spawn(run)
+            .expect("failed to spawn a scope helper thread");
+
+        self.threads.borrow_mut().push(thread);
+
+        Later::new(ScopedHandle { slot: slot, scope: PhantomData })
+    }
+
+    /// Wrap a future that borrows from outside of the scope in a `Later` that can't leave the scope
+    ///
+    /// Unlike [`spawn`](#method.spawn) the future is polled by whichever thread forces the
+    /// `Later`, the same as with `Later::new`. The `Later` borrows the scope, so it is either forced
+    /// or dropped (cancelling the future) before `scope` returns.
+    ///
+    /// ```rust
+    /// # #![feature(futures_api)]
+    /// # #![feature(async_await)]
+    /// # extern crate alligator;
+    /// # async fn checksum(data: &[u8]) -> u32 { data.iter().map(|b| *b as u32).sum() }
+    /// # fn main() {
+    /// let data = vec![1, 2, 3];
+    ///
+    /// let sum = alligator::scope(|s| {
+    ///     let sum = s.later(checksum(&data));
+    ///
+    ///     *sum
+    /// });
+    /// # assert_eq!(sum, 6);
+    /// # }
+    /// ```
+    pub fn later<'scope,T>(&'scope self, future: T) -> Later<Scoped<'scope,T>> where T: Future + 'env {
+        Later::new(Scoped { future: future, scope: PhantomData })
+    }
+
+    fn join_all(&self) {
+        for thread in self.threads.borrow_mut().drain(..) {
+            // Panics of the futures are caught and passed to their handles
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Joins all the helper threads of the scope when the scope exits, even by a panic
+struct JoinOnDrop<'a, 'env: 'a> {
+    scope: &'a Scope<'env>,
+}
+
+impl<'a, 'env> Drop for JoinOnDrop<'a, 'env> {
+    fn drop(&mut self) {
+        self.scope.join_all()
+    }
+}
+
+/// Create a scope for spawning futures that borrow local data
+///
+/// Every helper thread spawned within the scope is joined before this returns, so the futures
+/// spawned with [`Scope::spawn`](./struct.Scope.html#method.spawn) may borrow anything that
+/// outlives the call to `scope`.
+///
+/// # Example
+/// ```rust
+/// # #![feature(futures_api)]
+/// # #![feature(async_await)]
+/// # extern crate alligator;
+/// # async fn sum(values: &[u64]) -> u64 { values.iter().sum() }
+/// # fn main() {
+/// let values: Vec<u64> = (0..100).collect();
+///
+/// let total = alligator::scope(|s| {
+///     let low = s.spawn(sum(&values[..50]));
+///     let high = s.spawn(sum(&values[50..]));
+///
+///     low.into_inner() + high.into_inner()
+/// });
+/// # assert_eq!(total, 4950);
+/// # }
+/// ```
+pub fn scope<'env,F,R>(f: F) -> R where F: for<'scope> FnOnce(&'scope Scope<'env>) -> R {
+    let scope = Scope {
+        threads: RefCell::new(Vec::new()),
+        env: PhantomData,
+    };
+
+    let _join = JoinOnDrop { scope: &scope };
+
+    f(&scope)
+}
+
+/// Where the output of a spawned future is put for its handle
+struct Slot<O> {
+    output: Option<Result<O, Box<dyn Any + Send>>>,
+    waker: Option<Waker>,
+}
+
+impl<O> Slot<O> {
+    fn complete(&mut self, output: Result<O, Box<dyn Any + Send>>) {
+        self.output = Some(output);
+
+        if let Some(waker) = self.waker.take() {
+            waker.wake()
+        }
+    }
+}
+
+/// A future for the output of a future spawned within a scope
+///
+/// A `ScopedHandle` can't outlive its scope.
+pub struct ScopedHandle<'scope,O> {
+    slot: Arc<Mutex<Slot<O>>>,
+    scope: PhantomData<&'scope ()>,
+}
+
+impl<'scope,O> Future for ScopedHandle<'scope,O> {
+    type Output = O;
+
+    fn poll(self: Pin<&mut Self>, lw: &LocalWaker) -> Poll<O> {
+        let mut slot = self.slot.lock().unwrap();
+
+        match slot.output.take() {
+            Some(Ok(val)) => Poll::Ready(val),
+            Some(Err(payload)) => panic::resume_unwind(payload),
+            None => {
+                slot.waker = Some(lw.as_waker().clone());
+
+                Poll::Pending
+            },
+        }
+    }
+}
+
+/// A future that borrows from outside of a scope
+///
+/// This is created by [`Scope::later`](./struct.Scope.html#method.later), a `Scoped` can't outlive
+/// its scope.
+pub struct Scoped<'scope,T> {
+    future: T,
+    scope: PhantomData<&'scope ()>,
+}
+
+impl<'scope,T> Future for Scoped<'scope,T> where T: Future {
+    type Output = T::Output;
+
+    fn poll(self: Pin<&mut Self>, lw: &LocalWaker) -> Poll<T::Output> {
+        let this = unsafe { Pin::get_mut_unchecked(self) };
+
+        unsafe { Pin::new_unchecked(&mut this.future) }.poll(lw)
+    }
+}
@@ -0,0 +1,257 @@
+//! Waiting on whichever of many things becomes ready first
+//!
+//! [`select`](./fn.select.html) blocks the current thread until one of a list of
+//! [`Waitable`](./trait.Waitable.html)s is ready. The waitables can be of different kinds, such as
+//! `Later`s, `LaterStream`s, channels (through [`Received`](../struct.Received.html)) and the
+//! timers of the `time` module, and they all share a single waker so that every wait is a single
+//! park of the thread no matter how many waitables there are.
+//!
+//! [`select_take`](./fn.select_take.html) is the same for a slice of `Later`s of the same type,
+//! but it also moves the output out of the `Later` that completed first.
+//! [`wait_any`](./fn.wait_any.html) does the same for a tuple of `Later`s whose outputs are of
+//! different types, returning the output tagged with the position of its `Later`.
+
+use std::future::Future;
+use std::task::{Poll, LocalWaker};
+use std::time::Instant;
+
+use super::{earliest, wait_blocking_until, Later, MainWaker};
+
+/// Something that can be waited on by `select`
+///
+/// Once a waitable is ready, getting its value won't block. For a `Later` this means its output is
+/// available, and for a `LaterStream` that its next item (or its end) is.
+pub trait Waitable {
+
+    /// Make progress without blocking and return `Poll::Ready` if the waitable is ready
+    ///
+    /// If this returns `Poll::Pending`, `lw` is woken when the waitable may have become ready.
+    fn poll_ready(&mut self, lw: &LocalWaker) -> Poll<()>;
+
+    /// The time at which the waitable becomes ready without waking `lw`, if there is one
+    ///
+    /// For a `Later` this is the deadline given to `Later::with_deadline`, after which the `Later`
+    /// is ready with the `AccessError::Elapsed` error.
+    fn deadline(&self) -> Option<Instant> {
+        None
+    }
+}
+
+impl<T,O> Waitable for Later<T> where T: Future<Output=O> {
+    fn poll_ready(&mut self, lw: &LocalWaker) -> Poll<()> {
+        Later::poll_ready(self, lw)
+    }
+
+    fn deadline(&self) -> Option<Instant> {
+        Later::deadline(self)
+    }
+}
+
+impl<'a, W> Waitable for &'a mut W where W: Waitable + ?Sized {
+    fn poll_ready(&mut self, lw: &LocalWaker) -> Poll<()> {
+        (**self).poll_ready(lw)
+    }
+
+    fn deadline(&self) -> Option<Instant> {
+        (**self).deadline()
+    }
+}
+
+/// Block until one of the waitables is ready and return its index
+///
+/// When multiple waitables are ready at the same time the one with the lowest index is returned.
+/// The waitables that aren't ready are left as they were, so `select` can be called again with the
+/// same waitables. A `Later` that is cancelled or whose deadline passes is ready, and its fallible
+/// accessors return the error.
+///
+/// # Example
+/// ```rust
+/// # #![feature(futures_api)]
+/// # #![feature(async_await)]
+/// # #[macro_use] extern crate alligator;
+/// # async fn fetch_primary() -> u32 { 1 }
+/// # async fn fetch_backup() -> u32 { 2 }
+/// # fn main() {
+/// let mut primary = l!{ fetch_primary() };
+/// let mut backup = l!{ fetch_backup() };
+///
+/// let ready = alligator::select(&mut [&mut primary, &mut backup]);
+///
+/// let value = match ready {
+///     0 => *primary,
+///     _ => *backup,
+/// };
+/// # assert_eq!(value, 1);
+/// # }
+/// ```
+///
+/// # Panic
+/// This panics if `waitables` is empty
+pub fn select(waitables: &mut [&mut dyn Waitable]) -> usize {
+    assert!(!waitables.is_empty(), "select needs at least one waitable");
+
+    let waker = MainWaker::new();
+
+    let lw = waker.local();
+
+    loop {
+        for (index, waitable) in waitables.iter_mut().enumerate() {
+            if waitable.poll_ready(&lw).is_ready() {
+                return index;
+            }
+        }
+
+        let deadline = waitables.iter().fold(None, |deadline, waitable| earliest(deadline, waitable.deadline()));
+
+        wait_blocking_until(&waker, deadline);
+    }
+}
+
+/// Block until one of the `Later`s has completed and move its output out
+///
+/// The index of the `Later` is returned along with its output, and that `Later` is left empty (as
+/// after [`take_value`](../struct.Later.html#method.take_value)). `Later`s that are already empty
+/// are skipped, so calling `select_take` again with the same slice returns the output of the next
+/// `Later` to complete.
+///
+/// # Example
+/// ```rust
+/// # #![feature(futures_api)]
+/// # #![feature(async_await)]
+/// # #[macro_use] extern crate alligator;
+/// # async fn query(replica: u32) -> u32 { replica }
+/// # fn main() {
+/// let mut replicas = vec![l!{ query(0) }, l!{ query(1) }, l!{ query(2) }];
+///
+/// let (replica, answer) = alligator::select_take(&mut replicas);
+/// # assert_eq!((replica, answer), (0, 0));
+/// # }
+/// ```
+///
+/// # Panic
+/// This panics if every `Later` is empty, or if the first `Later` to be ready was cancelled or its
+/// deadline passed
+pub fn select_take<T,O>(laters: &mut [Later<T>]) -> (usize, O) where T: Future<Output=O> {
+    assert!(laters.iter().any(|later| !later.is_taken()), "select_take needs at least one Later that isn't empty");
+
+    let waker = MainWaker::new();
+
+    let lw = waker.local();
+
+    loop {
+        for (index, later) in laters.iter_mut().enumerate() {
+            if !later.is_taken() && later.poll_ready(&lw).is_ready() {
+                return (index, later.take_value().expect("Report a bug if you get this panic"));
+            }
+        }
+
+        let deadline = laters.iter().fold(None, |deadline, later| earliest(deadline, later.deadline()));
+
+        wait_blocking_until(&waker, deadline);
+    }
+}
+
+/// The output of the first of two `Later`s to complete, tagged with the position of the `Later`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Any2<A,B> {
+    First(A),
+    Second(B),
+}
+
+/// The output of the first of three `Later`s to complete, tagged with the position of the `Later`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Any3<A,B,C> {
+    First(A),
+    Second(B),
+    Third(C),
+}
+
+/// The output of the first of four `Later`s to complete, tagged with the position of the `Later`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Any4<A,B,C,D> {
+    First(A),
+    Second(B),
+    Third(C),
+    Fourth(D),
+}
+
+/// A tuple of mutable references to `Later`s that can be waited on by `wait_any`
+///
+/// This is implemented for tuples of two to four `Later`s.
+pub trait WaitAny {
+    /// The output of the first `Later` to complete, tagged with its position
+    type Output;
+
+    /// Block until one of the `Later`s has completed and move its output out
+    fn wait_any(self) -> Self::Output;
+}
+
+macro_rules! wait_any_tuple {
+    ( $any:ident { $( $later:ident : $T:ident, $O:ident => $variant:ident ),+ } ) => {
+        impl<'a, $($T, $O),+> WaitAny for ( $(&'a mut Later<$T>,)+ ) where $($T: Future<Output=$O>),+ {
+            type Output = $any<$($O),+>;
+
+            fn wait_any(self) -> Self::Output {
+                let ( $($later,)+ ) = self;
+
+                assert!(false $(|| !$later.is_taken())+, "wait_any needs at least one Later that isn't empty");
+
+                let waker = MainWaker::new();
+
+                let lw = waker.local();
+
+                loop {
+                    $(
+                        if !$later.is_taken() && $later.poll_ready(&lw).is_ready() {
+                            return $any::$variant($later.take_value().expect("Report a bug if you get this panic"));
+                        }
+                    )+
+
+                    let mut deadline = None;
+
+                    $( deadline = $crate::earliest(deadline, $later.deadline()); )+
+
+                    $crate::wait_blocking_until(&waker, deadline);
+                }
+            }
+        }
+    };
+}
+
+wait_any_tuple!(Any2 { a: T1, O1 => First, b: T2, O2 => Second });
+wait_any_tuple!(Any3 { a: T1, O1 => First, b: T2, O2 => Second, c: T3, O3 => Third });
+wait_any_tuple!(Any4 { a: T1, O1 => First, b: T2, O2 => Second, c: T3, O3 => Third, d: T4, O4 => Fourth });
+
+/// Block until one of a tuple of `Later`s has completed and move its output out
+///
+/// The `Later`s can have different output types, the output is returned in an `Any2`, `Any3`, or
+/// `Any4` whose variant is the position of the `Later` that completed. That `Later` is left empty
+/// (as after [`take_value`](../struct.Later.html#method.take_value)), and `Later`s that are
+/// already empty are skipped. When multiple `Later`s are ready at the same time the first of them
+/// is returned. The [`wait_any!`](../macro.wait_any.html) macro is a shortcut for this.
+///
+/// # Example
+/// ```rust
+/// # #![feature(futures_api)]
+/// # #![feature(async_await)]
+/// # #[macro_use] extern crate alligator;
+/// # use alligator::Any2;
+/// # async fn fetch_config() -> &'static str { "config" }
+/// # async fn fetch_version() -> u32 { 3 }
+/// # fn main() {
+/// let mut config = l!{ fetch_config() };
+/// let mut version = l!{ fetch_version() };
+///
+/// match alligator::wait_any((&mut config, &mut version)) {
+///     Any2::First(config) => println!("config first: {}", config),
+///     Any2::Second(version) => println!("version first: {}", version),
+/// }
+/// # }
+/// ```
+///
+/// # Panic
+/// This panics if every `Later` is empty, or if the first `Later` to be ready was cancelled or its
+/// deadline passed
+pub fn wait_any<L>(laters: L) -> L::Output where L: WaitAny {
+    laters.wait_any()
+}
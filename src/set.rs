@@ -0,0 +1,381 @@
+//! Collections of `Later`s whose outputs are retrieved as their futures complete
+
+use std::borrow::Borrow;
+use std::collections::HashMap;
+use std::future::Future;
+use std::hash::Hash;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::Poll;
+
+use super::{earliest, wait_blocking, wait_blocking_until, Later, MainWaker};
+use super::join::{Driver, Order, Policy};
+
+/// A set of futures whose outputs are retrieved in the order the futures complete
+///
+/// `LaterSet` is an iterator where every call to `next` blocks until the next future of the set
+/// completes. The futures are polled concurrently, so a slow future never holds up the outputs of
+/// the futures that have already completed. Use [`order`](#method.order) to instead retrieve the
+/// outputs in the order the futures were given. Iterator adaptors that consume the outputs, like
+/// `fold`, work over the outputs as they complete without collecting them first.
+///
+/// # Example
+/// ```rust
+/// # #![feature(futures_api)]
+/// # #![feature(async_await)]
+/// # extern crate alligator;
+/// # use alligator::LaterSet;
+/// # async fn shard(n: u64) -> u64 { n * 10 }
+/// # fn main() {
+/// let shards = LaterSet::new( (0..4).map(shard) );
+///
+/// // The sum of all shard results
+/// let sum = shards.fold(0, |acc, result| acc + result);
+/// # assert_eq!(sum, 60);
+/// # }
+/// ```
+pub struct LaterSet<T,O> where T: Future<Output=O> {
+    driver: Driver<T,O>,
+    waker: Arc<MainWaker>,
+    order: Order,
+    held: Vec<Option<O>>,
+    next_index: usize,
+}
+
+impl<T,O> LaterSet<T,O> where T: Future<Output=O> {
+
+    /// Create a new `LaterSet` that polls woken futures in round robin order
+    pub fn new<I>( futures: I ) -> Self where I: IntoIterator<Item=T> {
+        Self::with_policy(futures, Policy::default())
+    }
+
+    /// Create a new `LaterSet` that polls woken futures in the order given by `policy`
+    pub fn with_policy<I>( futures: I, policy: Policy ) -> Self where I: IntoIterator<Item=T> {
+        LaterSet {
+            driver: Driver::new(futures, policy),
+            waker: MainWaker::new(),
+            order: Order::Completion,
+            held: Vec::new(),
+            next_index: 0,
+        }
+    }
+
+    /// Set the order the outputs are retrieved in
+    ///
+    /// With `Order::Input` the outputs of futures that complete early are held by the set until
+    /// the futures given ahead of them complete.
+    pub fn order(mut self, order: Order) -> Self {
+        self.order = order;
+        self.held = match order {
+            Order::Input => (0..self.driver.len()).map(|_| None).collect(),
+            Order::Completion => Vec::new(),
+        };
+
+        self
+    }
+
+    /// Block until the next future to complete completes
+    fn next_completed(&mut self) -> Option<(usize, O)> {
+        loop {
+            match self.driver.poll_next(&self.waker.local()) {
+                Poll::Ready(next) => break next,
+                Poll::Pending => wait_blocking(&self.waker),
+            }
+        }
+    }
+
+    /// Block until the output of the future at `next_index` is available
+    fn next_in_order(&mut self) -> Option<O> {
+        let index = self.next_index;
+
+        if index >= self.held.len() {
+            return None;
+        }
+
+        while self.held[index].is_none() {
+            match self.next_completed() {
+                Some((completed, val)) => self.held[completed] = Some(val),
+                None => panic!("Report a bug if you get this panic"),
+            }
+        }
+
+        self.next_index += 1;
+
+        self.held[index].take()
+    }
+
+    /// The number of outputs that have not been retrieved from the set
+    pub fn len(&self) -> usize {
+        match self.order {
+            Order::Input => self.held.len() - self.next_index,
+            Order::Completion => self.driver.remaining(),
+        }
+    }
+
+    /// Check if every output has been retrieved from the set
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<T,O> Iterator for LaterSet<T,O> where T: Future<Output=O> {
+    type Item = O;
+
+    /// Block until the next output of the set is available and return it
+    fn next(&mut self) -> Option<O> {
+        match self.order {
+            Order::Input => self.next_in_order(),
+            Order::Completion => self.next_completed().map(|(_, val)| val),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len(), Some(self.len()))
+    }
+}
+
+impl<T,O> ExactSizeIterator for LaterSet<T,O> where T: Future<Output=O> {}
+
+/// A growable group of futures that are polled concurrently
+///
+/// Unlike [`LaterSet`](./struct.LaterSet.html), futures can be pushed into a `LaterGroup` at any
+/// time, including while the outputs of earlier futures are being retrieved. Iterating over a
+/// group blocks for the outputs in the order the futures complete, and
+/// [`resolve_all`](#method.resolve_all) waits for all the remaining outputs at once. Every future
+/// pushed is boxed so that the futures already in the group don't move. With the `pool` feature
+/// the futures of a group created with `LaterGroup::spawned` are polled by a pool instead of by
+/// the thread waiting on the group.
+///
+/// # Example
+/// ```rust
+/// # #![feature(futures_api)]
+/// # #![feature(async_await)]
+/// # extern crate alligator;
+/// # use alligator::LaterGroup;
+/// # async fn upload(chunk: u32) -> u32 { chunk }
+/// # fn main() {
+/// let mut uploads = LaterGroup::new();
+///
+/// for chunk in 0..3 {
+///     uploads.push(upload(chunk));
+/// }
+///
+/// assert_eq!(uploads.pending(), 3);
+///
+/// let done = uploads.resolve_all();
+/// # assert_eq!(done, vec![0, 1, 2]);
+/// # }
+/// ```
+pub struct LaterGroup<T,O> where T: Future<Output=O> {
+    driver: Driver<Pin<Box<T>>,O>,
+    waker: Arc<MainWaker>,
+}
+
+impl<T,O> LaterGroup<T,O> where T: Future<Output=O> {
+
+    /// Create an empty `LaterGroup` that polls woken futures in round robin order
+    pub fn new() -> Self {
+        Self::with_policy(Policy::default())
+    }
+
+    /// Create an empty `LaterGroup` that polls woken futures in the order given by `policy`
+    pub fn with_policy( policy: Policy ) -> Self {
+        LaterGroup {
+            driver: Driver::new(Vec::new(), policy),
+            waker: MainWaker::new(),
+        }
+    }
+
+    /// Add a future to the group
+    ///
+    /// The future isn't polled until an output of the group is waited for.
+    pub fn push(&mut self, future: T) {
+        self.driver.push(Box::pinned(future))
+    }
+
+    /// The number of futures whose output has not been retrieved from the group
+    pub fn pending(&self) -> usize {
+        self.driver.remaining()
+    }
+
+    /// Check if there are no futures whose output has not been retrieved from the group
+    pub fn is_empty(&self) -> bool {
+        self.pending() == 0
+    }
+
+    /// Block until every future of the group has completed and return their outputs
+    ///
+    /// The outputs are in the order their futures were pushed. Outputs that were already retrieved
+    /// by iterating over the group are not included.
+    pub fn resolve_all(&mut self) -> Vec<O> {
+        let mut outputs: Vec<(usize, O)> = Vec::with_capacity(self.pending());
+
+        while let Some(output) = self.next_completed() {
+            outputs.push(output);
+        }
+
+        outputs.sort_by_key(|&(index, _)| index);
+
+        outputs.into_iter().map(|(_, val)| val).collect()
+    }
+
+    /// Block until the next future to complete completes
+    fn next_completed(&mut self) -> Option<(usize, O)> {
+        loop {
+            match self.driver.poll_next(&self.waker.local()) {
+                Poll::Ready(next) => break next,
+                Poll::Pending => wait_blocking(&self.waker),
+            }
+        }
+    }
+}
+
+impl<T,O> Default for LaterGroup<T,O> where T: Future<Output=O> {
+    fn default() -> Self {
+        LaterGroup::new()
+    }
+}
+
+impl<T,O> Extend<T> for LaterGroup<T,O> where T: Future<Output=O> {
+    fn extend<I>(&mut self, futures: I) where I: IntoIterator<Item=T> {
+        for future in futures {
+            self.push(future)
+        }
+    }
+}
+
+impl<T,O> Iterator for LaterGroup<T,O> where T: Future<Output=O> {
+    type Item = O;
+
+    /// Block until the next future of the group to complete completes and return its output
+    fn next(&mut self) -> Option<O> {
+        self.next_completed().map(|(_, val)| val)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.pending(), Some(self.pending()))
+    }
+}
+
+/// A map of `Later`s addressed by key
+///
+/// Getting the output of an entry only waits for the future of that entry, and removing an entry
+/// drops its future, cancelling whatever work it had left. [`resolve_all`](#method.resolve_all)
+/// waits for the futures of every entry concurrently.
+///
+/// # Example
+/// ```rust
+/// # #![feature(futures_api)]
+/// # #![feature(async_await)]
+/// # extern crate alligator;
+/// # use alligator::LaterMap;
+/// # async fn lookup_quota(tenant: &'static str) -> u32 { tenant.len() as u32 }
+/// # fn main() {
+/// let mut quotas = LaterMap::new();
+///
+/// for tenant in &["acme", "initech", "hooli"] {
+///     quotas.insert(*tenant, lookup_quota(tenant));
+/// }
+///
+/// // Only waits for the lookup of "acme"
+/// assert_eq!(quotas.get("acme"), Some(&4));
+///
+/// // The lookup of "hooli" is no longer needed
+/// quotas.remove("hooli");
+/// # assert_eq!(quotas.len(), 2);
+/// # }
+/// ```
+pub struct LaterMap<K,T> where K: Eq + Hash, T: Future {
+    entries: HashMap<K, Later<T>>,
+}
+
+impl<K,T,O> LaterMap<K,T> where K: Eq + Hash, T: Future<Output=O> {
+
+    /// Create an empty `LaterMap`
+    pub fn new() -> Self {
+        LaterMap {
+            entries: HashMap::new(),
+        }
+    }
+
+    /// Insert a future for `key`
+    ///
+    /// The future is wrapped in a `Later` the same way as with `Later::new`. If there already was
+    /// an entry for `key` then its `Later` is returned.
+    pub fn insert(&mut self, key: K, future: T) -> Option<Later<T>> {
+        self.entries.insert(key, Later::new(future))
+    }
+
+    /// Get the output for `key`, waiting for the future of that entry only
+    ///
+    /// # Panic
+    /// This panics if the output of the entry was taken with `get_mut` and `take_value`
+    pub fn get<Q>(&self, key: &Q) -> Option<&O> where K: Borrow<Q>, Q: Eq + Hash + ?Sized {
+        self.entries.get(key).map(Later::force)
+    }
+
+    /// Get the `Later` of the entry for `key` without waiting for it
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut Later<T>> where K: Borrow<Q>, Q: Eq + Hash + ?Sized {
+        self.entries.get_mut(key)
+    }
+
+    /// Remove the entry for `key`
+    ///
+    /// Dropping the returned `Later` cancels the future of the entry if it hasn't completed.
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<Later<T>> where K: Borrow<Q>, Q: Eq + Hash + ?Sized {
+        self.entries.remove(key)
+    }
+
+    /// Check if there is an entry for `key`
+    pub fn contains_key<Q>(&self, key: &Q) -> bool where K: Borrow<Q>, Q: Eq + Hash + ?Sized {
+        self.entries.contains_key(key)
+    }
+
+    /// The number of entries
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Check if there are no entries
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// The number of entries whose future hasn't completed
+    pub fn pending(&self) -> usize {
+        self.entries.values().filter(|later| !later.is_terminated()).count()
+    }
+
+    /// Block until the future of every entry has completed
+    ///
+    /// The futures are all polled from the one wait, so this takes as long as the slowest future
+    /// instead of the sum of them all. Afterwards `get` doesn't wait for any entry. An entry whose
+    /// future is cancelled or whose deadline passes is done waiting as well.
+    pub fn resolve_all(&mut self) {
+        let waker = MainWaker::new();
+
+        let lw = waker.local();
+
+        loop {
+            let mut pending = false;
+
+            for later in self.entries.values_mut() {
+                pending |= later.poll_ready(&lw).is_pending();
+            }
+
+            if !pending {
+                break;
+            }
+
+            let deadline = self.entries.values().fold(None, |deadline, later| earliest(deadline, later.deadline()));
+
+            wait_blocking_until(&waker, deadline);
+        }
+    }
+}
+
+impl<K,T> Default for LaterMap<K,T> where K: Eq + Hash, T: Future {
+    fn default() -> Self {
+        LaterMap::new()
+    }
+}
@@ -0,0 +1,256 @@
+use std::cell::UnsafeCell;
+use std::future::Future;
+use std::pin::Pin;
+use std::ptr::NonNull;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::task::{LocalWaker, Poll, UnsafeWake, Wake, Waker};
+use std::thread::{self, Thread};
+
+use super::{block_until_woken, MainWaker};
+
+const INCOMPLETE: usize = 0;
+const RUNNING: usize = 1;
+const COMPLETE: usize = 2;
+const POISONED: usize = 3;
+
+/// The bits of the state that hold one of the states above, while the state is `RUNNING` the
+/// other bits are the address of the first `Waiter`
+const STATE_MASK: usize = 3;
+
+/// A thread waiting for the thread that polls the future of a `StaticLater`
+///
+/// Every waiter is on the stack of its thread, and the waiters form a list that is only walked
+/// once, by `finish`.
+struct Waiter {
+    waker: Arc<MainWaker>,
+    signaled: AtomicBool,
+    next: usize,
+}
+
+/// Set the final state of a `StaticLater` and wake every waiter
+fn finish(state: &AtomicUsize, done: usize) {
+    let mut next = state.swap(done, Ordering::AcqRel) & !STATE_MASK;
+
+    while next != 0 {
+        let waiter = unsafe { &*(next as *const Waiter) };
+
+        // The waiter can return as soon as it's signaled, so nothing of it is read after that
+        let waker = waiter.waker.clone();
+
+        next = waiter.next;
+
+        waiter.signaled.store(true, Ordering::Release);
+
+        Wake::wake(&waker);
+    }
+}
+
+/// The waker of a `StaticLater`
+///
+/// The waker lives inside of the `StaticLater`, so the `Waker`s created from it are just pointers
+/// to it and cloning or dropping them does nothing.
+struct StaticWaker {
+    woken: AtomicBool,
+    thread: UnsafeCell<Option<Thread>>,
+}
+
+unsafe impl Send for StaticWaker {}
+unsafe impl Sync for StaticWaker {}
+
+unsafe impl UnsafeWake for StaticWaker {
+    unsafe fn clone_raw(&self) -> Waker {
+        Waker::new(NonNull::from(self as &dyn UnsafeWake))
+    }
+
+    unsafe fn drop_raw(&self) {}
+
+    unsafe fn wake(&self) {
+        self.woken.store(true, Ordering::Release);
+
+        if let Some(ref thread) = *self.thread.get() {
+            thread.unpark()
+        }
+    }
+}
+
+impl StaticWaker {
+
+    /// Must be called by the thread polling the future before the future is polled
+    unsafe fn local(&'static self) -> LocalWaker {
+        *self.thread.get() = Some(thread::current());
+
+        LocalWaker::new(NonNull::from(self as &dyn UnsafeWake))
+    }
+
+    /// Park until woken
+    ///
+    /// This is prone to spurious wake ups like `MainWaker::wait`.
+    fn wait(&self) {
+        while !self.woken.swap(false, Ordering::AcqRel) {
+            thread::park();
+        }
+    }
+}
+
+/// Sets the state of a `StaticLater` to poisoned if the future panics
+struct PoisonOnPanic<'a> {
+    state: &'a AtomicUsize,
+}
+
+impl<'a> Drop for PoisonOnPanic<'a> {
+    fn drop(&mut self) {
+        if thread::panicking() {
+            finish(self.state, POISONED);
+        }
+    }
+}
+
+/// A `Later` for a `static` that doesn't allocate
+///
+/// `StaticLater` is created with a function that returns the future, and it can be created in a
+/// constant context so that it can be the value of a `static`. The first thread to access the
+/// output calls the function and polls the returned future to completion, any other thread that
+/// accesses the output at the same time is parked until it completes. The waker given to the
+/// future is within the `StaticLater` itself, so it doesn't allocate. Both the thread polling the
+/// future and the threads waiting for it count as blocked threads towards the
+/// [blocked limit](./fn.set_blocked_limit.html).
+///
+/// If the future panics then every access to the output will panic.
+///
+/// The [`later_static!`](./macro.later_static.html) macro declares a `static` backed by a
+/// `StaticLater` for futures whose type can't be named, such as those of `async fn`s.
+///
+/// # Example
+/// ```rust
+/// # #![feature(futures_api)]
+/// # #![feature(pin)]
+/// # #![feature(arbitrary_self_types)]
+/// # extern crate alligator;
+/// # use alligator::StaticLater;
+/// # use std::future::Future;
+/// # use std::pin::Pin;
+/// # use std::task::{LocalWaker, Poll};
+/// # struct ReadCalibration;
+/// # impl Future for ReadCalibration {
+/// #     type Output = f64;
+/// #     fn poll(self: Pin<&mut Self>, _: &LocalWaker) -> Poll<f64> { Poll::Ready(1.5) }
+/// # }
+/// fn read_calibration() -> ReadCalibration {
+///     // ...
+/// #   ReadCalibration
+/// }
+///
+/// static CALIBRATION: StaticLater<ReadCalibration, f64> = StaticLater::new(read_calibration);
+///
+/// # fn main() {
+/// println!("calibration: {}", CALIBRATION.get());
+/// # }
+/// ```
+pub struct StaticLater<T,O> {
+    state: AtomicUsize,
+    make_future: fn() -> T,
+    output: UnsafeCell<Option<O>>,
+    waker: StaticWaker,
+}
+
+unsafe impl<T,O> Sync for StaticLater<T,O> where O: Send + Sync {}
+
+impl<T,O> StaticLater<T,O> {
+
+    /// Create a new `StaticLater` with the function for creating the future
+    pub const fn new( make_future: fn() -> T ) -> Self {
+        StaticLater {
+            state: AtomicUsize::new(INCOMPLETE),
+            make_future: make_future,
+            output: UnsafeCell::new(None),
+            waker: StaticWaker {
+                woken: AtomicBool::new(false),
+                thread: UnsafeCell::new(None),
+            },
+        }
+    }
+}
+
+impl<T,O> StaticLater<T,O> where T: Future<Output=O> {
+
+    /// Check if the output is available
+    pub fn is_ready(&self) -> bool {
+        self.state.load(Ordering::Acquire) == COMPLETE
+    }
+
+    /// Get a reference to the output value of the future
+    ///
+    /// The first call will create and poll the future to completion.
+    ///
+    /// # Panic
+    /// This panics if the future panicked, or if the output isn't available and the current thread
+    /// can't block
+    pub fn get(&'static self) -> &'static O {
+        let mut state = self.state.load(Ordering::Acquire);
+
+        loop {
+            match state & STATE_MASK {
+                INCOMPLETE => match self.state.compare_exchange(state, RUNNING, Ordering::AcqRel, Ordering::Acquire) {
+                    Ok(_) => break self.run(),
+                    Err(actual) => state = actual,
+                },
+                RUNNING => state = self.wait(state),
+                COMPLETE => break self.output(),
+                _ => panic!("the future of a StaticLater panicked"),
+            }
+        }
+    }
+
+    /// Park until the thread polling the future is done, `state` is the last state that was read
+    ///
+    /// Returns the state once it's changed, without waiting if it changed before this thread was
+    /// added to the waiters.
+    fn wait(&self, state: usize) -> usize {
+        let waiter = Waiter {
+            waker: MainWaker::new(),
+            signaled: AtomicBool::new(false),
+            next: state & !STATE_MASK,
+        };
+
+        let head = &waiter as *const Waiter as usize | RUNNING;
+
+        if let Err(actual) = self.state.compare_exchange(state, head, Ordering::AcqRel, Ordering::Acquire) {
+            return actual;
+        }
+
+        let lw = waiter.waker.local();
+
+        while !waiter.signaled.load(Ordering::Acquire) {
+            block_until_woken(&lw, || waiter.waker.wait());
+        }
+
+        self.state.load(Ordering::Acquire)
+    }
+
+    fn output(&'static self) -> &'static O {
+        unsafe { &*self.output.get() }.as_ref().expect("Report a bug if you get this panic")
+    }
+
+    /// Poll the future to completion, must only be called by the thread that set `RUNNING`
+    fn run(&'static self) -> &'static O {
+        let _poison = PoisonOnPanic { state: &self.state };
+
+        let mut future = (self.make_future)();
+
+        let lw = unsafe { self.waker.local() };
+
+        let val = loop {
+            match unsafe { Pin::new_unchecked(&mut future) }.poll(&lw) {
+                Poll::Ready(val) => break val,
+                Poll::Pending    => block_until_woken(&lw, || self.waker.wait()),
+            }
+        };
+
+        unsafe { *self.output.get() = Some(val) };
+
+        finish(&self.state, COMPLETE);
+
+        self.output()
+    }
+}
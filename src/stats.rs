@@ -0,0 +1,174 @@
+//! Process wide latency statistics of `Later`s
+//!
+//! Every `Later` whose future completes adds two samples to the statistics of the process:
+//!
+//! - the *ready latency*, the time from the first poll of the future until it was ready
+//! - the *blocked time*, the total time threads spent waiting for the future within accessors of
+//!   the `Later`
+//!
+//! A `Later` that is forced after its future has already completed (for instance one whose future
+//! was spawned onto a pool) has a blocked time of zero. Comparing the two distributions shows how
+//! much of the work of the futures was hidden by laziness, a blocked time close to the ready
+//! latency means the output was needed almost as soon as the `Later` was created.
+//!
+//! The samples are kept in histograms with buckets that double in size, so percentiles are
+//! accurate to within a factor of two.
+//!
+//! # Example
+//! ```rust
+//! # #![feature(futures_api)]
+//! # #![feature(async_await)]
+//! # #[macro_use] extern crate alligator;
+//! # async fn get_fut() -> &'static str { "Hello World" }
+//! # fn main() {
+//! println!("{}", l!{ get_fut() });
+//!
+//! let stats = alligator::stats::snapshot();
+//!
+//! if let Some(p99) = stats.blocked.percentile(99.0) {
+//!     println!("99% of Laters blocked for less than {:?}", p99);
+//! }
+//! # }
+//! ```
+
+use std::sync::{Mutex, Once, ONCE_INIT};
+use std::time::Duration;
+
+/// The number of buckets of a histogram, the last bucket holds every sample of 2^62µs or more
+const BUCKETS: usize = 64;
+
+/// A histogram of durations
+///
+/// Bucket `0` holds samples under a microsecond and bucket `i` holds samples from `2^(i-1)` up to
+/// `2^i` microseconds.
+#[derive(Clone, Debug)]
+pub struct Distribution {
+    buckets: Vec<u64>,
+    count: u64,
+    sum: Duration,
+    max: Duration,
+}
+
+impl Distribution {
+
+    fn new() -> Self {
+        Distribution {
+            buckets: vec![0; BUCKETS],
+            count: 0,
+            sum: Duration::from_secs(0),
+            max: Duration::from_secs(0),
+        }
+    }
+
+    fn add(&mut self, sample: Duration) {
+        let micros = sample.as_secs().saturating_mul(1_000_000) + u64::from(sample.subsec_micros());
+
+        let bucket = (64 - micros.leading_zeros() as usize).min(BUCKETS - 1);
+
+        self.buckets[bucket] += 1;
+        self.count += 1;
+        self.sum += sample;
+
+        if sample > self.max {
+            self.max = sample;
+        }
+    }
+
+    /// The number of samples
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// The mean of the samples, `None` if there are no samples
+    pub fn mean(&self) -> Option<Duration> {
+        if self.count == 0 {
+            return None;
+        }
+
+        let nanos = self.sum.as_secs() as u128 * 1_000_000_000 + u128::from(self.sum.subsec_nanos());
+
+        Some(Duration::from_nanos((nanos / u128::from(self.count)) as u64))
+    }
+
+    /// The largest sample, `None` if there are no samples
+    pub fn max(&self) -> Option<Duration> {
+        if self.count == 0 { None } else { Some(self.max) }
+    }
+
+    /// The duration that `percentile` percent of the samples are under
+    ///
+    /// The return is the upper bound of the bucket containing the percentile (but never more than
+    /// the largest sample), or `None` if there are no samples.
+    ///
+    /// # Panic
+    /// This panics if `percentile` is not within `0.0..=100.0`
+    pub fn percentile(&self, percentile: f64) -> Option<Duration> {
+        assert!(percentile >= 0.0 && percentile <= 100.0, "a percentile must be within 0 to 100");
+
+        if self.count == 0 {
+            return None;
+        }
+
+        let rank = ((percentile / 100.0) * self.count as f64).ceil().max(1.0) as u64;
+
+        let mut seen = 0;
+
+        for (bucket, count) in self.buckets.iter().enumerate() {
+            seen += count;
+
+            if seen >= rank {
+                let upper = Duration::from_micros(1u64 << bucket.min(62));
+
+                return Some(upper.min(self.max));
+            }
+        }
+
+        Some(self.max)
+    }
+}
+
+/// A snapshot of the latency statistics of the process
+#[derive(Clone, Debug)]
+pub struct Stats {
+    /// The time from the first poll of a future until it was ready
+    pub ready_latency: Distribution,
+    /// The total time threads waited for a future within the accessors of its `Later`
+    pub blocked: Distribution,
+}
+
+fn stats() -> &'static Mutex<Stats> {
+    static INIT: Once = ONCE_INIT;
+    static mut STATS: *const Mutex<Stats> = 0 as *const _;
+
+    unsafe {
+        INIT.call_once(|| {
+            STATS = Box::into_raw( Box::new( Mutex::new( Stats {
+                ready_latency: Distribution::new(),
+                blocked: Distribution::new(),
+            })));
+        });
+
+        &*STATS
+    }
+}
+
+/// Record the samples of a completed future
+pub(crate) fn record(ready_latency: Duration, blocked: Duration) {
+    let mut stats = stats().lock().unwrap();
+
+    stats.ready_latency.add(ready_latency);
+    stats.blocked.add(blocked);
+}
+
+/// Get a snapshot of the statistics recorded so far
+pub fn snapshot() -> Stats {
+    stats().lock().unwrap().clone()
+}
+
+/// Discard all recorded statistics
+pub fn reset() {
+    let mut stats = stats().lock().unwrap();
+
+    stats.ready_latency = Distribution::new();
+    stats.blocked = Distribution::new();
+}
@@ -0,0 +1,597 @@
+//! Blocking iteration over a `Stream`
+//!
+//! [`LaterStream`](./struct.LaterStream.html) is the stream version of `Later`, it is an iterator
+//! where every call to `next` polls the wrapped stream until its next item is available. When only
+//! the complete set of items matters, [`collect_later`](./fn.collect_later.html) creates a `Later`
+//! for all of the items instead.
+
+use std::collections::VecDeque;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Poll, LocalWaker};
+use std::time::Duration;
+
+use futures::stream::Stream;
+
+use super::{clock, wait_blocking, Later, MainWaker};
+use super::error::TimeoutError;
+#[cfg(feature = "combinators")]
+use super::select::Waitable;
+
+/// An iterator over the items of a stream
+///
+/// Every call to `next` blocks until the stream produces its next item or ends. Once the stream
+/// has ended it is not polled again.
+///
+/// ```rust
+/// # #![feature(futures_api)]
+/// # extern crate alligator;
+/// # extern crate futures;
+/// # use alligator::LaterStream;
+/// # use futures::channel::mpsc;
+/// # fn main() {
+/// let (sender, receiver) = mpsc::unbounded();
+///
+/// ::std::thread::spawn(move || {
+///     for event in 0..3 {
+///         sender.unbounded_send(event).unwrap();
+///     }
+/// });
+///
+/// // The items come in the order they were sent, and iteration ends once the sender is dropped
+/// assert_eq!(LaterStream::new(receiver).collect::<Vec<u32>>(), vec![0, 1, 2]);
+/// # }
+/// ```
+pub struct LaterStream<S> where S: Stream {
+    stream: S,
+    waker: Arc<MainWaker>,
+    ended: bool,
+    peeked: Option<Option<S::Item>>,
+}
+
+impl<S> LaterStream<S> where S: Stream {
+
+    /// Create a new `LaterStream` with the provided stream
+    pub fn new( stream: S ) -> Self {
+        LaterStream {
+            stream: stream,
+            waker: MainWaker::new(),
+            ended: false,
+            peeked: None,
+        }
+    }
+
+    /// Consume self and return the wrapped stream
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+
+    /// Get the next item of the stream, waiting no longer than `timeout` for it
+    ///
+    /// A timeout doesn't lose the item, the next call to `next` or `next_timeout` continues to
+    /// wait for it. `Ok(None)` is returned once the stream has ended.
+    ///
+    /// ```rust
+    /// # #![feature(futures_api)]
+    /// # extern crate alligator;
+    /// # extern crate futures;
+    /// # use alligator::LaterStream;
+    /// # use futures::channel::mpsc;
+    /// # use std::time::Duration;
+    /// # fn main() {
+    /// let (sender, receiver) = mpsc::unbounded();
+    ///
+    /// let mut events = LaterStream::new(receiver);
+    ///
+    /// sender.unbounded_send("connected").unwrap();
+    ///
+    /// assert_eq!(events.next_timeout(Duration::from_millis(10)), Ok(Some("connected")));
+    ///
+    /// // Nothing was sent, so the wait gives up
+    /// assert!(events.next_timeout(Duration::from_millis(10)).is_err());
+    ///
+    /// sender.unbounded_send("closed").unwrap();
+    /// drop(sender);
+    ///
+    /// assert_eq!(events.next_timeout(Duration::from_millis(10)), Ok(Some("closed")));
+    /// assert_eq!(events.next_timeout(Duration::from_millis(10)), Ok(None));
+    /// # }
+    /// ```
+    pub fn next_timeout(&mut self, timeout: Duration) -> Result<Option<S::Item>, TimeoutError> {
+        let deadline = clock::now() + timeout;
+
+        if let Some(item) = self.peeked.take() {
+            return Ok(item);
+        }
+
+        if self.ended {
+            return Ok(None);
+        }
+
+        loop {
+            match unsafe { Pin::new_unchecked(&mut self.stream) }.poll_next(&self.waker.local()) {
+                Poll::Ready(item) => {
+                    self.ended = item.is_none();
+                    break Ok(item)
+                },
+                Poll::Pending => if !self.waker.wait_until(deadline) {
+                    break Err(TimeoutError::new())
+                },
+            }
+        }
+    }
+
+    /// Convert self into an iterator where waiting for any item takes no longer than `timeout`
+    ///
+    /// Items that took too long are replaced with a `TimeoutError`, and iteration can continue
+    /// past the error to keep waiting on the stream.
+    ///
+    /// ```rust
+    /// # #![feature(futures_api)]
+    /// # extern crate alligator;
+    /// # extern crate futures;
+    /// # use alligator::LaterStream;
+    /// # use futures::channel::mpsc;
+    /// # use std::time::Duration;
+    /// # fn main() {
+    /// let (sender, receiver) = mpsc::unbounded();
+    ///
+    /// let mut readings = LaterStream::new(receiver).timeout(Duration::from_millis(10));
+    ///
+    /// sender.unbounded_send(21).unwrap();
+    ///
+    /// assert_eq!(readings.next().unwrap().ok(), Some(21));
+    /// assert!(readings.next().unwrap().is_err());
+    ///
+    /// sender.unbounded_send(22).unwrap();
+    /// drop(sender);
+    ///
+    /// assert_eq!(readings.next().unwrap().ok(), Some(22));
+    /// assert!(readings.next().is_none());
+    /// # }
+    /// ```
+    pub fn timeout(self, timeout: Duration) -> Timeout<S> {
+        Timeout {
+            stream: self,
+            timeout: timeout,
+        }
+    }
+
+    /// Convert self into an iterator of batches of items
+    ///
+    /// Every batch has `size` items except for the last batch which has the items remaining when
+    /// the stream ended.
+    ///
+    /// ```rust
+    /// # #![feature(futures_api)]
+    /// # extern crate alligator;
+    /// # extern crate futures;
+    /// # use alligator::LaterStream;
+    /// # use futures::stream;
+    /// # fn main() {
+    /// let mut rows = LaterStream::new(stream::iter(1..8)).chunks(3);
+    ///
+    /// assert_eq!(rows.next(), Some(vec![1, 2, 3]));
+    /// assert_eq!(rows.next(), Some(vec![4, 5, 6]));
+    /// assert_eq!(rows.next(), Some(vec![7]));
+    /// assert_eq!(rows.next(), None);
+    /// # }
+    /// ```
+    ///
+    /// # Panic
+    /// This panics if `size` is zero
+    pub fn chunks(self, size: usize) -> Chunks<S> {
+        assert!(size != 0, "chunk size must not be zero");
+
+        Chunks {
+            stream: self,
+            size: size,
+        }
+    }
+
+    /// Convert a stream of futures into an iterator over their outputs with up to `n` of the
+    /// futures in flight at once
+    ///
+    /// While the consumer is waiting for the output of the next future, the futures after it are
+    /// taken from the stream (up to `n` futures in total) and polled as well. The outputs are in
+    /// the same order as the stream produced the futures.
+    ///
+    /// ```rust
+    /// # #![feature(futures_api)]
+    /// # extern crate alligator;
+    /// # extern crate futures;
+    /// # use alligator::LaterStream;
+    /// # use futures::{future, stream};
+    /// # use std::cell::Cell;
+    /// # use std::rc::Rc;
+    /// # fn main() {
+    /// let taken = Rc::new(Cell::new(0));
+    /// let counter = taken.clone();
+    ///
+    /// let pages = stream::iter((0..5).map(move |page| {
+    ///     counter.set(counter.get() + 1);
+    ///     future::ready(page * 10)
+    /// }));
+    ///
+    /// let mut fetched = LaterStream::new(pages).buffered(2);
+    ///
+    /// assert_eq!(fetched.next(), Some(0));
+    ///
+    /// // No more than two futures were taken from the stream for the first output
+    /// assert_eq!(taken.get(), 2);
+    ///
+    /// assert_eq!(fetched.collect::<Vec<_>>(), vec![10, 20, 30, 40]);
+    /// # }
+    /// ```
+    ///
+    /// # Panic
+    /// This panics if `n` is zero
+    pub fn buffered<F,O>(self, n: usize) -> Buffered<S,F,O> where S: Stream<Item=F>, F: Future<Output=O> {
+        assert!(n != 0, "the number of buffered futures must not be zero");
+
+        Buffered {
+            stream: self,
+            in_flight: VecDeque::with_capacity(n),
+            size: n,
+        }
+    }
+
+    /// Convert self into an iterator over pairs of items of this stream and `other`
+    ///
+    /// Both streams are polled under the same waker, so a call to `next` waits on whichever stream
+    /// hasn't produced its half of the pair yet. Iteration ends when either stream ends, an item
+    /// of the other stream already taken for the pair is dropped.
+    ///
+    /// ```rust
+    /// # #![feature(futures_api)]
+    /// # extern crate alligator;
+    /// # extern crate futures;
+    /// # use alligator::LaterStream;
+    /// # use futures::stream;
+    /// # fn main() {
+    /// let mut pairs = LaterStream::new(stream::iter(vec![1, 2, 3])).zip(stream::iter(vec!["a", "b"]));
+    ///
+    /// assert_eq!(pairs.next(), Some((1, "a")));
+    /// assert_eq!(pairs.next(), Some((2, "b")));
+    ///
+    /// // The second stream ended, so the third item of the first has no pair
+    /// assert_eq!(pairs.next(), None);
+    /// assert_eq!(pairs.next(), None);
+    /// # }
+    /// ```
+    pub fn zip<S2>(self, other: S2) -> Zip<S,S2> where S2: Stream {
+        Zip {
+            first: self,
+            second: other,
+            items: (None, None),
+        }
+    }
+}
+
+impl<S> Iterator for LaterStream<S> where S: Stream {
+    type Item = S::Item;
+
+    fn next(&mut self) -> Option<S::Item> {
+        if let Some(item) = self.peeked.take() {
+            return item;
+        }
+
+        if self.ended {
+            return None;
+        }
+
+        loop {
+            match unsafe { Pin::new_unchecked(&mut self.stream) }.poll_next(&self.waker.local()) {
+                Poll::Ready(item) => {
+                    self.ended = item.is_none();
+                    break item
+                },
+                Poll::Pending => wait_blocking(&self.waker),
+            }
+        }
+    }
+}
+
+/// A `LaterStream` is ready once its next item is available or the stream has ended
+#[cfg(feature = "combinators")]
+impl<S> Waitable for LaterStream<S> where S: Stream {
+    fn poll_ready(&mut self, lw: &LocalWaker) -> Poll<()> {
+        if self.peeked.is_some() || self.ended {
+            return Poll::Ready(());
+        }
+
+        match unsafe { Pin::new_unchecked(&mut self.stream) }.poll_next(lw) {
+            Poll::Ready(item) => {
+                self.ended = item.is_none();
+                self.peeked = Some(item);
+
+                Poll::Ready(())
+            },
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+/// An iterator over batches of the items of a stream
+///
+/// This is created by [`LaterStream::chunks`](./struct.LaterStream.html#method.chunks).
+pub struct Chunks<S> where S: Stream {
+    stream: LaterStream<S>,
+    size: usize,
+}
+
+impl<S> Iterator for Chunks<S> where S: Stream {
+    type Item = Vec<S::Item>;
+
+    /// Block until either there are enough items for a full batch or the stream ends
+    fn next(&mut self) -> Option<Vec<S::Item>> {
+        let chunk: Vec<S::Item> = self.stream.by_ref().take(self.size).collect();
+
+        if chunk.is_empty() {
+            None
+        } else {
+            Some(chunk)
+        }
+    }
+}
+
+/// A future taken from the stream of a `Buffered`, or its output once it has completed
+enum InFlight<F,O> {
+    Fut(Pin<Box<F>>),
+    Val(O),
+}
+
+/// An iterator over the outputs of a stream of futures with multiple futures in flight
+///
+/// This is created by [`LaterStream::buffered`](./struct.LaterStream.html#method.buffered).
+pub struct Buffered<S,F,O> where S: Stream<Item=F>, F: Future<Output=O> {
+    stream: LaterStream<S>,
+    in_flight: VecDeque<InFlight<F,O>>,
+    size: usize,
+}
+
+impl<S,F,O> Buffered<S,F,O> where S: Stream<Item=F>, F: Future<Output=O> {
+
+    /// Take futures from the stream until there are `size` in flight or the stream has nothing
+    /// ready
+    fn fill(&mut self) {
+        let lw = self.stream.waker.local();
+
+        if let Some(Some(future)) = self.stream.peeked.take() {
+            self.in_flight.push_back(InFlight::Fut(Box::pinned(future)));
+        }
+
+        while !self.stream.ended && self.in_flight.len() < self.size {
+            match unsafe { Pin::new_unchecked(&mut self.stream.stream) }.poll_next(&lw) {
+                Poll::Ready(Some(future)) => self.in_flight.push_back(InFlight::Fut(Box::pinned(future))),
+                Poll::Ready(None) => self.stream.ended = true,
+                Poll::Pending => break,
+            }
+        }
+    }
+
+    /// Poll every future in flight that hasn't completed
+    fn poll_in_flight(&mut self) {
+        let lw = self.stream.waker.local();
+
+        for in_flight in self.in_flight.iter_mut() {
+            let val = match *in_flight {
+                InFlight::Fut(ref mut future) => match future.as_mut().poll(&lw) {
+                    Poll::Ready(val) => val,
+                    Poll::Pending => continue,
+                },
+                InFlight::Val(_) => continue,
+            };
+
+            *in_flight = InFlight::Val(val);
+        }
+    }
+}
+
+impl<S,F,O> Iterator for Buffered<S,F,O> where S: Stream<Item=F>, F: Future<Output=O> {
+    type Item = O;
+
+    /// Block until the output of the next future of the stream is available
+    fn next(&mut self) -> Option<O> {
+        loop {
+            self.fill();
+            self.poll_in_flight();
+
+            match self.in_flight.pop_front() {
+                Some(InFlight::Val(val)) => break Some(val),
+                Some(future) => self.in_flight.push_front(future),
+                None if self.stream.ended => break None,
+                None => (),
+            }
+
+            wait_blocking(&self.stream.waker);
+        }
+    }
+}
+
+/// An iterator over pairs of items of two streams
+///
+/// This is created by [`LaterStream::zip`](./struct.LaterStream.html#method.zip).
+pub struct Zip<S,S2> where S: Stream, S2: Stream {
+    first: LaterStream<S>,
+    second: S2,
+    items: (Option<S::Item>, Option<S2::Item>),
+}
+
+impl<S,S2> Iterator for Zip<S,S2> where S: Stream, S2: Stream {
+    type Item = (S::Item, S2::Item);
+
+    /// Block until both streams have produced their next item, or either stream ends
+    fn next(&mut self) -> Option<(S::Item, S2::Item)> {
+        if self.first.ended {
+            return None;
+        }
+
+        let lw = self.first.waker.local();
+
+        loop {
+            if self.items.0.is_none() {
+                if let Some(item) = self.first.peeked.take() {
+                    match item {
+                        Some(item) => self.items.0 = Some(item),
+                        None => break,
+                    }
+                }
+            }
+
+            if self.items.0.is_none() {
+                match unsafe { Pin::new_unchecked(&mut self.first.stream) }.poll_next(&lw) {
+                    Poll::Ready(Some(item)) => self.items.0 = Some(item),
+                    Poll::Ready(None) => break,
+                    Poll::Pending => (),
+                }
+            }
+
+            if self.items.1.is_none() {
+                match unsafe { Pin::new_unchecked(&mut self.second) }.poll_next(&lw) {
+                    Poll::Ready(Some(item)) => self.items.1 = Some(item),
+                    Poll::Ready(None) => break,
+                    Poll::Pending => (),
+                }
+            }
+
+            if let (Some(_), Some(_)) = self.items {
+                let (first, second) = ::std::mem::replace(&mut self.items, (None, None));
+
+                return first.and_then(|first| second.map(|second| (first, second)));
+            }
+
+            wait_blocking(&self.first.waker);
+        }
+
+        self.first.ended = true;
+        self.items = (None, None);
+
+        None
+    }
+}
+
+/// An iterator over the items of a stream with a time limit on waiting for each item
+///
+/// This is created by [`LaterStream::timeout`](./struct.LaterStream.html#method.timeout).
+pub struct Timeout<S> where S: Stream {
+    stream: LaterStream<S>,
+    timeout: Duration,
+}
+
+impl<S> Iterator for Timeout<S> where S: Stream {
+    type Item = Result<S::Item, TimeoutError>;
+
+    fn next(&mut self) -> Option<Result<S::Item, TimeoutError>> {
+        match self.stream.next_timeout(self.timeout) {
+            Ok(Some(item)) => Some(Ok(item)),
+            Ok(None) => None,
+            Err(e) => Some(Err(e)),
+        }
+    }
+}
+
+/// A future for collecting the items of a stream
+///
+/// The output is every item of the stream, or if a limit was set, the items up to the limit. Once
+/// the output is returned the stream is never polled again, further polls return `Poll::Pending`.
+pub struct Collect<S> where S: Stream {
+    stream: S,
+    items: Vec<S::Item>,
+    limit: Option<usize>,
+    done: bool,
+}
+
+impl<S> Collect<S> where S: Stream {
+
+    /// Create a new `Collect` for all the items of `stream`
+    pub fn new( stream: S ) -> Self {
+        Self::with_capacity(stream, 0)
+    }
+
+    /// Create a new `Collect` with space reserved for `capacity` items
+    pub fn with_capacity( stream: S, capacity: usize ) -> Self {
+        Collect {
+            stream: stream,
+            items: Vec::with_capacity(capacity),
+            limit: None,
+            done: false,
+        }
+    }
+
+    /// Stop collecting once there are `limit` items
+    ///
+    /// The stream is not polled after the limit is reached.
+    ///
+    /// ```rust
+    /// # #![feature(futures_api)]
+    /// # extern crate alligator;
+    /// # extern crate futures;
+    /// # use alligator::Later;
+    /// # use alligator::stream::Collect;
+    /// # use futures::stream;
+    /// # fn main() {
+    /// let first = Later::new(Collect::with_capacity(stream::iter(0..), 3).limit(3));
+    ///
+    /// assert_eq!(*first, vec![0, 1, 2]);
+    /// # }
+    /// ```
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    fn is_full(&self) -> bool {
+        self.limit.map_or(false, |limit| self.items.len() >= limit)
+    }
+}
+
+impl<S> Future for Collect<S> where S: Stream {
+    type Output = Vec<S::Item>;
+
+    fn poll(self: Pin<&mut Self>, lw: &LocalWaker) -> Poll<Vec<S::Item>> {
+        let this = unsafe { Pin::get_mut_unchecked(self) };
+
+        if this.done {
+            return Poll::Pending;
+        }
+
+        loop {
+            if this.is_full() {
+                break;
+            }
+
+            match unsafe { Pin::new_unchecked(&mut this.stream) }.poll_next(lw) {
+                Poll::Ready(Some(item)) => this.items.push(item),
+                Poll::Ready(None) => break,
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+
+        this.done = true;
+
+        Poll::Ready(::std::mem::replace(&mut this.items, Vec::new()))
+    }
+}
+
+/// Create a `Later` for all the items of a stream
+///
+/// Use [`Collect`](./struct.Collect.html) with `Later::new` to set a capacity or a limit on the
+/// number of items collected.
+///
+/// ```rust
+/// # #![feature(futures_api)]
+/// # extern crate alligator;
+/// # extern crate futures;
+/// # use alligator::collect_later;
+/// # use futures::stream;
+/// # fn main() {
+/// let rows = collect_later(stream::iter(vec!["id", "name", "email"]));
+///
+/// assert_eq!(rows.into_inner(), vec!["id", "name", "email"]);
+/// # }
+/// ```
+pub fn collect_later<S>( stream: S ) -> Later<Collect<S>> where S: Stream {
+    Later::new(Collect::new(stream))
+}
@@ -0,0 +1,429 @@
+//! Synchronization primitives for values initialized by futures
+
+use std::cell::UnsafeCell;
+use std::future::Future;
+use std::ops::Deref;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Poll, LocalWaker, Waker};
+
+use super::{Later, Poller};
+use super::map::Source;
+
+enum State {
+    Uninit,
+    Running(Vec<Waker>),
+    Complete,
+}
+
+/// A cell that is initialized once by the output of a future
+///
+/// The cell can be initialized from an asynchronous context with
+/// [`get_or_init_async`](#method.get_or_init_async) or from a synchronous context with
+/// [`get_or_init_blocking`](#method.get_or_init_blocking). When multiple callers try to initialize
+/// the cell at the same time only the future of one caller is polled and every other caller waits
+/// for its output. If that caller stops (its future is dropped or panics) before the cell is
+/// initialized, one of the waiting callers takes over with its own future.
+///
+/// # Example
+/// ```rust
+/// # #![feature(futures_api)]
+/// # #![feature(async_await)]
+/// # extern crate alligator;
+/// # use alligator::sync::OnceCell;
+/// # struct ConnectionPool;
+/// # async fn connect() -> ConnectionPool { ConnectionPool }
+/// # fn main() {
+/// let pool: OnceCell<ConnectionPool> = OnceCell::new();
+///
+/// // Only the first call connects
+/// let first = pool.get_or_init_blocking(connect());
+/// let second = pool.get_or_init_blocking(connect());
+/// # assert!(first as *const _ == second as *const _);
+/// # }
+/// ```
+pub struct OnceCell<T> {
+    state: Mutex<State>,
+    value: UnsafeCell<Option<T>>,
+}
+
+unsafe impl<T> Send for OnceCell<T> where T: Send {}
+unsafe impl<T> Sync for OnceCell<T> where T: Send + Sync {}
+
+impl<T> OnceCell<T> {
+
+    /// Create a new `OnceCell` that isn't initialized
+    pub fn new() -> Self {
+        OnceCell {
+            state: Mutex::new(State::Uninit),
+            value: UnsafeCell::new(None),
+        }
+    }
+
+    /// Get the value if the cell is initialized
+    pub fn get(&self) -> Option<&T> {
+        match *self.state.lock().unwrap() {
+            State::Complete => unsafe { &*self.value.get() }.as_ref(),
+            _ => None,
+        }
+    }
+
+    /// Get a future for the value, initializing the cell with the output of `init` if it isn't
+    /// initialized
+    ///
+    /// `init` is only polled if no other caller is initializing the cell, it is dropped without
+    /// being polled if the cell is initialized by another caller.
+    pub fn get_or_init_async<F>(&self, init: F) -> GetOrInit<T,F> where F: Future<Output=T> {
+        GetOrInit {
+            cell: self,
+            init: Some(init),
+            running: false,
+        }
+    }
+
+    /// Get the value, initializing the cell with the output of `init` if it isn't initialized
+    ///
+    /// This blocks the current thread until the cell is initialized, either by `init` or by the
+    /// future of another caller.
+    pub fn get_or_init_blocking<F>(&self, init: F) -> &T where F: Future<Output=T> {
+        Poller::new(self.get_or_init_async(init)).poll_to_completion()
+    }
+
+    /// Consume the cell and return the value if it was initialized
+    pub fn into_inner(self) -> Option<T> {
+        self.value.into_inner()
+    }
+
+    /// Wake every caller waiting on the initialization and set the state
+    fn finish(&self, state: State) {
+        let previous = ::std::mem::replace(&mut *self.state.lock().unwrap(), state);
+
+        if let State::Running(waiters) = previous {
+            for waker in waiters {
+                waker.wake()
+            }
+        }
+    }
+}
+
+impl<T> Default for OnceCell<T> {
+    fn default() -> Self {
+        OnceCell::new()
+    }
+}
+
+/// A future for the value of a `OnceCell`
+///
+/// This is created by [`OnceCell::get_or_init_async`](./struct.OnceCell.html#method.get_or_init_async).
+pub struct GetOrInit<'a,T: 'a,F> {
+    cell: &'a OnceCell<T>,
+    init: Option<F>,
+    running: bool,
+}
+
+impl<'a,T,F> Future for GetOrInit<'a,T,F> where F: Future<Output=T> {
+    type Output = &'a T;
+
+    fn poll(self: Pin<&mut Self>, lw: &LocalWaker) -> Poll<&'a T> {
+        let this = unsafe { Pin::get_mut_unchecked(self) };
+
+        let cell = this.cell;
+
+        if !this.running {
+            let mut state = cell.state.lock().unwrap();
+
+            match *state {
+                State::Complete => return Poll::Ready(
+                    unsafe { &*cell.value.get() }.as_ref().expect("Report a bug if you get this panic")
+                ),
+                State::Running(ref mut waiters) => {
+                    waiters.push(lw.as_waker().clone());
+
+                    return Poll::Pending;
+                },
+                State::Uninit => {
+                    *state = State::Running(Vec::new());
+
+                    this.running = true;
+                },
+            }
+        }
+
+        let val = match this.init {
+            Some(ref mut init) => match unsafe { Pin::new_unchecked(init) }.poll(lw) {
+                Poll::Ready(val) => val,
+                Poll::Pending => return Poll::Pending,
+            },
+            None => panic!("GetOrInit polled after completion"),
+        };
+
+        this.init = None;
+
+        unsafe { *cell.value.get() = Some(val) };
+
+        this.running = false;
+
+        cell.finish(State::Complete);
+
+        Poll::Ready(unsafe { &*cell.value.get() }.as_ref().expect("Report a bug if you get this panic"))
+    }
+}
+
+/// Hands the initialization over to a waiting caller if this caller stops before it's done
+impl<'a,T,F> Drop for GetOrInit<'a,T,F> {
+    fn drop(&mut self) {
+        if self.running {
+            self.cell.finish(State::Uninit);
+        }
+    }
+}
+
+/// The future of a `SyncLater` claimed by the caller that initializes the cell
+///
+/// The future is only taken out of the `SyncLater` when the claim is first polled, which only the
+/// caller that won the initialization of the cell does. A caller that takes over the
+/// initialization after the first one panicked finds the future gone and panics as well.
+struct Claim<'a,T: 'a,O: 'a> {
+    future: &'a Mutex<Option<Source<T,O>>>,
+    source: Option<Source<T,O>>,
+}
+
+impl<'a,T,O> Future for Claim<'a,T,O> where T: Future<Output=O> {
+    type Output = O;
+
+    fn poll(self: Pin<&mut Self>, lw: &LocalWaker) -> Poll<O> {
+        let this = unsafe { Pin::get_mut_unchecked(self) };
+
+        if this.source.is_none() {
+            this.source = this.future.lock().unwrap().take();
+        }
+
+        match this.source {
+            Some(ref mut source) => unsafe { Pin::new_unchecked(source) }.poll(lw),
+            None => panic!("the future of a SyncLater panicked while it was polled"),
+        }
+    }
+}
+
+/// A `Later` that can be shared between threads by reference
+///
+/// `Later` can't be shared between threads because it waits for its future through a `Cell`.
+/// `SyncLater` keeps its future behind a lock instead, so it can be put in shared state such as
+/// an `Arc`. The first thread to access the output polls the future to completion while any other
+/// thread that accesses it at the same time waits for it. Unlike `Later::new` the future isn't
+/// polled until the output is first accessed.
+///
+/// # Example
+/// ```rust
+/// # #![feature(futures_api)]
+/// # #![feature(async_await)]
+/// # extern crate alligator;
+/// # use alligator::sync::SyncLater;
+/// # use std::sync::Arc;
+/// # async fn load_routes() -> Vec<&'static str> { vec!["/", "/health"] }
+/// # fn main() {
+/// let routes = Arc::new(SyncLater::new(load_routes()));
+///
+/// let handler = {
+///     let routes = routes.clone();
+///
+///     ::std::thread::spawn(move || routes.len())
+/// };
+///
+/// assert_eq!(handler.join().unwrap(), routes.len());
+/// # }
+/// ```
+pub struct SyncLater<T> where T: Future {
+    future: Mutex<Option<Source<T,T::Output>>>,
+    cell: OnceCell<T::Output>,
+}
+
+impl<T,O> SyncLater<T> where T: Future<Output=O> {
+
+    /// Create a new `SyncLater` of `future`
+    pub fn new(future: T) -> Self {
+        SyncLater::from_source(Source::Fut(future))
+    }
+
+    fn from_source(source: Source<T,O>) -> Self {
+        SyncLater {
+            future: Mutex::new(Some(source)),
+            cell: OnceCell::new(),
+        }
+    }
+
+    /// Get a reference to the output, waiting for it if it isn't available
+    ///
+    /// Any number of threads can force the `SyncLater` for the first time at once, the future is
+    /// polled by exactly one of them.
+    ///
+    /// ```rust
+    /// # #![feature(futures_api)]
+    /// # #![feature(async_await)]
+    /// # extern crate alligator;
+    /// # use alligator::sync::SyncLater;
+    /// # use std::sync::{Arc, Barrier};
+    /// # async fn load(n: u32) -> u32 { n }
+    /// # fn main() {
+    /// for round in 0..100 {
+    ///     let value = Arc::new(SyncLater::new(load(round)));
+    ///     let start = Arc::new(Barrier::new(8));
+    ///
+    ///     let threads: Vec<_> = (0..8).map(|_| {
+    ///         let value = value.clone();
+    ///         let start = start.clone();
+    ///
+    ///         ::std::thread::spawn(move || {
+    ///             start.wait();
+    ///
+    ///             *value.force()
+    ///         })
+    ///     }).collect();
+    ///
+    ///     for thread in threads {
+    ///         assert_eq!(thread.join().unwrap(), round);
+    ///     }
+    /// }
+    /// # }
+    /// ```
+    ///
+    /// # Panic
+    /// This panics if the future panicked while it was polled by any thread
+    pub fn force(&self) -> &O {
+        match self.cell.get() {
+            Some(val) => val,
+            None => self.cell.get_or_init_blocking(Claim { future: &self.future, source: None }),
+        }
+    }
+
+    /// Get a reference to the output if it is available, without waiting
+    pub fn try_ref(&self) -> Option<&O> {
+        self.cell.get()
+    }
+
+    /// Get a clone of the output, waiting for it if it isn't available
+    ///
+    /// # Panic
+    /// This panics if the future panicked while it was polled by any thread
+    pub fn get(&self) -> O where O: Clone {
+        self.force().clone()
+    }
+
+    /// Consume self and return the output, waiting for it if it isn't available
+    ///
+    /// # Panic
+    /// This panics if the future panicked while it was polled
+    pub fn into_inner(self) -> O {
+        self.force();
+
+        self.cell.into_inner().expect("Report a bug if you get this panic")
+    }
+}
+
+/// Dereference the output, waiting for it if it isn't available
+impl<T,O> Deref for SyncLater<T> where T: Future<Output=O> {
+    type Target = O;
+
+    fn deref(&self) -> &O {
+        self.force()
+    }
+}
+
+impl<T> From<Later<T>> for SyncLater<T> where T: Future {
+    fn from(later: Later<T>) -> Self {
+        SyncLater::from_source(later.into_source())
+    }
+}
+
+/// A handle to the output of a future that is shared by all of its clones
+///
+/// Cloning a `SharedLater` is cheap, every clone refers to the same future. The first clone to
+/// access the output polls the future to completion while any other clone that accesses it at the
+/// same time (from another thread) waits for it. The future is only ever polled by one thread at a
+/// time and never after it completes.
+///
+/// # Example
+/// ```rust
+/// # #![feature(futures_api)]
+/// # #![feature(async_await)]
+/// # #[macro_use] extern crate alligator;
+/// # async fn fetch_schema() -> String { String::from("schema") }
+/// # fn main() {
+/// let schema = l!{ fetch_schema() }.shared();
+///
+/// let workers: Vec<_> = (0..4).map(|_| {
+///     let schema = schema.clone();
+///
+///     ::std::thread::spawn(move || schema.len())
+/// }).collect();
+///
+/// for worker in workers {
+///     assert_eq!(worker.join().unwrap(), 6);
+/// }
+/// # }
+/// ```
+pub struct SharedLater<T> where T: Future {
+    shared: Arc<SyncLater<T>>,
+}
+
+impl<T,O> SharedLater<T> where T: Future<Output=O> {
+
+    /// Create a new `SharedLater` of `future`
+    ///
+    /// Unlike `Later::new` the future isn't polled until the output is first accessed.
+    pub fn new(future: T) -> Self {
+        SharedLater::from(SyncLater::new(future))
+    }
+
+    /// Get a reference to the output, waiting for it if it isn't available
+    ///
+    /// # Panic
+    /// This panics if the future panicked while it was polled by any of the clones
+    pub fn force(&self) -> &O {
+        self.shared.force()
+    }
+
+    /// Get a reference to the output if it is available, without waiting
+    pub fn try_ref(&self) -> Option<&O> {
+        self.shared.try_ref()
+    }
+
+    /// Get a clone of the output, waiting for it if it isn't available
+    ///
+    /// # Panic
+    /// This panics if the future panicked while it was polled by any of the clones
+    pub fn get(&self) -> O where O: Clone {
+        self.shared.get()
+    }
+}
+
+impl<T> Clone for SharedLater<T> where T: Future {
+    fn clone(&self) -> Self {
+        SharedLater {
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+/// Dereference the output, waiting for it if it isn't available
+impl<T,O> Deref for SharedLater<T> where T: Future<Output=O> {
+    type Target = O;
+
+    fn deref(&self) -> &O {
+        self.force()
+    }
+}
+
+impl<T> From<SyncLater<T>> for SharedLater<T> where T: Future {
+    fn from(later: SyncLater<T>) -> Self {
+        SharedLater {
+            shared: Arc::new(later),
+        }
+    }
+}
+
+impl<T> From<Later<T>> for SharedLater<T> where T: Future {
+    fn from(later: Later<T>) -> Self {
+        SharedLater::from(SyncLater::from(later))
+    }
+}
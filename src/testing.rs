@@ -0,0 +1,87 @@
+//! Utilities for testing code that uses alligator
+//!
+//! # Virtual time
+//! [`FakeTime`](./struct.FakeTime.html) replaces the clock that alligator uses for deadlines and
+//! timeouts with a virtual clock that only moves when it is advanced. A test of a timeout can then
+//! advance the clock past the timeout instantly, and the result is always the same.
+
+use std::sync::{Mutex, MutexGuard, Once, ONCE_INIT};
+use std::time::{Duration, Instant};
+
+use super::clock;
+
+/// A lock so that only one `FakeTime` exists at a time
+fn lock() -> &'static Mutex<()> {
+    static INIT: Once = ONCE_INIT;
+    static mut LOCK: *const Mutex<()> = 0 as *const _;
+
+    unsafe {
+        INIT.call_once(|| LOCK = Box::into_raw(Box::new(Mutex::new(()))));
+
+        &*LOCK
+    }
+}
+
+/// A virtual clock
+///
+/// While a `FakeTime` exists, every deadline and timeout within alligator is measured against the
+/// virtual clock instead of the real clock. The virtual clock starts at the time `FakeTime` is
+/// created and only moves forward by calls to [`advance`](#method.advance). Dropping the
+/// `FakeTime` goes back to the real clock.
+///
+/// The virtual clock is for the whole process, so creating a `FakeTime` waits for any other
+/// `FakeTime` to be dropped. This keeps tests that run in parallel from seeing each other's
+/// virtual time.
+///
+/// # Example
+/// ```rust
+/// # extern crate alligator;
+/// # use std::time::Duration;
+/// # use alligator::testing::FakeTime;
+/// # fn main() {
+/// let time = FakeTime::new();
+///
+/// let start = time.now();
+///
+/// time.advance(Duration::from_secs(60));
+///
+/// assert_eq!(time.now() - start, Duration::from_secs(60));
+/// # }
+/// ```
+pub struct FakeTime {
+    _lock: MutexGuard<'static, ()>,
+}
+
+impl FakeTime {
+
+    /// Replace the clock with a virtual clock starting at the current time
+    pub fn new() -> Self {
+        let lock = match lock().lock() {
+            Ok(lock) => lock,
+            // A test that panicked while holding the lock doesn't matter to this test
+            Err(poisoned) => poisoned.into_inner(),
+        };
+
+        clock::fake::set(Some(Instant::now()));
+
+        FakeTime { _lock: lock }
+    }
+
+    /// Get the current time of the virtual clock
+    pub fn now(&self) -> Instant {
+        clock::now()
+    }
+
+    /// Move the virtual clock forward
+    ///
+    /// Anything waiting on a deadline is woken to check the new time.
+    pub fn advance(&self, duration: Duration) {
+        clock::fake::set(Some(clock::now() + duration));
+    }
+}
+
+impl Drop for FakeTime {
+    fn drop(&mut self) {
+        clock::fake::set(None);
+    }
+}
@@ -0,0 +1,546 @@
+//! Timers for futures
+//!
+//! Every timer is kept by a single timer thread, which is started when the first timer is set. The
+//! thread sleeps until the earliest timer is due and wakes the future waiting on it, so any
+//! number of [`sleep`](./fn.sleep.html)s only ever take up the one thread.
+//!
+//! Timers are measured by the same clock as the deadlines of `Later`, so with the `testing`
+//! feature they follow the virtual clock of `testing::FakeTime` while it's in use.
+
+use std::cmp::Ordering as CmpOrdering;
+use std::collections::BinaryHeap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Condvar, Mutex, Once, ONCE_INIT};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::task::{Poll, LocalWaker, Waker};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use super::{clock, limit};
+use super::error::Elapsed;
+#[cfg(feature = "combinators")]
+use super::Waitable;
+
+/// The waker of a timer, `None` once the future of the timer is dropped
+type TimerWaker = Arc<Mutex<Option<Waker>>>;
+
+/// A timer within the queue of the timer thread
+///
+/// The ordering is reversed so that the `BinaryHeap` pops the earliest deadline first. Timers with
+/// the same deadline are popped in the order they were set.
+struct Timer {
+    deadline: Instant,
+    sequence: usize,
+    waker: TimerWaker,
+}
+
+impl PartialEq for Timer {
+    fn eq(&self, other: &Self) -> bool {
+        self.deadline == other.deadline && self.sequence == other.sequence
+    }
+}
+
+impl Eq for Timer {}
+
+impl PartialOrd for Timer {
+    fn partial_cmp(&self, other: &Self) -> Option<CmpOrdering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Timer {
+    fn cmp(&self, other: &Self) -> CmpOrdering {
+        other.deadline.cmp(&self.deadline).then_with(|| other.sequence.cmp(&self.sequence))
+    }
+}
+
+/// The queue of timers shared with the timer thread
+struct Timers {
+    queue: Mutex<BinaryHeap<Timer>>,
+    cvar: Condvar,
+    next: AtomicUsize,
+    /// The number of timers in the queue whose future was dropped before they were due
+    cancelled: AtomicUsize,
+}
+
+impl Timers {
+    fn insert(&self, deadline: Instant, waker: TimerWaker) {
+        self.queue.lock().unwrap().push( Timer {
+            deadline: deadline,
+            sequence: self.next.fetch_add(1, Ordering::Relaxed),
+            waker: waker,
+        });
+
+        // The new timer may be due before the one the thread is sleeping until
+        self.cvar.notify_one()
+    }
+
+    fn run(&self) {
+        let mut queue = self.queue.lock().unwrap();
+
+        loop {
+            let now = Instant::now();
+
+            let mut due = Vec::new();
+
+            while queue.peek().map_or(false, |timer| timer.deadline <= now) {
+                due.extend(queue.pop().and_then(|timer| timer.waker.lock().unwrap().take()));
+            }
+
+            if !due.is_empty() {
+                drop(queue);
+
+                for waker in due {
+                    waker.wake()
+                }
+
+                queue = self.queue.lock().unwrap();
+
+                continue;
+            }
+
+            // The timers of dropped futures are purged once they are at least half of the queue, so
+            // the time taken by a purge is in proportion to the timers it removes
+            if self.cancelled.load(Ordering::Relaxed) * 2 > queue.len() {
+                let timers = ::std::mem::replace(&mut *queue, BinaryHeap::new()).into_vec();
+
+                *queue = timers.into_iter().filter(|timer| timer.waker.lock().unwrap().is_some()).collect();
+
+                self.cancelled.store(0, Ordering::Relaxed);
+            }
+
+            queue = match queue.peek().map(|timer| timer.deadline) {
+                Some(deadline) => self.cvar.wait_timeout(queue, deadline - now).unwrap().0,
+                None => self.cvar.wait(queue).unwrap(),
+            };
+        }
+    }
+}
+
+/// Get the timers, starting the timer thread on the first call
+fn timers() -> &'static Timers {
+    static INIT: Once = ONCE_INIT;
+    static mut TIMERS: *const Timers = 0 as *const _;
+
+    unsafe {
+        INIT.call_once(|| {
+            TIMERS = Box::into_raw(Box::new( Timers {
+                queue: Mutex::new(BinaryHeap::new()),
+                cvar: Condvar::new(),
+                next: AtomicUsize::new(0),
+                cancelled: AtomicUsize::new(0),
+            }));
+
+            let timers: &'static Timers = &*TIMERS;
+
+            thread::Builder::new()
+                .name(String::from("alligator-timer"))
+                .spawn(move || {
+                    limit::forbid_blocking("the alligator timer thread");
+
+                    timers.run()
+                })
+                .expect("failed to spawn the alligator timer thread");
+        });
+
+        &*TIMERS
+    }
+}
+
+/// A future that completes at a deadline
+///
+/// This is created by [`sleep`](./fn.sleep.html) or [`sleep_until`](./fn.sleep_until.html). The
+/// timer is only set the first time the future is polled before the deadline.
+pub struct Sleep {
+    deadline: Instant,
+    waker: Option<TimerWaker>,
+    /// The waker registered with the virtual clock by the last poll
+    #[cfg(feature = "testing")]
+    fake_waiter: Option<clock::fake::Waiter>,
+}
+
+impl Sleep {
+
+    /// The time when the future completes
+    pub fn deadline(&self) -> Instant {
+        self.deadline
+    }
+
+    /// Check if the deadline has passed
+    pub fn is_elapsed(&self) -> bool {
+        clock::now() >= self.deadline
+    }
+
+    /// Stop the timer from waking anything
+    ///
+    /// The timer is purged from the queue the next time the timer thread wakes up, which it does
+    /// whenever a timer is set or is due.
+    fn cancel_timer(&mut self) {
+        if let Some(timer) = self.waker.take() {
+            if timer.lock().unwrap().take().is_some() {
+                timers().cancelled.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+
+        #[cfg(feature = "testing")]
+        {
+            self.fake_waiter = None;
+        }
+    }
+}
+
+impl Future for Sleep {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, lw: &LocalWaker) -> Poll<()> {
+        if self.is_elapsed() {
+            return Poll::Ready(());
+        }
+
+        // The virtual clock is advanced by hand, so the timer thread would never see the deadline
+        // pass. Registered before reading the clock again so that an advance after the read still
+        // wakes the future.
+        #[cfg(feature = "testing")]
+        {
+            if clock::is_fake() {
+                self.fake_waiter = Some(clock::fake::wake_on_change(lw.as_waker().clone()));
+
+                return if self.is_elapsed() { Poll::Ready(()) } else { Poll::Pending };
+            }
+        }
+
+        let waker = lw.as_waker().clone();
+
+        if let Some(ref timer) = self.waker {
+            *timer.lock().unwrap() = Some(waker);
+
+            return Poll::Pending;
+        }
+
+        let timer = Arc::new(Mutex::new(Some(waker)));
+
+        timers().insert(self.deadline, timer.clone());
+
+        self.waker = Some(timer);
+
+        Poll::Pending
+    }
+}
+
+/// Stops the timer from waking anything once the future is dropped
+impl Drop for Sleep {
+    fn drop(&mut self) {
+        self.cancel_timer()
+    }
+}
+
+/// A `Sleep` is ready once its deadline has passed
+#[cfg(feature = "combinators")]
+impl Waitable for Sleep {
+    fn poll_ready(&mut self, lw: &LocalWaker) -> Poll<()> {
+        unsafe { Pin::new_unchecked(self) }.poll(lw)
+    }
+
+    fn deadline(&self) -> Option<Instant> {
+        Some(self.deadline)
+    }
+}
+
+/// Create a future that completes once `duration` has passed
+///
+/// # Example
+/// ```rust
+/// # #![feature(futures_api)]
+/// # #![feature(async_await)]
+/// # #![feature(await_macro)]
+/// # #[macro_use] extern crate alligator;
+/// # use std::time::{Duration, Instant};
+/// async fn retry_later(attempt: u32) -> u32 {
+///     await!(alligator::time::sleep(Duration::from_millis(10 * attempt as u64)));
+///
+///     attempt + 1
+/// }
+///
+/// # fn main() {
+/// let start = Instant::now();
+///
+/// assert_eq!(*l!{ retry_later(2) }, 3);
+/// assert!(start.elapsed() >= Duration::from_millis(20));
+/// # }
+/// ```
+pub fn sleep(duration: Duration) -> Sleep {
+    sleep_until(clock::now() + duration)
+}
+
+/// Create a future that completes at `deadline`
+///
+/// The future is immediately ready if the deadline has already passed.
+pub fn sleep_until(deadline: Instant) -> Sleep {
+    Sleep {
+        deadline: deadline,
+        waker: None,
+        #[cfg(feature = "testing")]
+        fake_waiter: None,
+    }
+}
+
+/// A future that gives up on its future at a deadline
+///
+/// This is created by [`timeout`](./fn.timeout.html) or [`timeout_at`](./fn.timeout_at.html).
+pub struct Timeout<T> {
+    future: T,
+    sleep: Sleep,
+}
+
+impl<T> Timeout<T> {
+
+    /// Get the future back, dropping the timer
+    pub fn into_inner(self) -> T {
+        self.future
+    }
+}
+
+impl<T,O> Future for Timeout<T> where T: Future<Output=O> {
+    type Output = Result<O, Elapsed>;
+
+    fn poll(self: Pin<&mut Self>, lw: &LocalWaker) -> Poll<Result<O, Elapsed>> {
+        let this = unsafe { Pin::get_mut_unchecked(self) };
+
+        // The future is always polled first, so a future that completes at the deadline isn't
+        // thrown away
+        if let Poll::Ready(val) = unsafe { Pin::new_unchecked(&mut this.future) }.poll(lw) {
+            // The timer isn't needed anymore, even if the `Timeout` lives on
+            this.sleep.cancel_timer();
+
+            return Poll::Ready(Ok(val));
+        }
+
+        match unsafe { Pin::new_unchecked(&mut this.sleep) }.poll(lw) {
+            Poll::Ready(()) => Poll::Ready(Err(Elapsed::new())),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+/// Limit the time `future` has to complete
+///
+/// The output is `Err(Elapsed)` if `future` didn't complete within `duration`, in which case it
+/// isn't polled again. This bounds the time of a future from the future side, so it can be wrapped
+/// in a `Later` or be part of a bigger future like any other.
+///
+/// # Example
+/// ```rust
+/// # #![feature(futures_api)]
+/// # extern crate alligator;
+/// # use alligator::{Later, Pending};
+/// # use alligator::time::timeout;
+/// # use std::time::Duration;
+/// # fn main() {
+/// let reply = Later::new(timeout(Duration::from_millis(10), Pending::<u32>::new()));
+///
+/// assert!(reply.is_err());
+/// # }
+/// ```
+pub fn timeout<T,O>(duration: Duration, future: T) -> Timeout<T> where T: Future<Output=O> {
+    Timeout {
+        future: future,
+        sleep: sleep(duration),
+    }
+}
+
+/// Limit `future` to complete before `deadline`
+///
+/// This is the same as [`timeout`](./fn.timeout.html) with a point in time instead of a duration.
+pub fn timeout_at<T,O>(deadline: Instant, future: T) -> Timeout<T> where T: Future<Output=O> {
+    Timeout {
+        future: future,
+        sleep: sleep_until(deadline),
+    }
+}
+
+/// A timer that ticks once every period
+///
+/// This is created by [`interval`](./fn.interval.html) or [`interval_at`](./fn.interval_at.html).
+/// Every tick is at a whole number of periods after the first tick. Ticks that are missed because
+/// the interval wasn't polled in time are skipped, rather than all being returned at once. With
+/// the `stream` feature an `Interval` is also a `Stream` of its ticks.
+pub struct Interval {
+    period: Duration,
+    sleep: Sleep,
+}
+
+impl Interval {
+
+    /// Poll for the next tick, returning the time the tick was due
+    ///
+    /// # Panic
+    /// This panics if the tick after this one is past the latest time an `Instant` can hold
+    pub fn poll_tick(&mut self, lw: &LocalWaker) -> Poll<Instant> {
+        match unsafe { Pin::new_unchecked(&mut self.sleep) }.poll(lw) {
+            Poll::Ready(()) => (),
+            Poll::Pending => return Poll::Pending,
+        }
+
+        let tick = self.sleep.deadline();
+
+        let now = clock::now();
+
+        let mut next = tick.checked_add(self.period)
+            .expect("the next tick of an Interval is past the latest time an Instant can hold");
+
+        if next <= now {
+            // The missed ticks are counted in nanoseconds, as a `Duration` is only multiplied by a
+            // `u32`. If skipping them overflows the next tick is a period from now instead.
+            let period = nanos(self.period);
+
+            let periods = (nanos(now - tick) / period).saturating_add(1);
+
+            next = periods.checked_mul(period)
+                .and_then(|skip| tick.checked_add(Duration::from_nanos(skip)))
+                .or_else(|| now.checked_add(self.period))
+                .unwrap_or(next);
+        }
+
+        self.sleep = sleep_until(next);
+
+        Poll::Ready(tick)
+    }
+
+    /// Get a future for the next tick
+    pub fn tick(&mut self) -> Tick {
+        Tick { interval: self }
+    }
+
+    /// The time between ticks
+    pub fn period(&self) -> Duration {
+        self.period
+    }
+}
+
+#[cfg(feature = "stream")]
+impl ::futures::stream::Stream for Interval {
+    type Item = Instant;
+
+    fn poll_next(self: Pin<&mut Self>, lw: &LocalWaker) -> Poll<Option<Instant>> {
+        unsafe { Pin::get_mut_unchecked(self) }.poll_tick(lw).map(Some)
+    }
+}
+
+/// An `Interval` is ready once its next tick is due
+///
+/// Being ready doesn't take the tick, that's still returned by the next
+/// [`poll_tick`](./struct.Interval.html#method.poll_tick) or [`tick`](./struct.Interval.html#method.tick).
+///
+/// # Example
+/// ```rust
+/// # #![feature(futures_api)]
+/// # extern crate alligator;
+/// # use alligator::{block_on, select, Received};
+/// # use alligator::time::{interval, sleep};
+/// # use std::sync::mpsc::channel;
+/// # use std::time::Duration;
+/// # fn main() {
+/// let (sender, receiver) = channel();
+///
+/// ::std::thread::spawn(move || {
+///     ::std::thread::sleep(Duration::from_millis(50));
+///
+///     sender.send("finished").unwrap();
+/// });
+///
+/// let mut job = Received::new(receiver);
+/// let mut progress = interval(Duration::from_millis(10));
+/// let mut give_up = sleep(Duration::from_secs(10));
+///
+/// let status = loop {
+///     let ready = select(&mut [&mut job, &mut progress, &mut give_up]);
+///
+///     match ready {
+///         0 => break block_on(job),
+///         1 => println!("still waiting at {:?}", block_on(progress.tick())),
+///         _ => break "timed out",
+///     }
+/// };
+///
+/// assert_eq!(status, "finished");
+/// # }
+/// ```
+#[cfg(feature = "combinators")]
+impl Waitable for Interval {
+    fn poll_ready(&mut self, lw: &LocalWaker) -> Poll<()> {
+        self.sleep.poll_ready(lw)
+    }
+
+    fn deadline(&self) -> Option<Instant> {
+        Some(self.sleep.deadline())
+    }
+}
+
+/// Get the whole duration in nanoseconds, saturating at `u64::MAX`
+fn nanos(duration: Duration) -> u64 {
+    duration.as_secs()
+        .saturating_mul(1_000_000_000)
+        .saturating_add(duration.subsec_nanos() as u64)
+}
+
+/// A future for the next tick of an `Interval`
+///
+/// This is created by [`Interval::tick`](./struct.Interval.html#method.tick).
+pub struct Tick<'a> {
+    interval: &'a mut Interval,
+}
+
+impl<'a> Future for Tick<'a> {
+    type Output = Instant;
+
+    fn poll(self: Pin<&mut Self>, lw: &LocalWaker) -> Poll<Instant> {
+        unsafe { Pin::get_mut_unchecked(self) }.interval.poll_tick(lw)
+    }
+}
+
+/// Create an `Interval` whose first tick is one `period` from now
+///
+/// # Panic
+/// This panics if `period` is zero
+///
+/// # Example
+/// ```rust
+/// # #![feature(futures_api)]
+/// # #![feature(async_await)]
+/// # #![feature(await_macro)]
+/// # #[macro_use] extern crate alligator;
+/// # use alligator::time::{interval, Interval};
+/// # use std::time::Duration;
+/// # fn refresh_cache() {}
+/// async fn refresh_every(mut ticks: Interval, times: u32) -> u32 {
+///     for _ in 0..times {
+///         await!(ticks.tick());
+///
+///         refresh_cache();
+///     }
+///
+///     times
+/// }
+///
+/// # fn main() {
+/// let refreshes = l!{ refresh_every(interval(Duration::from_millis(5)), 3) };
+/// # assert_eq!(*refreshes, 3);
+/// # }
+/// ```
+pub fn interval(period: Duration) -> Interval {
+    interval_at(clock::now() + period, period)
+}
+
+/// Create an `Interval` whose first tick is at `start`
+///
+/// # Panic
+/// This panics if `period` is zero
+pub fn interval_at(start: Instant, period: Duration) -> Interval {
+    assert!(period != Duration::from_secs(0), "the period of an interval must not be zero");
+
+    Interval {
+        period: period,
+        sleep: sleep_until(start),
+    }
+}
@@ -0,0 +1,207 @@
+//! Recording of poll and wait timings
+//!
+//! The recorder is off by default. Once it is started with [`start`](./fn.start.html) every poll of
+//! the future of a `Later`, every time a thread waits for a future to wake it, and the completion
+//! of the future is recorded. The recording can be written out in the JSON format of
+//! `chrome://tracing` (also read by most flamegraph viewers) with
+//! [`write_chrome_trace`](./fn.write_chrome_trace.html).
+//!
+//! # Example
+//! ```rust
+//! # #![feature(futures_api)]
+//! # #![feature(async_await)]
+//! # #[macro_use] extern crate alligator;
+//! # async fn get_fut() -> &'static str { "Hello World" }
+//! # fn main() {
+//! alligator::trace::start();
+//!
+//! println!("{}", l!{ get_fut() });
+//!
+//! alligator::trace::stop();
+//!
+//! let mut json = Vec::new();
+//!
+//! alligator::trace::write_chrome_trace(&mut json).unwrap();
+//! # }
+//! ```
+
+use std::io::{self, Write};
+use std::sync::{Mutex, Once, ONCE_INIT};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+static NEXT_THREAD: AtomicUsize = AtomicUsize::new(1);
+
+thread_local! {
+    static THREAD: usize = register_thread();
+}
+
+/// The kind of a recorded event
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Kind {
+    /// A call to `poll` of a future
+    Poll,
+    /// A wait for the future to wake the waiting thread
+    Park,
+    /// The future returned `Poll::Ready`
+    Complete,
+}
+
+impl Kind {
+    fn name(self) -> &'static str {
+        match self {
+            Kind::Poll     => "poll",
+            Kind::Park     => "park",
+            Kind::Complete => "complete",
+        }
+    }
+}
+
+struct Event {
+    kind: Kind,
+    later: usize,
+    thread: usize,
+    start: Instant,
+    duration: Duration,
+}
+
+struct Recording {
+    epoch: Option<Instant>,
+    threads: Vec<(usize, String)>,
+    events: Vec<Event>,
+}
+
+fn recording() -> &'static Mutex<Recording> {
+    static INIT: Once = ONCE_INIT;
+    static mut RECORDING: *const Mutex<Recording> = 0 as *const _;
+
+    unsafe {
+        INIT.call_once(|| {
+            RECORDING = Box::into_raw( Box::new( Mutex::new( Recording {
+                epoch: None,
+                threads: Vec::new(),
+                events: Vec::new(),
+            })));
+        });
+
+        &*RECORDING
+    }
+}
+
+fn register_thread() -> usize {
+    let id = NEXT_THREAD.fetch_add(1, Ordering::Relaxed);
+
+    let name = thread::current().name().map(String::from).unwrap_or_else(|| format!("thread {}", id));
+
+    recording().lock().unwrap().threads.push((id, name));
+
+    id
+}
+
+/// Get the current time if the recorder is started
+pub(crate) fn now() -> Option<Instant> {
+    if ENABLED.load(Ordering::Relaxed) {
+        Some(Instant::now())
+    } else {
+        None
+    }
+}
+
+/// Record an event that began at `start` and ends now
+pub(crate) fn record(kind: Kind, later: usize, start: Instant) {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return;
+    }
+
+    let event = Event {
+        kind: kind,
+        later: later,
+        thread: THREAD.with(|t| *t),
+        start: start,
+        duration: start.elapsed(),
+    };
+
+    recording().lock().unwrap().events.push(event);
+}
+
+/// Start recording
+pub fn start() {
+    let mut recording = recording().lock().unwrap();
+
+    if recording.epoch.is_none() {
+        recording.epoch = Some(Instant::now());
+    }
+
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+/// Stop recording
+///
+/// The events recorded so far are kept until `clear` is called.
+pub fn stop() {
+    ENABLED.store(false, Ordering::Relaxed);
+}
+
+/// Discard all recorded events
+pub fn clear() {
+    let mut recording = recording().lock().unwrap();
+
+    recording.events.clear();
+    recording.epoch = if ENABLED.load(Ordering::Relaxed) { Some(Instant::now()) } else { None };
+}
+
+fn micros(duration: Duration) -> u64 {
+    duration.as_secs() * 1_000_000 + u64::from(duration.subsec_micros())
+}
+
+/// Write the recorded events in the `chrome://tracing` JSON format
+///
+/// Polls and waits are written as complete events with their duration and completions as instant
+/// events. Every event has the id of its `Later` under `args`.
+pub fn write_chrome_trace<W>(mut writer: W) -> io::Result<()> where W: Write {
+    let recording = recording().lock().unwrap();
+
+    let epoch = match recording.epoch {
+        Some(epoch) => epoch,
+        None => return writer.write_all(b"{\"traceEvents\":[]}"),
+    };
+
+    writer.write_all(b"{\"traceEvents\":[")?;
+
+    let mut first = true;
+
+    for &(id, ref name) in recording.threads.iter() {
+        if !first {
+            writer.write_all(b",")?;
+        }
+
+        first = false;
+
+        write!(writer, "{{\"name\":\"thread_name\",\"ph\":\"M\",\"pid\":1,\"tid\":{},\"args\":{{\"name\":\"{}\"}}}}",
+            id, name.replace('\\', "\\\\").replace('"', "\\\""))?;
+    }
+
+    for event in recording.events.iter().filter(|e| e.start >= epoch) {
+        if !first {
+            writer.write_all(b",")?;
+        }
+
+        first = false;
+
+        let ts = micros(event.start - epoch);
+
+        match event.kind {
+            Kind::Complete => write!(writer,
+                "{{\"name\":\"{}\",\"cat\":\"alligator\",\"ph\":\"i\",\"s\":\"t\",\"ts\":{},\"pid\":1,\"tid\":{},\"args\":{{\"later\":{}}}}}",
+                event.kind.name(), ts, event.thread, event.later)?,
+            _ => write!(writer,
+                "{{\"name\":\"{}\",\"cat\":\"alligator\",\"ph\":\"X\",\"ts\":{},\"dur\":{},\"pid\":1,\"tid\":{},\"args\":{{\"later\":{}}}}}",
+                event.kind.name(), ts, micros(event.duration), event.thread, event.later)?,
+        }
+    }
+
+    writer.write_all(b"]}")
+}